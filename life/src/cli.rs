@@ -0,0 +1,333 @@
+use failure::Error;
+use life::simulation::{
+    simple_serialization::{self, Pattern},
+    Grid, Rule, SearchSettings, Soup, SoupSearch, SparseGrid, Symmetry, Topology, Vec2Int, World,
+};
+use rayon::ThreadPoolBuilder;
+
+const USAGE: &str = "USAGE:
+    life run --pattern <file> --generations <n> [--out <file>]
+             [--rule <rule>] [--size <width>x<height>] [--topology <edges>] [--threads <n>]
+                                    Runs a pattern without opening a window. Without --size
+                                    the world is infinite. Without --out, prints statistics.
+                                    Edges are dead, torus, klein, cross or mirror; --wrap is
+                                    the same as --topology torus.
+    life convert <in> <out>         Converts a pattern between formats, by file extension.
+    life stats <file>               Prints a pattern's metadata and population.
+    life search [--soups <n>] [--size <n>] [--density <d>] [--symmetry <None|C2|C4|D8>]
+                [--seed <n>] [--rule <rule>] [--generations <n>] [--out <file>] [--threads <n>]
+                                    Runs random soups until they settle and writes any that
+                                    leave rare objects behind to --out (search_results.jsonl
+                                    by default), one JSON object per line. Without --soups it
+                                    runs until stopped.
+    life help                       Prints this message.
+
+Patterns can be .rle, .cells, .lif, .life or .json files.";
+
+/// Where `search` writes its finds unless told otherwise.
+const SEARCH_RESULTS: &str = "search_results.jsonl";
+
+#[derive(Debug, Fail)]
+pub enum CliError {
+    #[fail(display = "Unknown command `{}`. Try `help`.", _0)]
+    UnknownCommand(String),
+
+    #[fail(display = "Missing {}.", _0)]
+    MissingArgument(&'static str),
+
+    #[fail(display = "Unexpected argument `{}`.", _0)]
+    UnexpectedArgument(String),
+
+    #[fail(display = "Bad value `{}` for {}.", _1, _0)]
+    BadValue(&'static str, String),
+}
+
+/// Runs one of the batch commands, given the arguments after the program name.
+pub fn run(args: &[String]) -> Result<(), Error> {
+    let (command, rest) = args.split_first().ok_or(CliError::MissingArgument("a command"))?;
+    let mut args = Arguments::new(rest);
+
+    match command.as_str() {
+        "run" => run_pattern(&mut args),
+        "convert" => convert(&mut args),
+        "stats" => stats(&mut args),
+        "search" => search(&mut args),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(CliError::UnknownCommand(other.to_owned()).into()),
+    }
+}
+
+fn run_pattern(args: &mut Arguments<'_>) -> Result<(), Error> {
+    let input = args.option("--pattern")?.ok_or(CliError::MissingArgument("--pattern"))?;
+    let generations = args.option("--generations")?.ok_or(CliError::MissingArgument("--generations"))?;
+    let generations: u64 = parse_value("--generations", generations)?;
+    let out = args.option("--out")?;
+    let rule = args.option("--rule")?;
+    let size = args.option("--size")?;
+    let topology = args.option("--topology")?;
+    let wrap = args.flag("--wrap");
+    let threads = args.option("--threads")?;
+    args.finish()?;
+
+    let mut pattern = simple_serialization::load_pattern(input)?;
+    if let Some(rule) = rule {
+        pattern.rule = Some(parse_value("--rule", rule)?);
+    }
+    let rule = pattern.rule.unwrap_or_default();
+
+    let mut world = match size {
+        Some(size) => {
+            let (width, height) = parse_size(size)?;
+            World::Bounded(Grid::new(width, height))
+        }
+        None => World::Infinite(SparseGrid::new()),
+    };
+    world.paste(Vec2Int::ZERO, &pattern.cells);
+
+    let pool = match threads {
+        Some(threads) => {
+            let threads: usize = parse_value("--threads", threads)?;
+            Some(ThreadPoolBuilder::new().num_threads(threads).build()?)
+        }
+        None => None,
+    };
+    let topology = match (topology, wrap) {
+        (Some(topology), _) => parse_value("--topology", topology)?,
+        (None, true) => Topology::Torus,
+        (None, false) => Topology::Dead,
+    };
+    world.jump(&rule, generations, topology, pool.as_ref())?;
+    let live_bounds = world.live_bounds();
+    pattern.rule = Some(rule);
+
+    match out {
+        Some(out) => {
+            let mut result = Pattern::new(world.live_states().1);
+            result.name = pattern.name;
+            result.author = pattern.author;
+            result.comments = pattern.comments;
+            result.rule = pattern.rule;
+
+            simple_serialization::save_pattern(&result, out)?;
+            println!("Ran {} generations and saved the result to {}.", generations, out);
+        }
+        None => {
+            // Read straight from the world, rather than copying every cell of the area the
+            // pattern has spread over into a pattern.
+            let size = live_bounds.map_or(Vec2Int::ZERO, |(min, max)| max - min + Vec2Int::new(1, 1));
+            println!("generations: {}", generations);
+            print_details(&pattern);
+            println!("size: {} x {}", size.x, size.y);
+            println!("population: {}", world.population());
+        }
+    }
+
+    Ok(())
+}
+
+fn convert(args: &mut Arguments<'_>) -> Result<(), Error> {
+    let input = args.positional().ok_or(CliError::MissingArgument("an input file"))?;
+    let output = args.positional().ok_or(CliError::MissingArgument("an output file"))?;
+    args.finish()?;
+
+    let pattern = simple_serialization::load_pattern(input)?;
+    simple_serialization::save_pattern(&pattern, output)?;
+    println!("Converted {} to {}.", input, output);
+
+    Ok(())
+}
+
+fn stats(args: &mut Arguments<'_>) -> Result<(), Error> {
+    let input = args.positional().ok_or(CliError::MissingArgument("a pattern file"))?;
+    args.finish()?;
+
+    let pattern = simple_serialization::load_pattern(input)?;
+    print_stats(&pattern);
+
+    Ok(())
+}
+
+fn search(args: &mut Arguments<'_>) -> Result<(), Error> {
+    let soups = args.option("--soups")?;
+    let size = args.option("--size")?;
+    let density = args.option("--density")?;
+    let symmetry = args.option("--symmetry")?;
+    let seed = args.option("--seed")?;
+    let rule = args.option("--rule")?;
+    let generations = args.option("--generations")?;
+    let out = args.option("--out")?.unwrap_or(SEARCH_RESULTS);
+    let threads = args.option("--threads")?;
+    args.finish()?;
+
+    let mut settings = SearchSettings::default();
+    if let Some(size) = size {
+        settings.soup_size = parse_value("--size", size)?;
+    }
+    if let Some(density) = density {
+        settings.density = parse_value("--density", density)?;
+        if settings.density < 0.0 || settings.density > 1.0 {
+            return Err(CliError::BadValue("--density", density.to_owned()).into());
+        }
+    }
+    if let Some(symmetry) = symmetry {
+        settings.symmetry = parse_value::<Symmetry>("--symmetry", symmetry)?;
+    }
+    if let Some(rule) = rule {
+        settings.rule = parse_value("--rule", rule)?;
+    }
+    if let Some(generations) = generations {
+        settings.max_generations = parse_value("--generations", generations)?;
+    }
+    let soups: Option<u64> = match soups {
+        Some(soups) => Some(parse_value("--soups", soups)?),
+        None => None,
+    };
+    let first_seed = match seed {
+        Some(seed) => parse_value("--seed", seed)?,
+        None => Soup::random_seed(),
+    };
+
+    let mut builder = ThreadPoolBuilder::new();
+    if let Some(threads) = threads {
+        builder = builder.num_threads(parse_value("--threads", threads)?);
+    }
+    let pool = builder.build()?;
+    let batch = pool.current_num_threads() as u64 * 4;
+
+    let mut search = SoupSearch::new(settings, first_seed)?;
+    println!(
+        "Searching {}x{} soups at density {:.2} with {} symmetry under {}, from seed {}.",
+        settings.soup_size, settings.soup_size, settings.density, settings.symmetry, settings.rule, first_seed
+    );
+    loop {
+        let remaining = soups.map(|soups| soups - search.soups_searched());
+        let count = match remaining {
+            Some(0) => break,
+            Some(remaining) => remaining.min(batch),
+            None => batch,
+        };
+
+        let finds = pool.install(|| search.run(count));
+        if !finds.is_empty() {
+            simple_serialization::append_search_finds(&finds, out)?;
+        }
+        for find in &finds {
+            let objects: Vec<String> = find
+                .objects
+                .iter()
+                .map(|entry| format!("{} x{}", entry.name, entry.count))
+                .collect();
+            if find.stabilized {
+                println!("seed {}: {}", find.seed, objects.join(", "));
+            } else {
+                println!("seed {}: still going after {} generations", find.seed, find.generations);
+            }
+        }
+        println!(
+            "{} soups searched, {} finds, next seed {}.",
+            search.soups_searched(),
+            search.finds(),
+            search.next_seed()
+        );
+    }
+
+    println!("Most common objects:");
+    for (name, count) in search.tally().iter().take(10) {
+        println!("    {:>8} {}", count, name);
+    }
+
+    Ok(())
+}
+
+fn print_stats(pattern: &Pattern) {
+    print_details(pattern);
+    println!("size: {} x {}", pattern.width(), pattern.height());
+    println!("population: {}", pattern.alive_cells().count());
+}
+
+/// The name, author and rule.
+fn print_details(pattern: &Pattern) {
+    if let Some(name) = &pattern.name {
+        println!("name: {}", name);
+    }
+    if let Some(author) = &pattern.author {
+        println!("author: {}", author);
+    }
+    match &pattern.rule {
+        Some(rule) => println!("rule: {}", rule),
+        None => println!("rule: {} (default)", Rule::default()),
+    }
+}
+
+fn parse_size(size: &str) -> Result<(usize, usize), Error> {
+    let bad_value = || CliError::BadValue("--size", size.to_owned());
+
+    let mut parts = size.splitn(2, ['x', 'X']);
+    let width = parts.next().and_then(|width| width.trim().parse().ok()).ok_or_else(bad_value)?;
+    let height = parts.next().and_then(|height| height.trim().parse().ok()).ok_or_else(bad_value)?;
+
+    Ok((width, height))
+}
+
+fn parse_value<T: std::str::FromStr>(name: &'static str, value: &str) -> Result<T, Error> {
+    value
+        .parse()
+        .map_err(|_| CliError::BadValue(name, value.to_owned()).into())
+}
+
+/// The arguments after the command. Options and flags are taken out by name, then whatever
+/// is left over is read in order as positional arguments.
+struct Arguments<'a> {
+    args: Vec<&'a str>,
+}
+
+impl<'a> Arguments<'a> {
+    fn new(args: &'a [String]) -> Self {
+        Arguments {
+            args: args.iter().map(String::as_str).collect(),
+        }
+    }
+
+    fn option(&mut self, name: &'static str) -> Result<Option<&'a str>, CliError> {
+        match self.args.iter().position(|arg| *arg == name) {
+            Some(i) => {
+                if i + 1 >= self.args.len() {
+                    return Err(CliError::MissingArgument(name));
+                }
+                let value = self.args.remove(i + 1);
+                self.args.remove(i);
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn flag(&mut self, name: &'static str) -> bool {
+        match self.args.iter().position(|arg| *arg == name) {
+            Some(i) => {
+                self.args.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn positional(&mut self) -> Option<&'a str> {
+        if self.args.is_empty() {
+            None
+        } else {
+            Some(self.args.remove(0))
+        }
+    }
+
+    /// Complains about anything that wasn't used.
+    fn finish(&self) -> Result<(), CliError> {
+        match self.args.first() {
+            Some(arg) => Err(CliError::UnexpectedArgument((*arg).to_owned())),
+            None => Ok(()),
+        }
+    }
+}
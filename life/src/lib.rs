@@ -0,0 +1,27 @@
+//! The Game of Life simulation on its own: boards, rules, stepping, editing and pattern files,
+//! with no window, renderer or audio. The game is built on top of this, and anything else
+//! which wants to run Life boards can use it directly. It's a crate of its own so that depending
+//! on it doesn't pull in any graphics or audio libraries, and builds on headless machines.
+//!
+//! ```no_run
+//! use life::simulation::{simple_serialization, SparseGrid, Topology, Vec2Int, World};
+//!
+//! let pattern = simple_serialization::load_pattern("glider.rle").unwrap();
+//! let mut world = World::Infinite(SparseGrid::new());
+//! world.paste(Vec2Int::ZERO, &pattern.cells);
+//!
+//! let rule = pattern.rule.unwrap_or_default();
+//! world.jump(&rule, 1024, Topology::Dead, None).unwrap();
+//! println!("{} cells alive", world.population());
+//! ```
+#![warn(elided_lifetimes_in_paths)]
+// The `Fail` derive puts its impls inside a `const _` block, which newer compilers warn about.
+#![allow(non_local_definitions)]
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate failure;
+
+pub mod simulation;
+pub mod utilities;
@@ -0,0 +1,25 @@
+//! Batch commands for running, converting and searching patterns without a window, for
+//! scripts and headless machines. `life help` lists them.
+#![warn(elided_lifetimes_in_paths)]
+// The `Fail` derive puts its impls inside a `const _` block, which newer compilers warn about.
+#![allow(non_local_definitions)]
+
+#[macro_use]
+extern crate failure;
+
+mod cli;
+
+use std::{env, process};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args = if args.is_empty() { vec!["help".to_owned()] } else { args };
+
+    if let Err(e) = cli::run(&args) {
+        eprintln!("error: {}", e);
+        for this_cause in e.iter_causes() {
+            eprintln!("caused by: {}", this_cause);
+        }
+        process::exit(1);
+    }
+}
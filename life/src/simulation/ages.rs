@@ -0,0 +1,208 @@
+use super::{State, Vec2Int, World};
+use std::collections::HashMap;
+use std::mem;
+
+/// How long a cell has been in its current state, in generations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Age {
+    /// Alive for this many generations since it was born.
+    Alive(u64),
+    /// Dead for this many generations since it last died.
+    Dead(u64),
+}
+
+/// In a dense age, marks a live cell; the rest of the bits are how long it's been alive.
+const DENSE_ALIVE: u16 = 0x8000;
+/// The oldest a dense age can hold. Older cells stay at this age.
+const DENSE_MAX_AGE: u64 = 0x7ffe;
+
+/// Keeps each cell's age as the world is stepped. Cells which have been dead for longer
+/// than `forget_after` generations are dropped, so on an infinite world this only grows with
+/// the live cells and the ones which died recently. A bounded world's ages are kept densely
+/// instead, one `u16` per cell laid out like its grid, and top out at 32766 generations.
+///
+/// Ages only move on when `update` is called after a step; cells edited in between are
+/// picked up as newborn or freshly dead at the next one.
+#[derive(Debug, Clone)]
+pub struct CellAges {
+    ages: Ages,
+    forget_after: u64,
+}
+
+#[derive(Debug, Clone)]
+enum Ages {
+    /// Every cell of a `width` wide bounded world, row by row. Zero is a cell with no age,
+    /// `DENSE_ALIVE` plus the age a live cell, and one more than the age a dead cell.
+    Dense {
+        width: usize,
+        ages: Vec<u16>,
+    },
+    Sparse(HashMap<Vec2Int, Age>),
+}
+
+impl CellAges {
+    pub fn new(forget_after: u64) -> Self {
+        CellAges {
+            ages: Ages::Sparse(HashMap::new()),
+            forget_after: forget_after.min(DENSE_MAX_AGE),
+        }
+    }
+
+    /// The age of the cell at `pos`, or `None` if it isn't alive and hasn't died recently.
+    pub fn get(&self, pos: Vec2Int) -> Option<Age> {
+        match &self.ages {
+            Ages::Dense { width, ages } => {
+                if pos.x < 0 || pos.y < 0 || pos.x as usize >= *width {
+                    return None;
+                }
+
+                match ages.get(pos.y as usize * width + pos.x as usize).cloned() {
+                    None | Some(0) => None,
+                    Some(age) if age & DENSE_ALIVE != 0 => Some(Age::Alive((age & !DENSE_ALIVE) as u64)),
+                    Some(age) => Some(Age::Dead(age as u64 - 1)),
+                }
+            }
+            Ages::Sparse(ages) => ages.get(&pos).cloned(),
+        }
+    }
+
+    /// Ages every cell by `generations`, which is how far `world` has just been stepped. Over a
+    /// jump of more than one generation, cells alive at both ends count as alive all along.
+    pub fn update(&mut self, world: &World, generations: u64) {
+        match world {
+            World::Bounded(grid) => {
+                let cells = grid.width() * grid.height();
+                let fits = match &self.ages {
+                    Ages::Dense { width, ages } => *width == grid.width() && ages.len() == cells,
+                    Ages::Sparse(_) => false,
+                };
+                if !fits {
+                    self.ages = Ages::Dense {
+                        width: grid.width(),
+                        ages: vec![0; cells],
+                    };
+                }
+
+                let forget_after = self.forget_after;
+                if let Ages::Dense { ages, .. } = &mut self.ages {
+                    for ((_, _, state), age) in grid.iter().zip(ages.iter_mut()) {
+                        *age = Self::next_dense_age(*age, state, generations, forget_after);
+                    }
+                }
+            }
+
+            World::Infinite(_) => {
+                let previous = match &mut self.ages {
+                    Ages::Sparse(ages) => mem::take(ages),
+                    Ages::Dense { .. } => HashMap::new(),
+                };
+                let ages = self.next_sparse_ages(&previous, world, generations);
+                self.ages = Ages::Sparse(ages);
+            }
+        }
+    }
+
+    /// A dense age moved on by `generations`, for a cell which is now `state`.
+    fn next_dense_age(age: u16, state: State, generations: u64, forget_after: u64) -> u16 {
+        let was_alive = age & DENSE_ALIVE != 0;
+        match state {
+            State::Alive if was_alive => {
+                let age = ((age & !DENSE_ALIVE) as u64).saturating_add(generations);
+                DENSE_ALIVE | age.min(DENSE_MAX_AGE) as u16
+            }
+            State::Alive => DENSE_ALIVE,
+            _ if was_alive => 1,
+            _ if age == 0 => 0,
+            _ => {
+                let age = (age as u64 - 1).saturating_add(generations);
+                if age <= forget_after {
+                    age as u16 + 1
+                } else {
+                    0
+                }
+            }
+        }
+    }
+
+    /// The ages of an infinite `world`, moved on by `generations` from `previous`.
+    fn next_sparse_ages(
+        &self,
+        previous: &HashMap<Vec2Int, Age>,
+        world: &World,
+        generations: u64,
+    ) -> HashMap<Vec2Int, Age> {
+        let mut ages = HashMap::with_capacity(previous.len());
+        for (pos, state) in world.iter() {
+            if state == State::Alive {
+                let age = match previous.get(&pos) {
+                    Some(Age::Alive(age)) => Age::Alive(age.saturating_add(generations)),
+                    _ => Age::Alive(0),
+                };
+                ages.insert(pos, age);
+            }
+        }
+
+        for (pos, age) in previous {
+            if ages.contains_key(pos) {
+                continue;
+            }
+
+            let age = match age {
+                Age::Alive(_) => 0,
+                Age::Dead(age) => age.saturating_add(generations),
+            };
+            if age <= self.forget_after {
+                ages.insert(*pos, Age::Dead(age));
+            }
+        }
+        ages
+    }
+
+    /// Forgets every age, for when the world has been swapped out for a different one or
+    /// ages aren't being kept up.
+    pub fn clear(&mut self) {
+        self.ages = Ages::Sparse(HashMap::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{Grid, Rule, Soup, SparseGrid, Symmetry, Topology};
+
+    #[test]
+    fn bounded_ages_match_infinite_ones() {
+        let (width, height) = (60, 50);
+        let soup = Soup::new(7, 0.4, Symmetry::None).generate(20, 15);
+        let mut bounded = World::Bounded(Grid::new(width, height));
+        let mut infinite = World::Infinite(SparseGrid::new());
+        for (x, column) in soup.iter().enumerate() {
+            for (y, state) in column.iter().enumerate() {
+                let pos = Vec2Int::new(x as i32 + 20, y as i32 + 20);
+                bounded.set(pos, *state);
+                infinite.set(pos, *state);
+            }
+        }
+
+        let (mut bounded_ages, mut infinite_ages) = (CellAges::new(5), CellAges::new(5));
+        for generation in 0..30 {
+            // Nothing gets as far as the edges this soon, so both worlds stay the same.
+            let generations = if generation % 3 == 0 { 2 } else { 1 };
+            bounded
+                .jump(&Rule::CONWAY, generations, Topology::Dead, None)
+                .unwrap();
+            infinite
+                .jump(&Rule::CONWAY, generations, Topology::Dead, None)
+                .unwrap();
+            bounded_ages.update(&bounded, generations);
+            infinite_ages.update(&infinite, generations);
+
+            for x in -1..=width as i32 {
+                for y in -1..=height as i32 {
+                    let pos = Vec2Int::new(x, y);
+                    assert_eq!(bounded_ages.get(pos), infinite_ages.get(pos), "{:?}", pos);
+                }
+            }
+        }
+    }
+}
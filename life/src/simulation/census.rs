@@ -0,0 +1,516 @@
+use super::simple_serialization::{self, Format};
+use super::{PeriodDetector, Periodicity, Rule, SparseGrid, State, Topology, Vec2Int, World};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{
+    mpsc::{self, Receiver, TryRecvError},
+    Arc,
+};
+use std::thread;
+
+/// Objects anyone running soups will recognise. Every phase of each is matched, in any
+/// rotation or reflection.
+const CATALOGUE: &[(&str, &str)] = &[
+    // Still lifes
+    ("Block", "2o$2o!"),
+    ("Beehive", "b2o$o2bo$b2o!"),
+    ("Loaf", "b2o$o2bo$bobo$2bo!"),
+    ("Boat", "2o$obo$bo!"),
+    ("Tub", "bo$obo$bo!"),
+    ("Ship", "2o$obo$b2o!"),
+    ("Pond", "b2o$o2bo$o2bo$b2o!"),
+    ("Long Boat", "2o$obo$bobo$2bo!"),
+    ("Barge", "bo$obo$bobo$2bo!"),
+    ("Snake", "2obo$ob2o!"),
+    ("Eater", "2o$obo$2bo$2b2o!"),
+    ("Aircraft Carrier", "2o$o2bo$2b2o!"),
+    ("Mango", "b2o$o2bo$bo2bo$2b2o!"),
+    ("Long Barge", "bo$obo$bobo$2bobo$3bo!"),
+    ("Integral Sign", "2o$obo$2bo$2bobo$3b2o!"),
+    // Oscillators
+    ("Blinker", "3o!"),
+    ("Toad", "b3o$3o!"),
+    ("Beacon", "2o$2o$2b2o$2b2o!"),
+    (
+        "Pulsar",
+        "2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    ),
+    ("Pentadecathlon", "2bo4bo$2ob4ob2o$2bo4bo!"),
+    // Spaceships
+    ("Glider", "bo$2bo$3o!"),
+    ("Lightweight Spaceship", "bo2bo$o4b$o3bo$4o!"),
+    ("Middleweight Spaceship", "3bo$bo3bo$o$o4bo$5o!"),
+    ("Heavyweight Spaceship", "3b2o$bo4bo$o$o5bo$6o!"),
+];
+
+/// How far an object is run on its own to work out what it is, if it isn't in the catalogue.
+const MAX_ISOLATED_GENERATIONS: u64 = 256;
+
+/// Cells within this many cells of each other are tried as one object, since some
+/// oscillators and spaceships fall into pieces in some phases.
+const MERGE_DISTANCE: i32 = 2;
+
+type Shape = Vec<(i32, i32)>;
+type Transform = fn((i32, i32)) -> (i32, i32);
+
+/// One kind of object found on the board, and how many of it there were.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CensusEntry {
+    pub name: String,
+    /// What it does, such as `still life` or `period 2 oscillator`.
+    pub kind: String,
+    /// How many cells it has, in the phase it was found in.
+    pub cells: usize,
+    pub count: usize,
+}
+
+/// Every object on a board, most common first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Census {
+    pub entries: Vec<CensusEntry>,
+}
+
+/// Every phase of every object in `CATALOGUE`, ready to look shapes up in. Building it
+/// runs each object through its period, so it's worth keeping around when taking many
+/// censuses.
+#[derive(Debug, Clone)]
+pub struct Catalogue {
+    rule: Rule,
+    objects: HashMap<Shape, CensusEntry>,
+}
+
+impl Catalogue {
+    /// The catalogue for `rule`, which is empty for anything but Conway's rule.
+    pub fn new(rule: &Rule) -> Self {
+        Catalogue {
+            rule: *rule,
+            objects: if *rule == Rule::CONWAY {
+                build_catalogue(rule)
+            } else {
+                HashMap::new()
+            },
+        }
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    fn get(&self, shape: &[(i32, i32)]) -> Option<&CensusEntry> {
+        self.objects.get(shape)
+    }
+}
+
+impl Census {
+    /// Splits the world's live cells into separate objects and identifies each one. This is
+    /// meant for boards which have settled down; anything still interacting comes out as
+    /// an unknown object. Catalogue names are only given under Conway's rule.
+    ///
+    /// This builds the catalogue afresh, so use `take_with` when taking more than one.
+    pub fn take(world: &World, rule: &Rule) -> Census {
+        Census::take_with(world, &Catalogue::new(rule))
+    }
+
+    /// The same as `take`, with a catalogue built beforehand. The world is assumed to follow
+    /// the catalogue's rule.
+    pub fn take_with(world: &World, catalogue: &Catalogue) -> Census {
+        let rule = &catalogue.rule;
+        let alive: HashSet<(i32, i32)> = world
+            .iter()
+            .filter(|(_, state)| *state == State::Alive)
+            .map(|(pos, _)| (pos.x, pos.y))
+            .collect();
+
+        // Catalogue objects are counted by name, so all their phases count together.
+        // Anything else is counted by its shape.
+        let mut counts: HashMap<String, CensusEntry> = HashMap::new();
+        let mut count = |key: String, entry: &CensusEntry| {
+            counts
+                .entry(key)
+                .or_insert_with(|| CensusEntry {
+                    count: 0,
+                    ..entry.clone()
+                })
+                .count += 1;
+        };
+
+        // Nearby cells are tried together first, since some objects, like a pulsar, are
+        // made of pieces which don't touch. Failing that, each touching piece is tried
+        // alone, and whatever is left is an object of its own.
+        for group in clusters(&alive, MERGE_DISTANCE) {
+            if let Some(entry) = catalogue.get(&canonical(&group)) {
+                count(entry.name.clone(), entry);
+                continue;
+            }
+
+            let mut unidentified: HashSet<(i32, i32)> = HashSet::new();
+            for piece in clusters(&group.into_iter().collect(), 1) {
+                match catalogue.get(&canonical(&piece)) {
+                    Some(entry) => count(entry.name.clone(), entry),
+                    None => unidentified.extend(piece),
+                }
+            }
+
+            for object in clusters(&unidentified, MERGE_DISTANCE) {
+                let shape = canonical(&object);
+                match catalogue.get(&shape) {
+                    Some(entry) => count(entry.name.clone(), entry),
+                    None => {
+                        let entry = CensusEntry {
+                            name: format!("Unnamed {}-cell object", object.len()),
+                            kind: isolated_periodicity(&object, rule).map_or_else(
+                                || "unknown".to_owned(),
+                                |periodicity| periodicity.to_string(),
+                            ),
+                            cells: object.len(),
+                            count: 0,
+                        };
+                        count(format!("{:?}", shape), &entry);
+                    }
+                }
+            }
+        }
+
+        let mut entries: Vec<CensusEntry> = counts.values().cloned().collect();
+        // Different unnamed objects can share a name, so the kind settles the order too, and
+        // the same board always gives the same census.
+        entries.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.kind.cmp(&b.kind))
+        });
+        Census { entries }
+    }
+
+    pub fn total_objects(&self) -> usize {
+        self.entries.iter().map(|entry| entry.count).sum()
+    }
+}
+
+/// A census being taken on its own thread, of a copy of the world, so that big boards and
+/// unknown objects don't hold anything up. The catalogue comes back with it, ready for
+/// the next one.
+#[derive(Debug)]
+pub struct BackgroundCensus {
+    result: Receiver<(Census, Arc<Catalogue>)>,
+}
+
+impl BackgroundCensus {
+    /// Takes a census of `world` under `rule`, using `catalogue` if it's for that rule and
+    /// building a new one otherwise.
+    pub fn start(world: World, rule: Rule, catalogue: Option<Arc<Catalogue>>) -> Self {
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            let catalogue = match catalogue {
+                Some(catalogue) if catalogue.rule == rule => catalogue,
+                _ => Arc::new(Catalogue::new(&rule)),
+            };
+            let census = Census::take_with(&world, &catalogue);
+            // Nobody is waiting any more if this fails, so there's nothing to do.
+            let _ = sender.send((census, catalogue));
+        });
+        BackgroundCensus { result }
+    }
+
+    /// The census and the catalogue it used, once it's done. `TryRecvError::Empty` means it
+    /// isn't done yet, and `TryRecvError::Disconnected` that the thread ended without one.
+    pub fn poll(&self) -> Result<(Census, Arc<Catalogue>), TryRecvError> {
+        self.result.try_recv()
+    }
+}
+
+/// Every phase of every catalogue object, by shape.
+fn build_catalogue(rule: &Rule) -> HashMap<Shape, CensusEntry> {
+    let mut catalogue = HashMap::new();
+    for (name, rle) in CATALOGUE {
+        // The reader takes the size from the header, but grows to fit the cells anyway.
+        let pattern = match simple_serialization::parse(&format!("x = 0, y = 0\n{}", rle), Format::Rle) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                error!("Couldn't read the {} in the census catalogue => {}", name, e);
+                continue;
+            }
+        };
+        let cells: Vec<(i32, i32)> = pattern.alive_cells().map(|(x, y)| (x as i32, y as i32)).collect();
+
+        let periodicity = match isolated_periodicity(&cells, rule) {
+            Some(periodicity) => periodicity,
+            None => continue,
+        };
+        let entry = CensusEntry {
+            name: (*name).to_owned(),
+            kind: periodicity.to_string(),
+            cells: cells.len(),
+            count: 0,
+        };
+
+        let mut world = World::Infinite(SparseGrid::new());
+        for (x, y) in &cells {
+            world.set(Vec2Int::new(*x, *y), State::Alive);
+        }
+        for _ in 0..periodicity.period() {
+            catalogue.insert(canonical(&alive_cells(&world)), entry.clone());
+            if world.step(rule, Topology::Dead, None).is_err() {
+                break;
+            }
+        }
+    }
+    catalogue
+}
+
+/// Runs `cells` on an empty board to see whether they repeat. Rules which can't run on an
+/// empty board leave nothing to go on.
+fn isolated_periodicity(cells: &[(i32, i32)], rule: &Rule) -> Option<Periodicity> {
+    let mut world = World::Infinite(SparseGrid::new());
+    for (x, y) in cells {
+        world.set(Vec2Int::new(*x, *y), State::Alive);
+    }
+
+    let mut detector = PeriodDetector::new(MAX_ISOLATED_GENERATIONS as usize);
+    detector.observe(0, &world);
+    for generation in 1..=MAX_ISOLATED_GENERATIONS {
+        world.step(rule, Topology::Dead, None).ok()?;
+        if let Some(periodicity) = detector.observe(generation, &world) {
+            return match periodicity {
+                Periodicity::Extinct => None,
+                periodicity => Some(periodicity),
+            };
+        }
+    }
+    None
+}
+
+fn alive_cells(world: &World) -> Vec<(i32, i32)> {
+    world
+        .iter()
+        .filter(|(_, state)| *state == State::Alive)
+        .map(|(pos, _)| (pos.x, pos.y))
+        .collect()
+}
+
+/// Groups cells so that any two within `distance` of each other, counting diagonals,
+/// end up in the same group.
+fn clusters(cells: &HashSet<(i32, i32)>, distance: i32) -> Vec<Vec<(i32, i32)>> {
+    let mut visited: HashSet<(i32, i32)> = HashSet::new();
+    let mut clusters = Vec::new();
+
+    for &start in cells {
+        if !visited.insert(start) {
+            continue;
+        }
+
+        let mut cluster = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        while let Some((x, y)) = queue.pop_front() {
+            cluster.push((x, y));
+            for dx in -distance..=distance {
+                for dy in -distance..=distance {
+                    let neighbor = (x + dx, y + dy);
+                    if cells.contains(&neighbor) && visited.insert(neighbor) {
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+        clusters.push(cluster);
+    }
+
+    clusters
+}
+
+/// The same shape whichever way round it is: the smallest of its eight rotations and
+/// reflections, moved to the origin.
+fn canonical(cells: &[(i32, i32)]) -> Shape {
+    let transforms: [Transform; 8] = [
+        |(x, y)| (x, y),
+        |(x, y)| (-y, x),
+        |(x, y)| (-x, -y),
+        |(x, y)| (y, -x),
+        |(x, y)| (-x, y),
+        |(x, y)| (y, x),
+        |(x, y)| (x, -y),
+        |(x, y)| (-y, -x),
+    ];
+
+    transforms
+        .iter()
+        .map(|transform| {
+            let mut shape: Shape = cells.iter().map(|&cell| transform(cell)).collect();
+            let min_x = shape.iter().map(|(x, _)| *x).min().unwrap_or(0);
+            let min_y = shape.iter().map(|(_, y)| *y).min().unwrap_or(0);
+            for (x, y) in shape.iter_mut() {
+                *x -= min_x;
+                *y -= min_y;
+            }
+            shape.sort();
+            shape
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells_of(rle: &str) -> Vec<(i32, i32)> {
+        let pattern = simple_serialization::parse(&format!("x = 0, y = 0\n{}", rle), Format::Rle).unwrap();
+        pattern.alive_cells().map(|(x, y)| (x as i32, y as i32)).collect()
+    }
+
+    /// The live cells of a catalogue object, looked up by name.
+    fn object(name: &str) -> Vec<(i32, i32)> {
+        let (_, rle) = CATALOGUE.iter().find(|(object, _)| *object == name).unwrap();
+        cells_of(rle)
+    }
+
+    /// An infinite world with each group of cells moved over by its offset.
+    fn world_of(objects: &[(&Shape, (i32, i32))]) -> World {
+        let mut world = World::Infinite(SparseGrid::new());
+        for (cells, (dx, dy)) in objects {
+            for (x, y) in cells.iter() {
+                world.set(Vec2Int::new(x + dx, y + dy), State::Alive);
+            }
+        }
+        world
+    }
+
+    fn counts(census: &Census) -> Vec<(&str, usize)> {
+        census
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.count))
+            .collect()
+    }
+
+    #[test]
+    fn finds_separate_objects() {
+        let catalogue = Catalogue::new(&Rule::CONWAY);
+        let (block, blinker, glider) = (object("Block"), object("Blinker"), object("Glider"));
+        let mut world = world_of(&[(&block, (0, 0)), (&blinker, (10, 0)), (&glider, (0, 10))]);
+
+        // The glider and blinker are matched in every phase, with the objects kept far
+        // enough apart not to meet.
+        for _ in 0..4 {
+            let census = Census::take_with(&world, &catalogue);
+            assert_eq!(counts(&census), vec![("Blinker", 1), ("Block", 1), ("Glider", 1)]);
+            assert_eq!(census.total_objects(), 3);
+            world.step(&Rule::CONWAY, Topology::Dead, None).unwrap();
+        }
+
+        let census = Census::take_with(&world, &catalogue);
+        let kinds: Vec<&str> = census.entries.iter().map(|entry| entry.kind.as_str()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "period 2 oscillator",
+                "still life",
+                "c/4 diagonal spaceship, period 4"
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_objects_of_the_same_kind_together() {
+        let block = object("Block");
+        let world = world_of(&[(&block, (0, 0)), (&block, (10, 0)), (&block, (20, 0))]);
+
+        let census = Census::take(&world, &Rule::CONWAY);
+        assert_eq!(counts(&census), vec![("Block", 3)]);
+        assert_eq!(census.entries[0].cells, 4);
+    }
+
+    #[test]
+    fn finds_a_pulsar_in_every_phase() {
+        let catalogue = Catalogue::new(&Rule::CONWAY);
+        let pulsar = object("Pulsar");
+        let mut world = world_of(&[(&pulsar, (0, 0))]);
+
+        // Its four quarters never touch, so they only count as one pulsar when tried together.
+        for _ in 0..3 {
+            let census = Census::take_with(&world, &catalogue);
+            assert_eq!(counts(&census), vec![("Pulsar", 1)]);
+            assert_eq!(census.entries[0].kind, "period 3 oscillator");
+            world.step(&Rule::CONWAY, Topology::Dead, None).unwrap();
+        }
+    }
+
+    #[test]
+    fn finds_still_lifes_whichever_way_round_they_are() {
+        let catalogue = Catalogue::new(&Rule::CONWAY);
+        let transforms: [Transform; 8] = [
+            |(x, y)| (x, y),
+            |(x, y)| (-y, x),
+            |(x, y)| (-x, -y),
+            |(x, y)| (y, -x),
+            |(x, y)| (-x, y),
+            |(x, y)| (y, x),
+            |(x, y)| (x, -y),
+            |(x, y)| (-y, -x),
+        ];
+
+        let still_lifes = CATALOGUE.iter().take_while(|(name, _)| *name != "Blinker");
+        for (name, _) in still_lifes {
+            let cells = object(name);
+            for transform in &transforms {
+                let turned: Vec<(i32, i32)> = cells.iter().map(|&cell| transform(cell)).collect();
+                let census = Census::take_with(&world_of(&[(&turned, (50, 50))]), &catalogue);
+                assert_eq!(counts(&census), vec![(*name, 1)]);
+                assert_eq!(census.entries[0].kind, "still life");
+            }
+        }
+    }
+
+    #[test]
+    fn works_out_what_unknown_objects_do() {
+        // A hat isn't in the catalogue, and the pair of cells dies out.
+        let hat = cells_of("2bo$bobo$bobo$2ob2o!");
+        let pair = cells_of("2o!");
+        let world = world_of(&[(&hat, (0, 0)), (&pair, (20, 0))]);
+
+        let census = Census::take(&world, &Rule::CONWAY);
+        assert_eq!(
+            counts(&census),
+            vec![("Unnamed 2-cell object", 1), ("Unnamed 9-cell object", 1)]
+        );
+        assert_eq!(census.entries[0].kind, "unknown");
+        assert_eq!(census.entries[1].kind, "still life");
+    }
+
+    #[test]
+    fn only_names_objects_under_conways_rule() {
+        let world = world_of(&[(&object("Block"), (0, 0))]);
+        let highlife: Rule = "B36/S23".parse().unwrap();
+
+        let census = Census::take(&world, &highlife);
+        assert_eq!(counts(&census), vec![("Unnamed 4-cell object", 1)]);
+        assert_eq!(census.entries[0].kind, "still life");
+    }
+
+    #[test]
+    fn background_censuses_hand_their_catalogue_back() {
+        let world = world_of(&[(&object("Block"), (0, 0))]);
+        let census = BackgroundCensus::start(world.clone(), Rule::CONWAY, None);
+        let (first, catalogue) = loop {
+            match census.poll() {
+                Ok(result) => break result,
+                Err(TryRecvError::Empty) => thread::yield_now(),
+                Err(TryRecvError::Disconnected) => panic!("The census thread ended without a census."),
+            }
+        };
+        assert_eq!(counts(&first), vec![("Block", 1)]);
+        assert_eq!(catalogue.rule(), Rule::CONWAY);
+
+        let census = BackgroundCensus::start(world, Rule::CONWAY, Some(catalogue.clone()));
+        let (second, reused) = loop {
+            if let Ok(result) = census.poll() {
+                break result;
+            }
+            thread::yield_now();
+        };
+        assert_eq!(second, first);
+        assert!(Arc::ptr_eq(&reused, &catalogue));
+    }
+}
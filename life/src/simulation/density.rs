@@ -0,0 +1,138 @@
+use super::{State, Vec2Int, World};
+use std::collections::HashMap;
+
+/// Live-cell counts over square tiles, halving the detail at each level. Level `n` splits the
+/// world into tiles `2^n` cells across, keyed by their position divided by that, and only keeps
+/// tiles with something alive in them.
+#[derive(Debug, Clone, Default)]
+pub struct DensityPyramid {
+    /// `levels[n - 1]` holds level `n`; level 0 would just be the cells themselves.
+    levels: Vec<HashMap<Vec2Int, u32>>,
+}
+
+impl DensityPyramid {
+    /// Counts the live cells of `world` into every level from 1 up to `max_level`.
+    pub fn new(world: &World, max_level: u32) -> Self {
+        let mut levels: Vec<HashMap<Vec2Int, u32>> = Vec::with_capacity(max_level as usize);
+        if max_level == 0 {
+            return DensityPyramid { levels };
+        }
+
+        let mut first = HashMap::new();
+        for (pos, state) in world.iter() {
+            if state == State::Alive {
+                *first.entry(Self::parent(pos)).or_insert(0) += 1;
+            }
+        }
+        levels.push(first);
+
+        for _ in 1..max_level {
+            let mut next = HashMap::new();
+            for (tile, count) in &levels[levels.len() - 1] {
+                *next.entry(Self::parent(*tile)).or_insert(0) += count;
+            }
+            levels.push(next);
+        }
+
+        DensityPyramid { levels }
+    }
+
+    /// The highest level counted.
+    pub fn max_level(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    /// How many cells across a tile on `level` is.
+    pub fn tile_size(level: u32) -> i32 {
+        1 << level
+    }
+
+    /// The tile on `level` holding the cell at `pos`.
+    pub fn tile_of(pos: Vec2Int, level: u32) -> Vec2Int {
+        Vec2Int::new(pos.x >> level, pos.y >> level)
+    }
+
+    /// How many live cells are in `tile` on `level`. Level 0 isn't counted, so it's always empty.
+    pub fn count(&self, level: u32, tile: Vec2Int) -> u32 {
+        self.level(level).and_then(|tiles| tiles.get(&tile)).cloned().unwrap_or(0)
+    }
+
+    /// The share of `tile` on `level` that's alive, from 0 to 1.
+    pub fn density(&self, level: u32, tile: Vec2Int) -> f32 {
+        let area = Self::tile_size(level) as f32 * Self::tile_size(level) as f32;
+        self.count(level, tile) as f32 / area
+    }
+
+    /// Every tile on `level` with something alive in it, with its live-cell count.
+    pub fn tiles(&self, level: u32) -> impl Iterator<Item = (Vec2Int, u32)> + '_ {
+        self.level(level)
+            .into_iter()
+            .flat_map(|tiles| tiles.iter().map(|(tile, count)| (*tile, *count)))
+    }
+
+    fn level(&self, level: u32) -> Option<&HashMap<Vec2Int, u32>> {
+        if level == 0 {
+            return None;
+        }
+        self.levels.get(level as usize - 1)
+    }
+
+    fn parent(pos: Vec2Int) -> Vec2Int {
+        Self::tile_of(pos, 1)
+    }
+}
+
+/// Holds on to a `DensityPyramid` between frames, only counting it again once the world has
+/// changed or a higher level is wanted.
+#[derive(Debug, Clone, Default)]
+pub struct DensityCache {
+    pyramid: DensityPyramid,
+    /// The `World::version` the pyramid was counted from.
+    version: Option<u64>,
+}
+
+impl DensityCache {
+    pub fn new() -> Self {
+        DensityCache::default()
+    }
+
+    /// The pyramid for `world` as it is now, counted up to at least `max_level`.
+    pub fn get(&mut self, world: &World, max_level: u32) -> &DensityPyramid {
+        if self.version != Some(world.version()) || self.pyramid.max_level() < max_level {
+            self.pyramid = DensityPyramid::new(world, max_level);
+            self.version = Some(world.version());
+        }
+        &self.pyramid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{Grid, Rule, SparseGrid, Topology};
+
+    #[test]
+    fn the_cache_recounts_once_the_world_changes() {
+        for mut world in [
+            World::Bounded(Grid::new(16, 16)),
+            World::Infinite(SparseGrid::new()),
+        ] {
+            let mut cache = DensityCache::new();
+            world.set(Vec2Int::new(2, 2), State::Alive);
+            assert_eq!(cache.get(&world, 2).count(2, Vec2Int::ZERO), 1);
+
+            // A copy holds the same cells, so it can share the counts.
+            let counted = cache.version;
+            assert_eq!(cache.get(&world.clone(), 1).count(2, Vec2Int::ZERO), 1);
+            assert_eq!(cache.version, counted);
+
+            world.set(Vec2Int::new(3, 2), State::Alive);
+            world.set(Vec2Int::new(1, 2), State::Alive);
+            assert_eq!(cache.get(&world, 2).count(2, Vec2Int::ZERO), 3);
+
+            world.step(&Rule::CONWAY, Topology::Dead, None).unwrap();
+            assert_eq!(cache.get(&world, 2).count(1, Vec2Int::new(1, 0)), 1);
+            assert_eq!(cache.get(&world, 4).max_level(), 4);
+        }
+    }
+}
@@ -0,0 +1,595 @@
+use super::{world::fresh_version, Rule, State, Topology, Vec2Int};
+use rayon::{prelude::*, ThreadPool};
+use std::mem;
+
+const WORD_BITS: usize = 64;
+const BANDS_PER_THREAD: usize = 4;
+const MIN_ROWS_PER_BAND: usize = 8;
+
+/// The game world, packed one bit per cell.
+///
+/// Each row is stored as `words_per_row` machine words, so the neighbor counting in `step`
+/// can work on 64 cells at a time. We keep two buffers of live cells and swap them every
+/// generation, plus a third plane remembering which cells have ever been alive so we can
+/// still tell `State::Dead` apart from `State::Unborn`.
+#[derive(Debug, Clone)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    current: Vec<u64>,
+    next: Vec<u64>,
+    touched: Vec<u64>,
+    /// See `World::version`.
+    version: u64,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize) -> Self {
+        let words_per_row = width.div_ceil(WORD_BITS);
+        let words = words_per_row * height;
+
+        Grid {
+            width,
+            height,
+            words_per_row,
+            current: vec![0; words],
+            next: vec![0; words],
+            touched: vec![0; words],
+            version: fresh_version(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height
+    }
+
+    pub fn is_alive(&self, x: usize, y: usize) -> bool {
+        let (word, bit) = self.index(x, y);
+        self.current[word] & bit != 0
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> State {
+        let (word, bit) = self.index(x, y);
+        if self.current[word] & bit != 0 {
+            State::Alive
+        } else if self.touched[word] & bit != 0 {
+            State::Dead
+        } else {
+            State::Unborn
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, state: State) {
+        let (word, bit) = self.index(x, y);
+        self.version = fresh_version();
+        match state {
+            State::Alive => {
+                self.current[word] |= bit;
+                self.touched[word] |= bit;
+            }
+
+            State::Dead => {
+                self.current[word] &= !bit;
+                self.touched[word] |= bit;
+            }
+
+            State::Unborn => {
+                self.current[word] &= !bit;
+                self.touched[word] &= !bit;
+            }
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.current.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn iter(&self) -> GridIter<'_> {
+        GridIter { grid: self, index: 0 }
+    }
+
+    /// Every cell whose state differs from the same cell in `other`, which must be the same size.
+    pub fn changed_cells(&self, other: &Grid) -> Vec<(usize, usize)> {
+        debug_assert!(self.width == other.width && self.height == other.height);
+
+        let mut changed = Vec::new();
+        for (i, (current, touched)) in self.current.iter().zip(self.touched.iter()).enumerate() {
+            let mut diff = (current ^ other.current[i]) | (touched ^ other.touched[i]);
+            while diff != 0 {
+                let bit = diff.trailing_zeros() as usize;
+                diff &= diff - 1;
+
+                let y = i / self.words_per_row;
+                let x = (i % self.words_per_row) * WORD_BITS + bit;
+                changed.push((x, y));
+            }
+        }
+        changed
+    }
+
+    /// Roughly how many bytes the grid's cells take up.
+    pub fn memory_size(&self) -> usize {
+        (self.current.len() + self.next.len() + self.touched.len()) * mem::size_of::<u64>()
+    }
+
+    /// Advances the world by one generation. With a thread pool, the rows are split into
+    /// bands which are worked on in parallel; the result is identical either way.
+    pub fn step(&mut self, rule: &Rule, topology: Topology, pool: Option<&ThreadPool>) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+
+        self.compute_next(rule, topology, pool);
+        self.swap_in_next();
+    }
+
+    /// Steps like `step`, pushing every cell the generation changes onto `changes` as its
+    /// position and its states before and after. The changes fall straight out of the two
+    /// buffers, so this costs far less than comparing against a copy of the grid.
+    pub fn step_with_changes(
+        &mut self,
+        rule: &Rule,
+        topology: Topology,
+        pool: Option<&ThreadPool>,
+        changes: &mut Vec<(Vec2Int, State, State)>,
+    ) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+
+        self.compute_next(rule, topology, pool);
+        for (i, (current, next)) in self.current.iter().zip(self.next.iter()).enumerate() {
+            let mut diff = current ^ next;
+            while diff != 0 {
+                let bit = diff.trailing_zeros() as usize;
+                diff &= diff - 1;
+
+                let y = i / self.words_per_row;
+                let x = (i % self.words_per_row) * WORD_BITS + bit;
+                let mask = 1 << bit;
+                let before = if current & mask != 0 {
+                    State::Alive
+                } else if self.touched[i] & mask != 0 {
+                    State::Dead
+                } else {
+                    State::Unborn
+                };
+                let after = if next & mask != 0 {
+                    State::Alive
+                } else {
+                    State::Dead
+                };
+                changes.push((Vec2Int::new(x as i32, y as i32), before, after));
+            }
+        }
+        self.swap_in_next();
+    }
+
+    /// Fills `next` with the generation after `current`.
+    fn compute_next(&mut self, rule: &Rule, topology: Topology, pool: Option<&ThreadPool>) {
+        let width = self.width;
+        let height = self.height;
+        let wpr = self.words_per_row;
+        let current = &self.current;
+        let next = &mut self.next;
+
+        match pool {
+            Some(pool) => {
+                let bands = pool.current_num_threads() * BANDS_PER_THREAD;
+                let rows_per_band = height.div_ceil(bands).max(MIN_ROWS_PER_BAND);
+
+                pool.install(|| {
+                    next.par_chunks_mut(rows_per_band * wpr)
+                        .enumerate()
+                        .for_each(|(band, next_band)| {
+                            let first_row = band * rows_per_band;
+                            Self::step_rows(current, width, wpr, rule, topology, first_row, next_band)
+                        });
+                });
+            }
+
+            None => Self::step_rows(current, width, wpr, rule, topology, 0, next),
+        }
+    }
+
+    /// Makes the generation in `next` the current one.
+    fn swap_in_next(&mut self) {
+        mem::swap(&mut self.current, &mut self.next);
+        self.version = fresh_version();
+        for (touched, alive) in self.touched.iter_mut().zip(self.current.iter()) {
+            *touched |= *alive;
+        }
+    }
+
+    /// Computes the next generation for a band of rows, starting at `first_row`, into `next_band`.
+    fn step_rows(
+        current: &[u64],
+        width: usize,
+        wpr: usize,
+        rule: &Rule,
+        topology: Topology,
+        first_row: usize,
+        next_band: &mut [u64],
+    ) {
+        let last_word_mask = match width % WORD_BITS {
+            0 => !0,
+            used_bits => (1 << used_bits) - 1,
+        };
+
+        let height = current.len() / wpr;
+        let mut north_buffer = vec![0; wpr];
+        let mut south_buffer = vec![0; wpr];
+        let mut north_west = vec![0; wpr];
+        let mut north_east = vec![0; wpr];
+        let mut west = vec![0; wpr];
+        let mut east = vec![0; wpr];
+        let mut south_west = vec![0; wpr];
+        let mut south_east = vec![0; wpr];
+
+        // The cells just past the west and east ends of row `y`, which may itself be past an edge.
+        let edges = |y: isize| {
+            let cell = |x: isize| {
+                topology
+                    .wrap(x, y, width, height)
+                    .map_or(0, |(x, y)| Self::bit(&current[y * wpr..(y + 1) * wpr], x))
+            };
+            (cell(-1), cell(width as isize))
+        };
+
+        for (band_row, next_row) in next_band.chunks_mut(wpr).enumerate() {
+            let y = first_row + band_row;
+            let (north_y, center_y, south_y) = (y as isize + 1, y as isize, y as isize - 1);
+            let north = Self::neighbor_row(current, width, wpr, topology, north_y, &mut north_buffer);
+            let center = &current[y * wpr..(y + 1) * wpr];
+            let south = Self::neighbor_row(current, width, wpr, topology, south_y, &mut south_buffer);
+
+            let (north_west_edge, north_east_edge) = edges(north_y);
+            let (west_edge, east_edge) = edges(center_y);
+            let (south_west_edge, south_east_edge) = edges(south_y);
+
+            Self::west_neighbors(north, width, north_west_edge, &mut north_west);
+            Self::east_neighbors(north, width, north_east_edge, &mut north_east);
+            Self::west_neighbors(center, width, west_edge, &mut west);
+            Self::east_neighbors(center, width, east_edge, &mut east);
+            Self::west_neighbors(south, width, south_west_edge, &mut south_west);
+            Self::east_neighbors(south, width, south_east_edge, &mut south_east);
+
+            for i in 0..wpr {
+                let neighbors = [
+                    north_west[i],
+                    north[i],
+                    north_east[i],
+                    west[i],
+                    east[i],
+                    south_west[i],
+                    south[i],
+                    south_east[i],
+                ];
+                next_row[i] = rule.next_word(center[i], &neighbors);
+            }
+            next_row[wpr - 1] &= last_word_mask;
+        }
+    }
+
+    /// Row `y`, which may be just past the top or bottom edge. Rows which come back flipped,
+    /// or not at all, are written into `buffer`.
+    fn neighbor_row<'a>(
+        current: &'a [u64],
+        width: usize,
+        wpr: usize,
+        topology: Topology,
+        y: isize,
+        buffer: &'a mut [u64],
+    ) -> &'a [u64] {
+        let height = current.len() / wpr;
+        let row = |y: usize| &current[y * wpr..(y + 1) * wpr];
+        if y >= 0 && (y as usize) < height {
+            return row(y as usize);
+        }
+
+        for word in buffer.iter_mut() {
+            *word = 0;
+        }
+        match topology.wrap(0, y, width, height) {
+            None => buffer,
+            Some((0, y)) => row(y),
+            Some((_, y)) => {
+                let source = row(y);
+                for x in 0..width {
+                    let flipped = width - 1 - x;
+                    buffer[flipped / WORD_BITS] |= Self::bit(source, x) << (flipped % WORD_BITS);
+                }
+                buffer
+            }
+        }
+    }
+
+    /// Fills `out` so that bit `x` holds the cell at `x - 1` in `row`, with `edge` being the
+    /// cell before the first.
+    fn west_neighbors(row: &[u64], width: usize, edge: u64, out: &mut [u64]) {
+        let mut carry = edge;
+        for (i, word) in row.iter().enumerate() {
+            out[i] = (word << 1) | carry;
+            carry = word >> (WORD_BITS - 1);
+        }
+
+        let last = out.len() - 1;
+        if !width.is_multiple_of(WORD_BITS) {
+            out[last] &= (1 << (width % WORD_BITS)) - 1;
+        }
+    }
+
+    /// Fills `out` so that bit `x` holds the cell at `x + 1` in `row`, with `edge` being the
+    /// cell after the last.
+    fn east_neighbors(row: &[u64], width: usize, edge: u64, out: &mut [u64]) {
+        for i in 0..row.len() {
+            let next_word = if i + 1 < row.len() { row[i + 1] } else { 0 };
+            out[i] = (row[i] >> 1) | (next_word << (WORD_BITS - 1));
+        }
+
+        let last = width - 1;
+        out[last / WORD_BITS] |= edge << (last % WORD_BITS);
+    }
+
+    fn bit(row: &[u64], x: usize) -> u64 {
+        (row[x / WORD_BITS] >> (x % WORD_BITS)) & 1
+    }
+
+    fn index(&self, x: usize, y: usize) -> (usize, u64) {
+        debug_assert!(self.contains(x, y), "[{}, {}] is outside the grid", x, y);
+        (y * self.words_per_row + x / WORD_BITS, 1 << (x % WORD_BITS))
+    }
+}
+
+/// Walks every cell of a `Grid` row by row, yielding `(x, y, state)`.
+pub struct GridIter<'a> {
+    grid: &'a Grid,
+    index: usize,
+}
+
+impl<'a> Iterator for GridIter<'a> {
+    type Item = (usize, usize, State);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.grid.width * self.grid.height {
+            return None;
+        }
+
+        let x = self.index % self.grid.width;
+        let y = self.index / self.grid.width;
+        self.index += 1;
+
+        Some((x, y, self.grid.get(x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{Soup, SparseGrid, Symmetry, Vec2Int, World};
+    use rayon::ThreadPoolBuilder;
+    use std::collections::HashSet;
+
+    /// A glider heading towards larger x and y, as offsets from its corner.
+    const GLIDER: [(isize, isize); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+    /// A `width` by `height` grid filled with the soup from `seed`.
+    fn soup_grid(seed: u64, width: usize, height: usize) -> Grid {
+        let mut grid = Grid::new(width, height);
+        let soup = Soup::new(seed, 0.4, Symmetry::None).generate(width, height);
+        for (x, column) in soup.iter().enumerate() {
+            for (y, state) in column.iter().enumerate() {
+                if *state == State::Alive {
+                    grid.set(x, y, State::Alive);
+                }
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn parallel_step_matches_single_threaded() {
+        let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let sizes = [
+            (1, 1),
+            (5, 3),
+            (63, 20),
+            (64, 64),
+            (65, 9),
+            (100, 130),
+            (129, 200),
+            (300, 41),
+        ];
+        let rules = ["B3/S23", "B36/S23", "B3678/S34678", "B2/S", "B3/S12345"];
+
+        let mut seed = 0;
+        for &(width, height) in &sizes {
+            for rule in &rules {
+                let rule: Rule = rule.parse().unwrap();
+                for &topology in &Topology::ALL {
+                    seed += 1;
+                    let mut single = soup_grid(seed, width, height);
+                    let mut parallel = single.clone();
+
+                    for generation in 0..8 {
+                        single.step(&rule, topology, None);
+                        parallel.step(&rule, topology, Some(&pool));
+                        assert!(
+                            single.changed_cells(&parallel).is_empty(),
+                            "{}x{} {} {:?} differs after {} generations",
+                            width,
+                            height,
+                            rule,
+                            topology,
+                            generation + 1
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Steps `grid` a cell at a time, looking up every neighbor through `Topology::wrap`.
+    fn naive_step(grid: &Grid, rule: &Rule, topology: Topology) -> Grid {
+        let (width, height) = (grid.width(), grid.height());
+        let mut next = grid.clone();
+        for x in 0..width {
+            for y in 0..height {
+                let mut count = 0;
+                for dx in -1..=1 {
+                    for dy in -1..=1 {
+                        if dx == 0 && dy == 0 {
+                            continue;
+                        }
+                        let neighbor = topology.wrap(x as isize + dx, y as isize + dy, width, height);
+                        if neighbor.is_some_and(|(nx, ny)| grid.is_alive(nx, ny)) {
+                            count += 1;
+                        }
+                    }
+                }
+                next.set(x, y, rule.next_state(grid.get(x, y), count));
+            }
+        }
+        next
+    }
+
+    fn live_cells(grid: &Grid) -> HashSet<(usize, usize)> {
+        grid.iter()
+            .filter(|&(_, _, state)| state == State::Alive)
+            .map(|(x, y, _)| (x, y))
+            .collect()
+    }
+
+    #[test]
+    fn step_matches_naive_wrap_for_every_topology() {
+        let sizes = [(1, 1), (2, 3), (3, 2), (7, 5), (64, 6), (65, 7), (130, 4)];
+        let rule = Rule::CONWAY;
+
+        let mut seed = 1000;
+        for &(width, height) in &sizes {
+            for &topology in &Topology::ALL {
+                seed += 1;
+                let mut grid = soup_grid(seed, width, height);
+                for generation in 0..6 {
+                    let expected = naive_step(&grid, &rule, topology);
+                    grid.step(&rule, topology, None);
+                    assert!(
+                        grid.changed_cells(&expected).is_empty(),
+                        "{}x{} {:?} differs from the naive step after {} generations",
+                        width,
+                        height,
+                        topology,
+                        generation + 1
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gliders_cross_each_seam() {
+        let (width, height) = (70, 40);
+        let rule = Rule::CONWAY;
+
+        // Where each glider starts and which way it flies, so between them they cross the
+        // east, south, west and north seams, away from the corners.
+        let launches = [((66, 18), 1), ((33, 36), 1), ((3, 20), -1), ((35, 3), -1)];
+        for &topology in &Topology::ALL {
+            for &((start_x, start_y), heading) in &launches {
+                let cells: Vec<(isize, isize)> = GLIDER
+                    .iter()
+                    .map(|&(x, y)| (start_x + x * heading, start_y + y * heading))
+                    .collect();
+
+                let mut grid = Grid::new(width, height);
+                let mut plane = World::Infinite(SparseGrid::new());
+                for &(x, y) in &cells {
+                    grid.set(x as usize, y as usize, State::Alive);
+                    plane.set(Vec2Int::new(x as i32, y as i32), State::Alive);
+                }
+
+                for generation in 1..=48 {
+                    let expected = naive_step(&grid, &rule, topology);
+                    grid.step(&rule, topology, None);
+                    plane.step(&rule, Topology::Dead, None).unwrap();
+                    assert!(
+                        grid.changed_cells(&expected).is_empty(),
+                        "{:?} glider from ({}, {}) differs from the naive step after {} generations",
+                        topology,
+                        start_x,
+                        start_y,
+                        generation
+                    );
+
+                    // Gluing the edges together shouldn't change the glider at all, only where
+                    // it's drawn: it's the glider on an endless plane, wrapped onto the grid.
+                    if let Topology::Torus | Topology::KleinBottle | Topology::CrossSurface = topology {
+                        let wrapped: HashSet<(usize, usize)> = plane
+                            .iter()
+                            .filter(|&(_, state)| state == State::Alive)
+                            .filter_map(|(pos, _)| {
+                                topology.wrap(pos.x as isize, pos.y as isize, width, height)
+                            })
+                            .collect();
+                        assert_eq!(
+                            live_cells(&grid),
+                            wrapped,
+                            "{:?} glider from ({}, {}) came apart after {} generations",
+                            topology,
+                            start_x,
+                            start_y,
+                            generation
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn step_changes_match_a_diff_of_the_world() {
+        let rule = Rule::CONWAY;
+        for seed in 0..6 {
+            let soup = soup_grid(seed, 70, 45);
+            let mut sparse = SparseGrid::new();
+            for (x, y, state) in soup.iter() {
+                sparse.set(Vec2Int::new(x as i32 - 30, y as i32 - 20), state);
+            }
+            // A lone dead cell, whose tile is freed on the first step.
+            sparse.set(Vec2Int::new(500, -500), State::Dead);
+
+            for mut world in [World::Bounded(soup), World::Infinite(sparse)] {
+                for generation in 1..=10 {
+                    let before = world.clone();
+                    let mut changes = Vec::new();
+                    world
+                        .step_with_changes(&rule, Topology::Torus, None, &mut changes)
+                        .unwrap();
+
+                    let mut expected = world.changes_since(&before).unwrap();
+                    let key = |change: &(Vec2Int, State, State)| (change.0.x, change.0.y);
+                    changes.sort_by_key(key);
+                    expected.sort_by_key(key);
+                    assert_eq!(
+                        changes, expected,
+                        "seed {} differs after {} generations",
+                        seed, generation
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,475 @@
+use super::{Rule, Vec2Int};
+use std::collections::HashMap;
+use std::mem;
+
+type NodeId = u32;
+/// The smallest and largest positions of an area, inclusive.
+type Region = ((i64, i64), (i64, i64));
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// The biggest the universe is allowed to grow, which keeps every offset within an `i64`.
+/// Anything near its edges is so far out that `live_cells` would drop it anyway.
+const MAX_LEVEL: u8 = 62;
+/// The longest step which still fits in a universe of `MAX_LEVEL`. Longer ones are taken as
+/// several of these.
+const MAX_STEP_LOG2: u8 = MAX_LEVEL - 3;
+/// Once a universe has made this many nodes, the next `set_cells` starts it afresh rather
+/// than keep every node and result it has ever made.
+const MAX_NODES: usize = 1 << 22;
+
+/// A square of `2^level` cells on a side. Leaves (level 0) are single cells, and every
+/// other node is made of four children one level down.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    nw: NodeId,
+    ne: NodeId,
+    sw: NodeId,
+    se: NodeId,
+    level: u8,
+    population: u64,
+}
+
+/// A HashLife engine: a quadtree of canonicalized nodes, where the future of each node is
+/// memoized, so that repetitive patterns can be advanced by `2^n` generations in one go.
+///
+/// HashLife works on an unbounded plane, so this pairs with `World::Infinite`. Like the
+/// sparse grid, it can't run rules which give birth on zero neighbors, which
+/// `World::check_rule` turns away before they get here.
+#[derive(Debug)]
+pub struct HashLife {
+    rule: Rule,
+    nodes: Vec<Node>,
+    lookup: HashMap<[NodeId; 4], NodeId>,
+    results: HashMap<(NodeId, u8), NodeId>,
+    empty: Vec<NodeId>,
+    root: NodeId,
+    origin: (i64, i64),
+    generation: u64,
+}
+
+impl HashLife {
+    pub fn new(rule: Rule) -> Self {
+        let leaf = |population| Node {
+            nw: DEAD,
+            ne: DEAD,
+            sw: DEAD,
+            se: DEAD,
+            level: 0,
+            population,
+        };
+
+        let mut this = HashLife {
+            rule,
+            nodes: vec![leaf(0), leaf(1)],
+            lookup: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            origin: (0, 0),
+            generation: 0,
+        };
+        this.root = this.empty(3);
+        this
+    }
+
+    /// Builds a universe holding the given live cells.
+    pub fn from_cells(rule: Rule, cells: &[Vec2Int]) -> Self {
+        let mut this = HashLife::new(rule);
+        this.set_cells(cells);
+        this
+    }
+
+    /// Replaces the universe with the given live cells. Everything worked out so far is kept,
+    /// so whatever parts of the new pattern have been seen before are as quick to advance as
+    /// they were then.
+    pub fn set_cells(&mut self, cells: &[Vec2Int]) {
+        if self.nodes.len() > MAX_NODES {
+            *self = HashLife::new(self.rule);
+        }
+
+        self.generation = 0;
+        self.origin = (0, 0);
+        self.root = self.empty(3);
+        if cells.is_empty() {
+            return;
+        }
+
+        let min_x = cells.iter().map(|pos| pos.x).min().unwrap() as i64;
+        let min_y = cells.iter().map(|pos| pos.y).min().unwrap() as i64;
+        let max_x = cells.iter().map(|pos| pos.x).max().unwrap() as i64;
+        let max_y = cells.iter().map(|pos| pos.y).max().unwrap() as i64;
+        let extent = (max_x - min_x).max(max_y - min_y) + 1;
+
+        let mut level = 3;
+        while (1i64 << level) < extent {
+            level += 1;
+        }
+
+        self.root = self.empty(level);
+        self.origin = (min_x, min_y);
+        for pos in cells {
+            let root = self.root;
+            self.root = self.set_alive(root, pos.x as i64 - min_x, pos.y as i64 - min_y);
+        }
+    }
+
+    /// A universe holding the same cells, made of only the nodes they need, without any of
+    /// the results worked out so far.
+    pub fn snapshot(&self) -> Self {
+        let mut copy = HashLife::new(self.rule);
+        copy.root = copy.copy_node(self, self.root, &mut HashMap::new());
+        copy.origin = self.origin;
+        copy.generation = self.generation;
+        copy
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    pub fn is_alive(&self, pos: Vec2Int) -> bool {
+        let (mut x, mut y) = (pos.x as i64 - self.origin.0, pos.y as i64 - self.origin.1);
+        let mut node = self.nodes[self.root as usize];
+        if x < 0 || y < 0 || x >> node.level != 0 || y >> node.level != 0 {
+            return false;
+        }
+
+        while node.level > 0 && node.population > 0 {
+            let half = 1i64 << (node.level - 1);
+            let child = match (x < half, y < half) {
+                (true, true) => node.nw,
+                (false, true) => node.ne,
+                (true, false) => node.sw,
+                (false, false) => node.se,
+            };
+            x %= half;
+            y %= half;
+            node = self.nodes[child as usize];
+        }
+        node.population > 0
+    }
+
+    /// Roughly how many bytes the nodes and results take up.
+    pub fn memory_size(&self) -> usize {
+        self.nodes.len() * mem::size_of::<Node>()
+            + self.lookup.len() * mem::size_of::<([NodeId; 4], NodeId)>()
+            + self.results.len() * mem::size_of::<((NodeId, u8), NodeId)>()
+    }
+
+    /// How many generations this universe has been advanced by.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Advances the universe by `generations`, one power of two at a time.
+    pub fn advance(&mut self, generations: u64) {
+        for step_log2 in 0..64 {
+            if generations & (1 << step_log2) == 0 {
+                continue;
+            }
+
+            if step_log2 <= MAX_STEP_LOG2 {
+                self.advance_by_power_of_two(step_log2);
+            } else {
+                for _ in 0..1u64 << (step_log2 - MAX_STEP_LOG2) {
+                    self.advance_by_power_of_two(MAX_STEP_LOG2);
+                }
+            }
+        }
+    }
+
+    /// Every live cell. Cells which have flown too far away to fit in a `Vec2Int` are dropped.
+    pub fn live_cells(&self) -> Vec<Vec2Int> {
+        self.live_cells_in(Vec2Int::new(i32::MIN, i32::MIN), Vec2Int::new(i32::MAX, i32::MAX))
+    }
+
+    /// The live cells from `min` to `max` inclusive, found without looking inside any node
+    /// lying wholly outside them.
+    pub fn live_cells_in(&self, min: Vec2Int, max: Vec2Int) -> Vec<Vec2Int> {
+        let mut cells = Vec::new();
+        let region = ((min.x as i64, min.y as i64), (max.x as i64, max.y as i64));
+        self.collect_cells(self.root, self.origin.0, self.origin.1, region, &mut cells);
+        cells
+    }
+
+    fn advance_by_power_of_two(&mut self, step_log2: u8) {
+        // Pad the universe until the pattern sits in its innermost quarter, so nothing can
+        // escape the centre square that `successor` hands back. Once it's as big as it's
+        // allowed to get, whatever is out near the edges is lost.
+        loop {
+            let level = self.nodes[self.root as usize].level;
+            let inner = {
+                let center = self.center(self.root);
+                self.center(center)
+            };
+            let contained = self.population() == self.nodes[inner as usize].population;
+            if level >= step_log2 + 3 && (contained || level >= MAX_LEVEL) {
+                break;
+            }
+            self.grow();
+        }
+
+        let level = self.nodes[self.root as usize].level;
+        let root = self.root;
+        self.root = self.successor(root, step_log2);
+
+        let quarter = 1i64 << (level - 2);
+        self.origin = (self.origin.0 + quarter, self.origin.1 + quarter);
+        self.generation = self.generation.wrapping_add(1 << step_log2);
+    }
+
+    /// Wraps the root in a node twice its size, keeping the pattern in the middle.
+    fn grow(&mut self) {
+        let root = self.nodes[self.root as usize];
+        let empty = self.empty(root.level - 1);
+
+        let nw = self.join(empty, empty, empty, root.nw);
+        let ne = self.join(empty, empty, root.ne, empty);
+        let sw = self.join(empty, root.sw, empty, empty);
+        let se = self.join(root.se, empty, empty, empty);
+        self.root = self.join(nw, ne, sw, se);
+
+        let half = 1i64 << (root.level - 1);
+        self.origin = (self.origin.0 - half, self.origin.1 - half);
+    }
+
+    /// Returns the centre of `node`, one level down, advanced by `2^step_log2` generations.
+    /// `step_log2` can be at most two less than the node's level.
+    fn successor(&mut self, node: NodeId, step_log2: u8) -> NodeId {
+        let this = self.nodes[node as usize];
+        debug_assert!(this.level >= 2 && step_log2 <= this.level - 2);
+
+        if this.population == 0 {
+            return self.empty(this.level - 1);
+        }
+        if this.level == 2 {
+            return self.base_case(node);
+        }
+        if let Some(&result) = self.results.get(&(node, step_log2)) {
+            return result;
+        }
+
+        let n00 = this.nw;
+        let n01 = self.centered_horizontal(this.nw, this.ne);
+        let n02 = this.ne;
+        let n10 = self.centered_vertical(this.nw, this.sw);
+        let n11 = self.center(node);
+        let n12 = self.centered_vertical(this.ne, this.se);
+        let n20 = this.sw;
+        let n21 = self.centered_horizontal(this.sw, this.se);
+        let n22 = this.se;
+
+        // At full speed both halves of the jump are spent recursing; otherwise the first
+        // half just re-centers and the whole jump happens in the second.
+        let full_speed = step_log2 == this.level - 2;
+        let first_pass = |this: &mut Self, n: NodeId| {
+            if full_speed {
+                this.successor(n, step_log2 - 1)
+            } else {
+                this.center(n)
+            }
+        };
+        let r00 = first_pass(self, n00);
+        let r01 = first_pass(self, n01);
+        let r02 = first_pass(self, n02);
+        let r10 = first_pass(self, n10);
+        let r11 = first_pass(self, n11);
+        let r12 = first_pass(self, n12);
+        let r20 = first_pass(self, n20);
+        let r21 = first_pass(self, n21);
+        let r22 = first_pass(self, n22);
+
+        let second_step = if full_speed { step_log2 - 1 } else { step_log2 };
+        let nw = self.join(r00, r01, r10, r11);
+        let ne = self.join(r01, r02, r11, r12);
+        let sw = self.join(r10, r11, r20, r21);
+        let se = self.join(r11, r12, r21, r22);
+
+        let nw = self.successor(nw, second_step);
+        let ne = self.successor(ne, second_step);
+        let sw = self.successor(sw, second_step);
+        let se = self.successor(se, second_step);
+        let result = self.join(nw, ne, sw, se);
+
+        self.results.insert((node, step_log2), result);
+        result
+    }
+
+    /// Runs a single generation on a 4x4 node by hand, returning its centre 2x2.
+    fn base_case(&mut self, node: NodeId) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        let this = self.nodes[node as usize];
+        for &(child, x0, y0) in &[(this.nw, 0, 0), (this.ne, 2, 0), (this.sw, 0, 2), (this.se, 2, 2)] {
+            let child = self.nodes[child as usize];
+            cells[y0][x0] = child.nw == ALIVE;
+            cells[y0][x0 + 1] = child.ne == ALIVE;
+            cells[y0 + 1][x0] = child.sw == ALIVE;
+            cells[y0 + 1][x0 + 1] = child.se == ALIVE;
+        }
+
+        let mut next = [DEAD; 4];
+        for (i, &(x, y)) in [(1, 1), (2, 1), (1, 2), (2, 2)].iter().enumerate() {
+            let mut count = 0;
+            for (ny, row) in cells.iter().enumerate().skip(y - 1).take(3) {
+                for (nx, &alive) in row.iter().enumerate().skip(x - 1).take(3) {
+                    if (nx, ny) != (x, y) && alive {
+                        count += 1;
+                    }
+                }
+            }
+
+            let alive = if cells[y][x] {
+                self.rule.survives_on(count)
+            } else {
+                self.rule.births_on(count)
+            };
+            next[i] = if alive { ALIVE } else { DEAD };
+        }
+
+        self.join(next[0], next[1], next[2], next[3])
+    }
+
+    fn center(&mut self, node: NodeId) -> NodeId {
+        let this = self.nodes[node as usize];
+        let (nw, ne, sw, se) = (
+            self.nodes[this.nw as usize],
+            self.nodes[this.ne as usize],
+            self.nodes[this.sw as usize],
+            self.nodes[this.se as usize],
+        );
+        self.join(nw.se, ne.sw, sw.ne, se.nw)
+    }
+
+    fn centered_horizontal(&mut self, west: NodeId, east: NodeId) -> NodeId {
+        let (west, east) = (self.nodes[west as usize], self.nodes[east as usize]);
+        self.join(west.ne, east.nw, west.se, east.sw)
+    }
+
+    fn centered_vertical(&mut self, north: NodeId, south: NodeId) -> NodeId {
+        let (north, south) = (self.nodes[north as usize], self.nodes[south as usize]);
+        self.join(north.sw, north.se, south.nw, south.ne)
+    }
+
+    fn set_alive(&mut self, node: NodeId, x: i64, y: i64) -> NodeId {
+        let this = self.nodes[node as usize];
+        if this.level == 0 {
+            return ALIVE;
+        }
+
+        let half = 1i64 << (this.level - 1);
+        let (mut nw, mut ne, mut sw, mut se) = (this.nw, this.ne, this.sw, this.se);
+        match (x < half, y < half) {
+            (true, true) => nw = self.set_alive(nw, x, y),
+            (false, true) => ne = self.set_alive(ne, x - half, y),
+            (true, false) => sw = self.set_alive(sw, x, y - half),
+            (false, false) => se = self.set_alive(se, x - half, y - half),
+        }
+        self.join(nw, ne, sw, se)
+    }
+
+    fn collect_cells(&self, node: NodeId, x: i64, y: i64, region: Region, cells: &mut Vec<Vec2Int>) {
+        let this = self.nodes[node as usize];
+        let ((min_x, min_y), (max_x, max_y)) = region;
+        let size = 1i64 << this.level;
+        if this.population == 0 || x > max_x || y > max_y || x + size <= min_x || y + size <= min_y {
+            return;
+        }
+
+        if this.level == 0 {
+            cells.push(Vec2Int::new(x as i32, y as i32));
+            return;
+        }
+
+        let half = 1i64 << (this.level - 1);
+        self.collect_cells(this.nw, x, y, region, cells);
+        self.collect_cells(this.ne, x + half, y, region, cells);
+        self.collect_cells(this.sw, x, y + half, region, cells);
+        self.collect_cells(this.se, x + half, y + half, region, cells);
+    }
+
+    /// Makes `node` of `from` again in this universe, along with everything under it.
+    fn copy_node(&mut self, from: &HashLife, node: NodeId, copied: &mut HashMap<NodeId, NodeId>) -> NodeId {
+        if node == DEAD || node == ALIVE {
+            return node;
+        }
+        if let Some(&id) = copied.get(&node) {
+            return id;
+        }
+
+        let this = from.nodes[node as usize];
+        let nw = self.copy_node(from, this.nw, copied);
+        let ne = self.copy_node(from, this.ne, copied);
+        let sw = self.copy_node(from, this.sw, copied);
+        let se = self.copy_node(from, this.se, copied);
+        let id = self.join(nw, ne, sw, se);
+        copied.insert(node, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap();
+            let next = self.join(below, below, below, below);
+            self.empty.push(next);
+        }
+        self.empty[level as usize]
+    }
+
+    fn join(&mut self, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+        let key = [nw, ne, sw, se];
+        if let Some(&id) = self.lookup.get(&key) {
+            return id;
+        }
+
+        let population = key.iter().map(|&child| self.nodes[child as usize].population).sum();
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            nw,
+            ne,
+            sw,
+            se,
+            level: self.nodes[nw as usize].level + 1,
+            population,
+        });
+        self.lookup.insert(key, id);
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut cells: Vec<Vec2Int>) -> Vec<(i32, i32)> {
+        cells.sort_by_key(|pos| (pos.y, pos.x));
+        cells.into_iter().map(|pos| (pos.x, pos.y)).collect()
+    }
+
+    #[test]
+    fn the_longest_jumps_dont_overflow() {
+        let block = vec![(0, 0), (1, 0), (0, 1), (1, 1)];
+        let cells: Vec<Vec2Int> = block.iter().map(|&(x, y)| Vec2Int::new(x, y)).collect();
+        let mut hashlife = HashLife::from_cells(Rule::CONWAY, &cells);
+        hashlife.advance(u64::MAX);
+        assert_eq!(sorted(hashlife.live_cells()), block);
+
+        // An odd number of generations leaves the blinker standing up.
+        let blinker: Vec<Vec2Int> = (0..3).map(|x| Vec2Int::new(x, 0)).collect();
+        let mut hashlife = HashLife::from_cells(Rule::CONWAY, &blinker);
+        hashlife.advance(u64::MAX);
+        assert_eq!(sorted(hashlife.live_cells()), vec![(1, -1), (1, 0), (1, 1)]);
+
+        // A glider flies off past where a `Vec2Int` can reach, and on past the edge of the universe.
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let glider: Vec<Vec2Int> = glider.iter().map(|&(x, y)| Vec2Int::new(x, y)).collect();
+        let mut hashlife = HashLife::from_cells(Rule::CONWAY, &glider);
+        hashlife.advance(1 << 63);
+        assert!(hashlife.live_cells().is_empty());
+    }
+}
@@ -0,0 +1,370 @@
+use super::{State, Vec2Int, World};
+use std::{collections::VecDeque, mem};
+
+/// A single cell being changed from one state to another.
+#[derive(Debug, Clone, Copy)]
+struct CellChange {
+    pos: Vec2Int,
+    before: State,
+    after: State,
+}
+
+#[derive(Debug)]
+enum Change {
+    /// Only the cells that changed. Used for drawing, pasting, single generations and most jumps.
+    Cells(Vec<CellChange>),
+    /// The whole world before and after. Used when the board is resized, or when a
+    /// jump changes so much that listing the cells would cost more than a copy.
+    Replace { before: World, after: World },
+}
+
+#[derive(Debug)]
+struct Edit {
+    change: Change,
+    /// How many generations the world was stepped forward by. Zero for edits by hand.
+    generations: u64,
+}
+
+impl Edit {
+    fn memory_size(&self) -> usize {
+        match &self.change {
+            Change::Cells(changes) => changes.len() * mem::size_of::<CellChange>(),
+            Change::Replace { before, after } => before.memory_size() + after.memory_size(),
+        }
+    }
+
+    /// The cells the edit changes, as their positions and their states before and after.
+    fn changes(&self) -> Vec<(Vec2Int, State, State)> {
+        match &self.change {
+            Change::Cells(changes) => changes
+                .iter()
+                .map(|change| (change.pos, change.before, change.after))
+                .collect(),
+            Change::Replace { before, after } => after.changes_since(before).unwrap_or_default(),
+        }
+    }
+
+    fn undo(&self, world: &mut World) {
+        match &self.change {
+            Change::Cells(changes) => {
+                for change in changes.iter().rev() {
+                    world.set(change.pos, change.before);
+                }
+            }
+            Change::Replace { before, .. } => *world = before.clone(),
+        }
+    }
+
+    fn redo(&self, world: &mut World) {
+        match &self.change {
+            Change::Cells(changes) => {
+                for change in changes {
+                    world.set(change.pos, change.after);
+                }
+            }
+            Change::Replace { after, .. } => *world = after.clone(),
+        }
+    }
+}
+
+/// Undo and redo stacks for everything that changes the board. Once the edits on both
+/// take up more than `memory_cap` bytes, the oldest ones are forgotten, and after them
+/// the undone ones furthest from the current board.
+#[derive(Debug)]
+pub struct History {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    in_progress: Vec<CellChange>,
+    memory_used: usize,
+    memory_cap: usize,
+}
+
+impl History {
+    pub fn new(memory_cap: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            in_progress: Vec::new(),
+            memory_used: 0,
+            memory_cap,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || !self.in_progress.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn set_memory_cap(&mut self, memory_cap: usize) {
+        self.memory_cap = memory_cap;
+        self.trim();
+    }
+
+    /// Adds a cell to the edit currently being drawn. Everything recorded until
+    /// `finish_edit` is undone in one go.
+    pub fn record_cell(&mut self, pos: Vec2Int, before: State, after: State) {
+        if before != after {
+            self.in_progress.push(CellChange { pos, before, after });
+        }
+    }
+
+    pub fn finish_edit(&mut self) {
+        if !self.in_progress.is_empty() {
+            let changes = mem::take(&mut self.in_progress);
+            self.push(Edit {
+                change: Change::Cells(changes),
+                generations: 0,
+            });
+        }
+    }
+
+    /// Records the world going from `before` to `after` over `generations` steps (zero for
+    /// edits by hand), keeping whichever of a cell-by-cell diff or a full copy is smaller.
+    pub fn record_change(&mut self, before: World, after: &World, generations: u64) {
+        let changes = after.changes_since(&before);
+        self.record_diff(before, after, changes, generations);
+    }
+
+    /// Like `record_change`, for when the caller has already worked out `after.changes_since(&before)`
+    /// and would rather not have it done twice.
+    pub fn record_diff(
+        &mut self,
+        before: World,
+        after: &World,
+        changes: Option<Vec<(Vec2Int, State, State)>>,
+        generations: u64,
+    ) {
+        self.finish_edit();
+
+        let snapshot_size = before.memory_size() + after.memory_size();
+        let change = match changes {
+            Some(changes) => {
+                if changes.is_empty() {
+                    return;
+                }
+
+                if changes.len() * mem::size_of::<CellChange>() <= snapshot_size {
+                    Change::Cells(
+                        changes
+                            .into_iter()
+                            .map(|(pos, before, after)| CellChange { pos, before, after })
+                            .collect(),
+                    )
+                } else {
+                    Change::Replace {
+                        before,
+                        after: after.clone(),
+                    }
+                }
+            }
+
+            None => Change::Replace {
+                before,
+                after: after.clone(),
+            },
+        };
+
+        self.push(Edit { change, generations });
+    }
+
+    /// Records a step of `generations` which changed the cells in `changes`, given as their
+    /// positions and their states before and after, as `World::step_with_changes` lists them.
+    pub fn record_step(&mut self, changes: Vec<(Vec2Int, State, State)>, generations: u64) {
+        self.finish_edit();
+
+        if changes.is_empty() {
+            return;
+        }
+
+        let changes = changes
+            .into_iter()
+            .map(|(pos, before, after)| CellChange { pos, before, after })
+            .collect();
+        self.push(Edit {
+            change: Change::Cells(changes),
+            generations,
+        });
+    }
+
+    /// Puts the world back how it was before the last edit. Returns how many generations
+    /// that edit had stepped forward, or `None` if there was nothing to undo.
+    pub fn undo(&mut self, world: &mut World) -> Option<u64> {
+        self.finish_edit();
+
+        let edit = self.undo.pop_back()?;
+        edit.undo(world);
+
+        let generations = edit.generations;
+        self.redo.push(edit);
+        Some(generations)
+    }
+
+    /// Reapplies the last undone edit. Returns how many generations that edit steps
+    /// forward, or `None` if there was nothing to redo.
+    pub fn redo(&mut self, world: &mut World) -> Option<u64> {
+        self.finish_edit();
+
+        let edit = self.redo.pop()?;
+        edit.redo(world);
+
+        let generations = edit.generations;
+        self.undo.push_back(edit);
+        Some(generations)
+    }
+
+    /// The cells changed by the edit which would be undone next, as their positions and their
+    /// states before and after.
+    pub fn latest_changes(&self) -> Vec<(Vec2Int, State, State)> {
+        self.undo.back().map_or_else(Vec::new, Edit::changes)
+    }
+
+    fn push(&mut self, edit: Edit) {
+        // A new edit branches off from here, so anything undone is gone for good.
+        for edit in self.redo.drain(..) {
+            self.memory_used -= edit.memory_size();
+        }
+
+        self.memory_used += edit.memory_size();
+        self.undo.push_back(edit);
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.memory_used > self.memory_cap {
+            let edit = match self.undo.pop_front() {
+                Some(edit) => edit,
+                None if !self.redo.is_empty() => self.redo.remove(0),
+                None => break,
+            };
+            self.memory_used -= edit.memory_size();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{Grid, Rule, Topology};
+
+    const CELL_CHANGE: usize = mem::size_of::<CellChange>();
+
+    fn blinker(width: usize, height: usize) -> World {
+        let mut world = World::Bounded(Grid::new(width, height));
+        for x in 1..4 {
+            world.set(Vec2Int::new(x, 2), State::Alive);
+        }
+        world
+    }
+
+    /// Draws a single cell as an edit of its own.
+    fn draw(history: &mut History, world: &mut World, pos: Vec2Int) {
+        let before = world.get(pos);
+        world.set(pos, State::Alive);
+        history.record_cell(pos, before, State::Alive);
+        history.finish_edit();
+    }
+
+    #[test]
+    fn undoes_and_redoes_cell_diffs() {
+        let mut history = History::new(usize::MAX);
+        let mut world = blinker(5, 5);
+        let start = world.to_states();
+
+        let before = world.clone();
+        world.step(&Rule::CONWAY, Topology::Dead, None).unwrap();
+        history.record_change(before, &world, 1);
+        assert_eq!(history.latest_changes().len(), 4);
+        let stepped = world.to_states();
+
+        draw(&mut history, &mut world, Vec2Int::new(0, 0));
+        let drawn = world.to_states();
+
+        assert_eq!(history.undo(&mut world), Some(0));
+        assert_eq!(world.to_states(), stepped);
+        assert_eq!(history.undo(&mut world), Some(1));
+        assert_eq!(world.to_states(), start);
+        assert_eq!(history.undo(&mut world), None);
+        assert!(!history.can_undo());
+
+        assert_eq!(history.redo(&mut world), Some(1));
+        assert_eq!(world.to_states(), stepped);
+        assert_eq!(history.redo(&mut world), Some(0));
+        assert_eq!(world.to_states(), drawn);
+        assert_eq!(history.redo(&mut world), None);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn undoes_and_redoes_replacements() {
+        let mut history = History::new(usize::MAX);
+        let mut world = blinker(5, 5);
+        let small = world.to_states();
+
+        // Worlds of different sizes can't be diffed, so the whole world is kept.
+        let before = world.clone();
+        world = blinker(8, 6);
+        history.record_change(before, &world, 0);
+        assert!(matches!(
+            history.undo.back().unwrap().change,
+            Change::Replace { .. }
+        ));
+        let large = world.to_states();
+
+        assert_eq!(history.undo(&mut world), Some(0));
+        assert_eq!(world.to_states(), small);
+        assert_eq!(world.size(), Some(Vec2Int::new(5, 5)));
+
+        assert_eq!(history.redo(&mut world), Some(0));
+        assert_eq!(world.to_states(), large);
+        assert_eq!(world.size(), Some(Vec2Int::new(8, 6)));
+    }
+
+    #[test]
+    fn forgets_the_oldest_edits_past_the_cap() {
+        let mut history = History::new(3 * CELL_CHANGE);
+        let mut world = World::Bounded(Grid::new(8, 8));
+        for x in 0..5 {
+            draw(&mut history, &mut world, Vec2Int::new(x, 0));
+        }
+        assert_eq!(history.memory_used, 3 * CELL_CHANGE);
+
+        for _ in 0..3 {
+            assert!(history.undo(&mut world).is_some());
+        }
+        assert_eq!(history.undo(&mut world), None);
+        assert_eq!(world.population(), 2);
+    }
+
+    #[test]
+    fn counts_undone_edits_towards_the_cap() {
+        let mut history = History::new(3 * CELL_CHANGE);
+        let mut world = World::Bounded(Grid::new(8, 8));
+        for x in 0..3 {
+            draw(&mut history, &mut world, Vec2Int::new(x, 0));
+        }
+
+        // Going back and forth moves edits between the stacks without adding any.
+        for _ in 0..10 {
+            while history.undo(&mut world).is_some() {}
+            while history.redo(&mut world).is_some() {}
+        }
+        assert_eq!(history.memory_used, 3 * CELL_CHANGE);
+
+        // With nothing left to undo, the edits furthest from the board go first.
+        while history.undo(&mut world).is_some() {}
+        history.set_memory_cap(CELL_CHANGE);
+        assert_eq!(history.memory_used, CELL_CHANGE);
+        assert_eq!(history.redo(&mut world), Some(0));
+        assert_eq!(world.population(), 1);
+        assert_eq!(history.redo(&mut world), None);
+
+        // A new edit drops everything undone.
+        history.undo(&mut world);
+        draw(&mut history, &mut world, Vec2Int::new(5, 5));
+        assert_eq!(history.memory_used, CELL_CHANGE);
+        assert!(!history.can_redo());
+    }
+}
@@ -0,0 +1,40 @@
+pub use super::utilities::Vec2Int;
+
+mod ages;
+mod census;
+mod density;
+mod grid;
+mod hashlife;
+mod history;
+mod period;
+mod prefabs;
+mod rule;
+mod search;
+mod selection;
+pub mod simple_serialization;
+mod soup;
+mod sparse_grid;
+mod state;
+mod statistics;
+mod topology;
+mod world;
+
+pub use ages::{Age, CellAges};
+pub use census::{BackgroundCensus, Catalogue, Census, CensusEntry};
+pub use density::{DensityCache, DensityPyramid};
+pub use grid::Grid;
+pub use hashlife::HashLife;
+pub use history::History;
+pub use period::{PeriodDetector, Periodicity};
+pub use prefabs::{Prefab, Prefabs};
+pub use rule::*;
+pub use search::{
+    run_soup, BackgroundSearch, SearchFind, SearchProgress, SearchSettings, SoupSearch, COMMON_OBJECTS,
+};
+pub use selection::Selection;
+pub use soup::{Soup, Symmetry};
+pub use sparse_grid::SparseGrid;
+pub use state::State;
+pub use statistics::{GenerationStats, Statistics};
+pub use topology::Topology;
+pub use world::{World, WorldError};
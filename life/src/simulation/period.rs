@@ -0,0 +1,308 @@
+use super::{State, Vec2Int, World};
+use std::collections::{hash_map::DefaultHasher, HashMap, VecDeque};
+use std::fmt::{self, Display};
+use std::hash::{Hash, Hasher};
+
+/// How a pattern repeats, once it does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Periodicity {
+    /// Every cell has died.
+    Extinct,
+    StillLife,
+    Oscillator { period: u64 },
+    /// Repeats `period` generations later, moved over by `displacement`.
+    Spaceship { period: u64, displacement: Vec2Int },
+}
+
+impl Periodicity {
+    pub fn period(&self) -> u64 {
+        match self {
+            Periodicity::Extinct | Periodicity::StillLife => 1,
+            Periodicity::Oscillator { period } | Periodicity::Spaceship { period, .. } => *period,
+        }
+    }
+
+    pub fn displacement(&self) -> Vec2Int {
+        match self {
+            Periodicity::Spaceship { displacement, .. } => *displacement,
+            _ => Vec2Int::ZERO,
+        }
+    }
+
+    /// The speed and heading of a spaceship, written the usual way, such as `c/4 diagonal`
+    /// for a glider. `None` for anything that stays put.
+    pub fn velocity(&self) -> Option<String> {
+        let (period, displacement) = match self {
+            Periodicity::Spaceship { period, displacement } => (*period, *displacement),
+            _ => return None,
+        };
+
+        let dx = u64::from(displacement.x.unsigned_abs());
+        let dy = u64::from(displacement.y.unsigned_abs());
+        let distance = dx.max(dy);
+        let divisor = gcd(distance, period);
+        let (distance, period) = (distance / divisor, period / divisor);
+
+        let speed = match (distance, period) {
+            (1, 1) => "c".to_owned(),
+            (1, period) => format!("c/{}", period),
+            (distance, 1) => format!("{}c", distance),
+            (distance, period) => format!("{}c/{}", distance, period),
+        };
+        let heading = if dx == 0 || dy == 0 {
+            "orthogonal"
+        } else if dx == dy {
+            "diagonal"
+        } else {
+            "oblique"
+        };
+
+        Some(format!("{} {}", speed, heading))
+    }
+}
+
+impl Display for Periodicity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Periodicity::Extinct => write!(f, "died out"),
+            Periodicity::StillLife => write!(f, "still life"),
+            Periodicity::Oscillator { period } => write!(f, "period {} oscillator", period),
+            Periodicity::Spaceship { period, .. } => write!(
+                f,
+                "{} spaceship, period {}",
+                self.velocity().unwrap_or_default(),
+                period
+            ),
+        }
+    }
+}
+
+/// Watches a world one generation at a time and spots when it starts repeating itself.
+///
+/// Each generation's live cells are compared relative to their bounding box, so a pattern
+/// which has moved still matches its earlier self. Only the last `max_period` generations
+/// are remembered, and skipping a generation starts the search over.
+///
+/// A generation can only repeat an earlier one with the same population and bounding box,
+/// so the cells are only kept for generations which share those with one before them. That
+/// keeps the memory down while a pattern is still changing, but means a repeat is spotted a
+/// period later than it could be, once the first time round has been kept.
+#[derive(Debug)]
+pub struct PeriodDetector {
+    max_period: usize,
+    last_generation: Option<u64>,
+    sightings: VecDeque<Sighting>,
+    /// How many of the sightings have each population and bounding box size.
+    shapes: HashMap<(usize, Vec2Int), usize>,
+    found: Option<Periodicity>,
+}
+
+#[derive(Debug)]
+struct Sighting {
+    generation: u64,
+    shape: (usize, Vec2Int),
+    origin: Vec2Int,
+    /// A hash of the cells, and the cells themselves relative to `origin` in row order, if
+    /// they were kept.
+    cells: Option<(u64, Vec<Vec2Int>)>,
+}
+
+impl PeriodDetector {
+    pub fn new(max_period: usize) -> Self {
+        PeriodDetector {
+            max_period: max_period.max(1),
+            last_generation: None,
+            sightings: VecDeque::new(),
+            shapes: HashMap::new(),
+            found: None,
+        }
+    }
+
+    pub fn periodicity(&self) -> Option<Periodicity> {
+        self.found
+    }
+
+    /// Forgets everything seen so far. Needed whenever the world changes other than by
+    /// stepping, since the generations before no longer lead to the ones after.
+    pub fn reset(&mut self) {
+        self.last_generation = None;
+        self.sightings.clear();
+        self.shapes.clear();
+        self.found = None;
+    }
+
+    /// Looks at the world as it is at `generation`, returning how it repeats if it has started to.
+    pub fn observe(&mut self, generation: u64, world: &World) -> Option<Periodicity> {
+        if let Some(last_generation) = self.last_generation {
+            if last_generation + 1 != generation {
+                self.reset();
+            }
+        }
+        self.last_generation = Some(generation);
+
+        if self.found.is_some() {
+            return self.found;
+        }
+
+        let mut cells: Vec<Vec2Int> = world
+            .iter()
+            .filter(|(_, state)| *state == State::Alive)
+            .map(|(pos, _)| pos)
+            .collect();
+        let (origin, size) = Self::bounds(&cells);
+        let shape = (cells.len(), size);
+
+        let mut kept = None;
+        if self.shapes.get(&shape).is_some_and(|count| *count > 0) {
+            for pos in cells.iter_mut() {
+                *pos -= origin;
+            }
+            // Infinite worlds don't iterate in any particular order.
+            cells.sort_by_key(|pos| (pos.y, pos.x));
+
+            let mut hasher = DefaultHasher::new();
+            cells.hash(&mut hasher);
+            let hash = hasher.finish();
+
+            let earlier = self
+                .sightings
+                .iter()
+                .rev()
+                .find(|sighting| match &sighting.cells {
+                    Some((earlier_hash, earlier_cells)) => *earlier_hash == hash && *earlier_cells == cells,
+                    None => false,
+                });
+            if let Some(earlier) = earlier {
+                let period = generation - earlier.generation;
+                let displacement = origin - earlier.origin;
+                self.found = Some(if shape.0 == 0 {
+                    Periodicity::Extinct
+                } else if displacement != Vec2Int::ZERO {
+                    Periodicity::Spaceship { period, displacement }
+                } else if period == 1 {
+                    Periodicity::StillLife
+                } else {
+                    Periodicity::Oscillator { period }
+                });
+
+                return self.found;
+            }
+
+            kept = Some((hash, cells));
+        }
+
+        *self.shapes.entry(shape).or_insert(0) += 1;
+        self.sightings.push_back(Sighting {
+            generation,
+            shape,
+            origin,
+            cells: kept,
+        });
+        while self.sightings.len() > self.max_period {
+            if let Some(old) = self.sightings.pop_front() {
+                if let Some(count) = self.shapes.get_mut(&old.shape) {
+                    *count -= 1;
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The top left corner of `cells` and how far the bottom right one is from it.
+    fn bounds(cells: &[Vec2Int]) -> (Vec2Int, Vec2Int) {
+        let bounds = cells
+            .iter()
+            .fold(None, |bounds: Option<(Vec2Int, Vec2Int)>, pos| match bounds {
+                Some((min, max)) => Some((
+                    Vec2Int::new(min.x.min(pos.x), min.y.min(pos.y)),
+                    Vec2Int::new(max.x.max(pos.x), max.y.max(pos.y)),
+                )),
+                None => Some((*pos, *pos)),
+            });
+        bounds.map_or((Vec2Int::ZERO, Vec2Int::ZERO), |(min, max)| (min, max - min))
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{Rule, SparseGrid, Topology};
+
+    /// Steps `cells` on an infinite plane until the detector spots a repeat.
+    fn periodicity_of(cells: &[(i32, i32)]) -> Option<Periodicity> {
+        let mut world = World::Infinite(SparseGrid::new());
+        for &(x, y) in cells {
+            world.set(Vec2Int::new(x, y), State::Alive);
+        }
+
+        let mut detector = PeriodDetector::new(16);
+        for generation in 0..64 {
+            if let Some(periodicity) = detector.observe(generation, &world) {
+                return Some(periodicity);
+            }
+            world.step(&Rule::CONWAY, Topology::Dead, None).unwrap();
+        }
+        None
+    }
+
+    #[test]
+    fn spots_common_patterns() {
+        let block = [(0, 0), (1, 0), (0, 1), (1, 1)];
+        let blinker = [(0, 0), (1, 0), (2, 0)];
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let pair = [(0, 0), (1, 0)];
+
+        assert_eq!(periodicity_of(&block), Some(Periodicity::StillLife));
+        assert_eq!(
+            periodicity_of(&blinker),
+            Some(Periodicity::Oscillator { period: 2 })
+        );
+        assert_eq!(
+            periodicity_of(&glider),
+            Some(Periodicity::Spaceship {
+                period: 4,
+                displacement: Vec2Int::new(1, 1)
+            })
+        );
+        assert_eq!(periodicity_of(&pair), Some(Periodicity::Extinct));
+    }
+
+    #[test]
+    fn only_keeps_cells_which_could_repeat() {
+        // Every generation of a growing line is a new shape, so none of them are worth keeping.
+        let mut detector = PeriodDetector::new(16);
+        let mut world = World::Infinite(SparseGrid::new());
+        for generation in 0..8 {
+            world.set(Vec2Int::new(generation as i32 * 2, 0), State::Alive);
+            assert_eq!(detector.observe(generation, &world), None);
+        }
+        assert!(detector.sightings.iter().all(|sighting| sighting.cells.is_none()));
+    }
+
+    #[test]
+    fn a_pattern_with_the_same_shape_is_not_a_repeat() {
+        // Same population and bounding box every time, but the cells move about inside it.
+        let frames = [
+            [(0, 0), (2, 2), (1, 1)],
+            [(0, 0), (2, 2), (2, 0)],
+            [(0, 0), (2, 2), (0, 2)],
+        ];
+        let mut detector = PeriodDetector::new(16);
+        for (generation, frame) in frames.iter().enumerate() {
+            let mut world = World::Infinite(SparseGrid::new());
+            for &(x, y) in frame {
+                world.set(Vec2Int::new(x, y), State::Alive);
+            }
+            assert_eq!(detector.observe(generation as u64, &world), None);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use super::simple_serialization::{self, Format, Pattern, SerializationError};
+use super::{State, Vec2Int};
+use failure::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prefabs sitting straight in a library directory, rather than in a sub-directory.
+const UNCATEGORIZED: &str = "Uncategorized";
+
+/// A pattern from the prefab library, along with the metadata its file carried.
+#[derive(Debug, Clone)]
+pub struct Prefab {
+    pub name: String,
+    pub author: Option<String>,
+    pub description: String,
+    pub category: String,
+    pub cells: Vec<Vec<State>>,
+}
+
+impl Prefab {
+    pub fn width(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.first().map_or(0, |column| column.len())
+    }
+
+    /// Turns the prefab a quarter turn clockwise, as it appears on screen.
+    pub fn rotate_clockwise(&mut self) {
+        let (width, height) = (self.width(), self.height());
+        let mut rotated = vec![vec![State::Dead; width]; height];
+        for (x, column) in self.cells.iter().enumerate() {
+            for (y, state) in column.iter().enumerate() {
+                rotated[height - 1 - y][x] = *state;
+            }
+        }
+        self.cells = rotated;
+    }
+
+    /// Flips the prefab left to right.
+    pub fn mirror(&mut self) {
+        self.cells.reverse();
+    }
+
+    /// Where the prefab's corner goes so that its center lands on `center`.
+    pub fn anchor(&self, center: Vec2Int) -> Vec2Int {
+        center - Vec2Int::new(self.width() as i32 / 2, self.height() as i32 / 2)
+    }
+
+    /// Does this prefab's name, author, category or description contain `query`? Case is ignored.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+
+        let fields = [
+            Some(&self.name),
+            self.author.as_ref(),
+            Some(&self.category),
+            Some(&self.description),
+        ];
+        fields
+            .iter()
+            .filter_map(|field| *field)
+            .any(|field| field.to_lowercase().contains(&query))
+    }
+}
+
+/// Every prefab we found at startup, sorted by category and then by name.
+///
+/// Each directory is searched recursively, and the sub-directory a file sits in becomes its
+/// category. Files which can't be read are skipped with a warning, so one bad pattern
+/// doesn't cost us the whole library.
+#[derive(Debug, Default)]
+pub struct Prefabs {
+    prefabs: Vec<Prefab>,
+}
+
+impl Prefabs {
+    pub fn load(directories: &[PathBuf]) -> Prefabs {
+        let mut prefabs = Vec::new();
+        for directory in directories {
+            if directory.is_dir() {
+                Self::load_directory(directory, None, &mut prefabs);
+            } else {
+                info!("No prefab directory at {}", directory.display());
+            }
+        }
+
+        prefabs.sort_by(|a, b| (&a.category, &a.name).cmp(&(&b.category, &b.name)));
+        Prefabs { prefabs }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Prefab> {
+        self.prefabs.iter()
+    }
+
+    /// The category names, in order, without repeats.
+    pub fn categories(&self) -> Vec<&str> {
+        let mut categories: Vec<&str> = self.prefabs.iter().map(|prefab| prefab.category.as_str()).collect();
+        categories.dedup();
+        categories
+    }
+
+    /// Writes `prefab` out as RLE into `directory`, in a sub-directory named after its category,
+    /// and adds it to the library. A prefab with the same name and category is replaced.
+    pub fn save(&mut self, prefab: Prefab, directory: &Path) -> Result<PathBuf, Error> {
+        let category_directory = directory.join(file_stem(&prefab.category));
+        fs::create_dir_all(&category_directory).map_err(SerializationError::WriteToDisk)?;
+        let path = category_directory.join(format!("{}.rle", file_stem(&prefab.name)));
+
+        let mut pattern = Pattern::new(prefab.cells.clone());
+        pattern.name = Some(prefab.name.clone());
+        pattern.author = prefab.author.clone();
+        pattern.comments = prefab.description.lines().map(str::to_owned).collect();
+        simple_serialization::save_pattern(&pattern, &path)?;
+
+        self.prefabs
+            .retain(|other| (&other.category, &other.name) != (&prefab.category, &prefab.name));
+        self.prefabs.push(prefab);
+        self.prefabs
+            .sort_by(|a, b| (&a.category, &a.name).cmp(&(&b.category, &b.name)));
+
+        Ok(path)
+    }
+
+    fn load_directory(directory: &Path, category: Option<&str>, prefabs: &mut Vec<Prefab>) {
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Couldn't read prefab directory {} => {}", directory.display(), e);
+                return;
+            }
+        };
+
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.is_dir() {
+                let sub_category = category.map(str::to_owned).unwrap_or_else(|| file_title(&path));
+                Self::load_directory(&path, Some(&sub_category), prefabs);
+                continue;
+            }
+
+            if Format::from_extension(&path).is_none() {
+                continue;
+            }
+
+            match simple_serialization::load_pattern(&path) {
+                Ok(pattern) => prefabs.push(Prefab {
+                    name: pattern.name.unwrap_or_else(|| file_title(&path)),
+                    author: pattern.author,
+                    description: pattern.comments.join("\n"),
+                    category: category.unwrap_or(UNCATEGORIZED).to_owned(),
+                    cells: pattern.cells,
+                }),
+                Err(e) => warn!("Couldn't load prefab {} => {}", path.display(), e),
+            }
+        }
+    }
+}
+
+/// Turns a file name like `lw_spaceship.rle` into `Lw Spaceship`.
+fn file_title(path: &Path) -> String {
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("Untitled");
+    stem.split(|c: char| c == '_' || c == '-' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Turns a title like `Lw Spaceship` back into `lw_spaceship`, safe to use as a file name.
+fn file_stem(title: &str) -> String {
+    let stem: String = title
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+
+    if stem.is_empty() {
+        "untitled".to_owned()
+    } else {
+        stem
+    }
+}
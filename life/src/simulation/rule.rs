@@ -0,0 +1,254 @@
+use super::State;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// A Life-like birth/survival rule, written as a `B/S` rulestring such as `B3/S23`.
+/// Each field is a bitmask over neighbor counts, so bit `n` is set if `n` neighbors
+/// cause a birth (or allow a cell to survive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rule {
+    birth: u16,
+    survival: u16,
+}
+
+impl Rule {
+    pub const CONWAY: Rule = Rule {
+        birth: 1 << 3,
+        survival: 1 << 2 | 1 << 3,
+    };
+
+    pub const PRESETS: [(&'static str, &'static str); 6] = [
+        ("Conway's Life", "B3/S23"),
+        ("HighLife", "B36/S23"),
+        ("Day & Night", "B3678/S34678"),
+        ("Seeds", "B2/S"),
+        ("Life without Death", "B3/S012345678"),
+        ("Maze", "B3/S12345"),
+    ];
+
+    pub fn births_on(&self, count: u8) -> bool {
+        count < 9 && self.birth & (1 << count) != 0
+    }
+
+    pub fn survives_on(&self, count: u8) -> bool {
+        count < 9 && self.survival & (1 << count) != 0
+    }
+
+    /// Given the current state of a cell and how many of its eight neighbors are alive,
+    /// returns the state it will be in next generation.
+    pub fn next_state(&self, state: State, count: u8) -> State {
+        match state {
+            State::Alive => {
+                if self.survives_on(count) {
+                    State::Alive
+                } else {
+                    State::Dead
+                }
+            }
+
+            State::Dead | State::Unborn => {
+                if self.births_on(count) {
+                    State::Alive
+                } else {
+                    state
+                }
+            }
+        }
+    }
+
+    /// Applies the rule to 64 cells at once. `alive` holds the current cells, one per bit,
+    /// and `neighbors` holds the same 64 cells' eight neighbors, each shifted into place.
+    pub fn next_word(&self, alive: u64, neighbors: &[u64; 8]) -> u64 {
+        // Bit-sliced counter: bit `k` of the neighbor count for every cell in this word.
+        let mut count = [0u64; 4];
+        for &neighbor in neighbors {
+            let mut carry = neighbor;
+            for bit in count.iter_mut() {
+                let new_carry = *bit & carry;
+                *bit ^= carry;
+                carry = new_carry;
+            }
+        }
+
+        let mut result = 0;
+        for n in 0..9u8 {
+            let births = self.births_on(n);
+            let survives = self.survives_on(n);
+            if !births && !survives {
+                continue;
+            }
+
+            let mut equals_n = !0u64;
+            for (k, bit) in count.iter().enumerate() {
+                equals_n &= if (n >> k) & 1 == 1 { *bit } else { !*bit };
+            }
+
+            if births {
+                result |= equals_n & !alive;
+            }
+            if survives {
+                result |= equals_n & alive;
+            }
+        }
+        result
+    }
+
+    fn parse_counts(section: &str) -> Result<u16, RuleError> {
+        let mut mask = 0;
+        for c in section.chars() {
+            match c.to_digit(10) {
+                Some(count) if count <= 8 => mask |= 1 << count,
+                _ => return Err(RuleError::InvalidCharacter(c)),
+            }
+        }
+        Ok(mask)
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::CONWAY
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    /// Parses `B3/S23` style rulestrings (in either order, any case), as well as the older
+    /// `23/3` survival/birth notation.
+    fn from_str(rulestring: &str) -> Result<Rule, RuleError> {
+        let rulestring = rulestring.trim();
+        if rulestring.is_empty() {
+            return Err(RuleError::Empty);
+        }
+
+        let sections: Vec<&str> = rulestring.split('/').map(|s| s.trim()).collect();
+        if sections.len() != 2 {
+            return Err(RuleError::MissingSeparator(rulestring.to_owned()));
+        }
+
+        let mut birth = None;
+        let mut survival = None;
+        for (i, section) in sections.iter().enumerate() {
+            let mut chars = section.chars();
+            match chars.next() {
+                Some('B') | Some('b') => birth = Some(Self::parse_counts(chars.as_str())?),
+                Some('S') | Some('s') => survival = Some(Self::parse_counts(chars.as_str())?),
+                // Legacy notation: survival counts come first, then birth counts.
+                _ if i == 0 => survival = Some(Self::parse_counts(section)?),
+                _ => birth = Some(Self::parse_counts(section)?),
+            }
+        }
+
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok(Rule { birth, survival }),
+            _ => Err(RuleError::MissingSection(rulestring.to_owned())),
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "B")?;
+        for count in 0..9 {
+            if self.births_on(count) {
+                write!(f, "{}", count)?;
+            }
+        }
+        write!(f, "/S")?;
+        for count in 0..9 {
+            if self.survives_on(count) {
+                write!(f, "{}", count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum RuleError {
+    #[fail(display = "Rulestring is empty.")]
+    Empty,
+
+    #[fail(display = "Rulestring {} must have exactly one '/' separator.", _0)]
+    MissingSeparator(String),
+
+    #[fail(display = "Rulestring {} needs both a B and an S section.", _0)]
+    MissingSection(String),
+
+    #[fail(display = "'{}' is not a neighbor count between 0 and 8.", _0)]
+    InvalidCharacter(char),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(rulestring: &str) -> Rule {
+        rulestring.parse().unwrap()
+    }
+
+    fn counts(rule: &Rule) -> (Vec<u8>, Vec<u8>) {
+        let births = (0..9).filter(|&count| rule.births_on(count)).collect();
+        let survivals = (0..9).filter(|&count| rule.survives_on(count)).collect();
+        (births, survivals)
+    }
+
+    #[test]
+    fn parses_the_presets() {
+        assert_eq!(parse("B3/S23"), Rule::CONWAY);
+        assert_eq!(counts(&parse("B36/S23")), (vec![3, 6], vec![2, 3]));
+        assert_eq!(
+            counts(&parse("B3678/S34678")),
+            (vec![3, 6, 7, 8], vec![3, 4, 6, 7, 8])
+        );
+        assert_eq!(counts(&parse("B2/S")), (vec![2], vec![]));
+
+        for (name, rulestring) in Rule::PRESETS.iter() {
+            assert_eq!(parse(rulestring).to_string(), *rulestring, "{}", name);
+        }
+    }
+
+    #[test]
+    fn parses_other_spellings() {
+        assert_eq!(parse("23/3"), Rule::CONWAY);
+        assert_eq!(parse("/2"), parse("B2/S"));
+        assert_eq!(parse("s23/b3"), Rule::CONWAY);
+        assert_eq!(parse(" B3 / S23 "), Rule::CONWAY);
+        assert_eq!(parse("B0/S8").to_string(), "B0/S8");
+    }
+
+    #[test]
+    fn refuses_invalid_rulestrings() {
+        let error = |rulestring: &str| rulestring.parse::<Rule>().unwrap_err();
+        assert!(matches!(error(""), RuleError::Empty));
+        assert!(matches!(error("  "), RuleError::Empty));
+        assert!(matches!(error("B3S23"), RuleError::MissingSeparator(_)));
+        assert!(matches!(error("B3/S2/3"), RuleError::MissingSeparator(_)));
+        assert!(matches!(error("B3/B23"), RuleError::MissingSection(_)));
+        assert!(matches!(error("B9/S23"), RuleError::InvalidCharacter('9')));
+        assert!(matches!(error("B3/S2x"), RuleError::InvalidCharacter('x')));
+    }
+
+    #[test]
+    fn next_word_agrees_with_next_state() {
+        for rule in Rule::PRESETS.iter().map(|(_, rulestring)| parse(rulestring)) {
+            // Cell `n` has `n % 9` live neighbors, and is alive for `n` from 9 to 17.
+            let mut neighbors = [0u64; 8];
+            for n in 0..18 {
+                for neighbor in neighbors.iter_mut().take(n % 9) {
+                    *neighbor |= 1 << n;
+                }
+            }
+            let alive = 0x1ff << 9;
+
+            let next = rule.next_word(alive, &neighbors);
+            for n in 0..18 {
+                let state = [State::Dead, State::Alive][(alive >> n & 1) as usize];
+                let count = (n % 9) as u8;
+                let expected = rule.next_state(state, count) == State::Alive;
+                assert_eq!(next >> n & 1 == 1, expected, "{} with {} neighbors", rule, count);
+            }
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use super::simple_serialization;
+use super::{
+    Catalogue, Census, CensusEntry, Rule, Soup, SparseGrid, Symmetry, Topology, Vec2Int, World, WorldError,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, Receiver},
+    Arc,
+};
+use std::thread;
+
+/// Objects turning up in nearly every soup, which aren't worth writing down.
+pub const COMMON_OBJECTS: &[&str] = &[
+    "Block", "Beehive", "Loaf", "Boat", "Tub", "Ship", "Pond", "Blinker", "Glider",
+];
+
+/// The longest period a settled soup's population is checked for. Long enough to cover a
+/// pentadecathlon sitting next to a pulsar.
+const MAX_STABLE_PERIOD: usize = 30;
+
+/// How many times the population has to go round its period before the soup counts as
+/// settled. Short periods have to hold for at least `MAX_STABLE_PERIOD` generations too.
+const STABLE_REPEATS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchSettings {
+    /// Soups are this many cells along each side.
+    pub soup_size: usize,
+    pub density: f32,
+    pub symmetry: Symmetry,
+    pub rule: Rule,
+    /// Soups still going after this many generations are given up on, and written down.
+    pub max_generations: u64,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        SearchSettings {
+            soup_size: 16,
+            density: 0.5,
+            symmetry: Symmetry::None,
+            rule: Rule::default(),
+            max_generations: 10_000,
+        }
+    }
+}
+
+/// A soup which left something unusual behind. Running the soup with the same seed,
+/// size, density, symmetry and rule gives it back.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchFind {
+    pub seed: u64,
+    pub soup_size: usize,
+    pub density: f32,
+    pub symmetry: String,
+    pub rule: String,
+    /// How long the soup took to settle, or how long it was run for if it didn't.
+    pub generations: u64,
+    pub stabilized: bool,
+    /// Everything it left behind that isn't in `COMMON_OBJECTS`.
+    pub objects: Vec<CensusEntry>,
+}
+
+/// Runs one seeded soup after another, keeping count of every object they leave behind.
+/// Seeds are used in order, so a search can be picked up again from `next_seed`.
+#[derive(Debug)]
+pub struct SoupSearch {
+    settings: SearchSettings,
+    catalogue: Catalogue,
+    next_seed: u64,
+    soups: u64,
+    finds: u64,
+    tally: HashMap<String, usize>,
+}
+
+impl SoupSearch {
+    /// Fails if the rule can't be run on the infinite board soups are run on.
+    pub fn new(settings: SearchSettings, first_seed: u64) -> Result<Self, WorldError> {
+        World::Infinite(SparseGrid::new()).check_rule(&settings.rule)?;
+        Ok(SoupSearch {
+            catalogue: Catalogue::new(&settings.rule),
+            settings,
+            next_seed: first_seed,
+            soups: 0,
+            finds: 0,
+            tally: HashMap::new(),
+        })
+    }
+
+    pub fn settings(&self) -> &SearchSettings {
+        &self.settings
+    }
+
+    pub fn next_seed(&self) -> u64 {
+        self.next_seed
+    }
+
+    pub fn soups_searched(&self) -> u64 {
+        self.soups
+    }
+
+    pub fn finds(&self) -> u64 {
+        self.finds
+    }
+
+    /// How many of each object every soup so far has left behind, most common first.
+    pub fn tally(&self) -> Vec<(String, usize)> {
+        let mut tally: Vec<(String, usize)> =
+            self.tally.iter().map(|(name, count)| (name.clone(), *count)).collect();
+        tally.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        tally
+    }
+
+    /// Runs the next `count` soups, spread over rayon's threads, and returns the ones worth keeping.
+    pub fn run(&mut self, count: u64) -> Vec<SearchFind> {
+        let (settings, catalogue) = (&self.settings, &self.catalogue);
+        let first_seed = self.next_seed;
+        // `new` has already turned away any rule `run_soup` would refuse.
+        let results: Vec<(u64, Census, u64, bool)> = (0..count)
+            .into_par_iter()
+            .filter_map(|i| {
+                let seed = first_seed.wrapping_add(i);
+                let (census, generations, stabilized) = run_soup(seed, settings, catalogue).ok()?;
+                Some((seed, census, generations, stabilized))
+            })
+            .collect();
+
+        self.next_seed = first_seed.wrapping_add(count);
+        self.soups += count;
+
+        let mut finds = Vec::new();
+        for (seed, census, generations, stabilized) in results {
+            for entry in &census.entries {
+                *self.tally.entry(entry.name.clone()).or_insert(0) += entry.count;
+            }
+
+            let objects: Vec<CensusEntry> = census
+                .entries
+                .into_iter()
+                .filter(|entry| !COMMON_OBJECTS.contains(&entry.name.as_str()))
+                .collect();
+            if objects.is_empty() && stabilized {
+                continue;
+            }
+
+            finds.push(SearchFind {
+                seed,
+                soup_size: self.settings.soup_size,
+                density: self.settings.density,
+                symmetry: self.settings.symmetry.to_string(),
+                rule: self.settings.rule.to_string(),
+                generations,
+                stabilized,
+                objects,
+            });
+        }
+
+        self.finds += finds.len() as u64;
+        finds
+    }
+}
+
+/// Runs a soup on an empty, infinite board until its population settles into a cycle, then
+/// takes a census. Returns the census, how many generations it ran and whether it settled.
+pub fn run_soup(
+    seed: u64,
+    settings: &SearchSettings,
+    catalogue: &Catalogue,
+) -> Result<(Census, u64, bool), WorldError> {
+    let soup = Soup::new(seed, settings.density, settings.symmetry);
+    let size = settings.soup_size.max(1) as i32;
+    let mut world = World::Infinite(SparseGrid::new());
+    soup.fill(&mut world, Vec2Int::ZERO, Vec2Int::new(size - 1, size - 1));
+
+    let window = MAX_STABLE_PERIOD * (STABLE_REPEATS + 1);
+    let mut populations: VecDeque<usize> = VecDeque::with_capacity(window + 1);
+    let mut generation = 0;
+    let mut stabilized = false;
+    while generation < settings.max_generations {
+        world.step(&settings.rule, Topology::Dead, None)?;
+        generation += 1;
+
+        populations.push_back(world.population());
+        if populations.len() > window {
+            populations.pop_front();
+        }
+        if is_stable(&populations) {
+            stabilized = true;
+            break;
+        }
+    }
+
+    Ok((Census::take_with(&world, catalogue), generation, stabilized))
+}
+
+/// Whether the most recent populations have gone round the same cycle `STABLE_REPEATS` times.
+fn is_stable(populations: &VecDeque<usize>) -> bool {
+    let len = populations.len();
+    (1..=MAX_STABLE_PERIOD).any(|period| {
+        let span = (period * STABLE_REPEATS).max(MAX_STABLE_PERIOD);
+        span + period <= len && (len - span..len).all(|i| populations[i] == populations[i - period])
+    })
+}
+
+/// Where a background search has got to, sent after every batch of soups.
+#[derive(Debug, Clone)]
+pub struct SearchProgress {
+    pub soups_searched: u64,
+    pub next_seed: u64,
+    pub total_finds: u64,
+    /// The finds from this batch alone.
+    pub finds: Vec<SearchFind>,
+    pub tally: Vec<(String, usize)>,
+}
+
+/// A search running on its own thread, writing its finds to `results` as it goes. It stops
+/// once it's told to or dropped, after finishing the batch it's on. Neither waits for that:
+/// the thread is left to end on its own, so keep hold of a stopped search until
+/// `is_finished` to get its last batch.
+#[derive(Debug)]
+pub struct BackgroundSearch {
+    stop: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+    progress: Receiver<SearchProgress>,
+}
+
+impl BackgroundSearch {
+    pub fn start(mut search: SoupSearch, batch: u64, results: PathBuf) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let finished = Arc::new(AtomicBool::new(false));
+        let (sender, progress) = mpsc::channel();
+
+        let thread_stop = stop.clone();
+        let thread_finished = finished.clone();
+        thread::spawn(move || {
+            let _finished = Finished(thread_finished);
+            while !thread_stop.load(Ordering::Relaxed) {
+                let finds = search.run(batch.max(1));
+                if !finds.is_empty() {
+                    if let Err(e) = simple_serialization::append_search_finds(&finds, &results) {
+                        error!("Couldn't write search results to {} => {}", results.display(), e);
+                    }
+                }
+
+                let progress = SearchProgress {
+                    soups_searched: search.soups_searched(),
+                    next_seed: search.next_seed(),
+                    total_finds: search.finds(),
+                    finds,
+                    tally: search.tally(),
+                };
+                if sender.send(progress).is_err() {
+                    break;
+                }
+            }
+        });
+
+        BackgroundSearch {
+            stop,
+            finished,
+            progress,
+        }
+    }
+
+    /// Everything the search has sent since last time, oldest first.
+    pub fn poll(&self) -> Vec<SearchProgress> {
+        self.progress.try_iter().collect()
+    }
+
+    /// Tells the search to stop after the batch it's on, without waiting for it.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the thread has ended, after which it has sent everything it's going to.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for BackgroundSearch {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Marks a search finished once its thread ends, panicking or not. Nothing joins the
+/// thread, so this is also where a panic gets noticed.
+struct Finished(Arc<AtomicBool>);
+
+impl Drop for Finished {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            error!("The soup search thread panicked.");
+        }
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    fn settings() -> SearchSettings {
+        SearchSettings {
+            soup_size: 6,
+            max_generations: 1000,
+            ..SearchSettings::default()
+        }
+    }
+
+    fn populations(values: impl IntoIterator<Item = usize>) -> VecDeque<usize> {
+        values.into_iter().collect()
+    }
+
+    #[test]
+    fn settles_once_the_population_cycles() {
+        let window = MAX_STABLE_PERIOD * (STABLE_REPEATS + 1);
+
+        assert!(is_stable(&populations(vec![12; MAX_STABLE_PERIOD + 1])));
+        assert!(!is_stable(&populations(vec![12; MAX_STABLE_PERIOD])));
+
+        // A blinker and a pulsar together go round every 6 generations.
+        let cycle = [62, 62, 66, 62, 62, 66];
+        let settled = (0..window).map(|i| cycle[i % cycle.len()]);
+        assert!(is_stable(&populations(settled)));
+
+        // One generation out of step is enough to keep going.
+        let mut almost: VecDeque<usize> = (0..window).map(|i| cycle[i % cycle.len()]).collect();
+        almost[window - 2] += 1;
+        assert!(!is_stable(&almost));
+
+        assert!(!is_stable(&populations(0..window)));
+        assert!(!is_stable(&populations(vec![])));
+    }
+
+    #[test]
+    fn soups_come_out_the_same_from_the_same_seed() {
+        let settings = settings();
+        let catalogue = Catalogue::new(&settings.rule);
+        for seed in 0..2 {
+            let first = run_soup(seed, &settings, &catalogue).unwrap();
+            let second = run_soup(seed, &settings, &catalogue).unwrap();
+            assert_eq!(first, second);
+        }
+    }
+
+    #[test]
+    fn searches_come_out_the_same_from_the_same_seed() {
+        let mut search = SoupSearch::new(settings(), 1000).unwrap();
+        let finds = search.run(6);
+
+        // However the soups are split into batches, and whichever threads run them.
+        let mut again = SoupSearch::new(settings(), 1000).unwrap();
+        let mut finds_again = again.run(2);
+        finds_again.extend(again.run(4));
+
+        assert_eq!(finds, finds_again);
+        assert_eq!(search.tally(), again.tally());
+        assert_eq!(search.finds(), again.finds());
+        assert_eq!((search.next_seed(), search.soups_searched()), (1006, 6));
+        assert_eq!((again.next_seed(), again.soups_searched()), (1006, 6));
+        assert!(finds.iter().all(|find| (1000..1006).contains(&find.seed)));
+    }
+
+    #[test]
+    fn refuses_rules_which_need_a_bounded_world() {
+        let settings = SearchSettings {
+            rule: "B0/S8".parse().unwrap(),
+            ..settings()
+        };
+        assert!(SoupSearch::new(settings, 0).is_err());
+    }
+
+    #[test]
+    fn background_searches_stop_when_told() {
+        let results = env::temp_dir().join(format!("life-search-{}.json", process::id()));
+        let search = BackgroundSearch::start(SoupSearch::new(settings(), 0).unwrap(), 1, results.clone());
+
+        let mut progress = vec![];
+        while progress.is_empty() {
+            progress = search.poll();
+            thread::yield_now();
+        }
+        search.stop();
+        while !search.is_finished() {
+            thread::yield_now();
+        }
+        progress.extend(search.poll());
+        let _ = fs::remove_file(&results);
+
+        let last = progress.last().unwrap();
+        assert_eq!(last.soups_searched, progress.len() as u64);
+        assert_eq!(last.next_seed, last.soups_searched);
+    }
+}
@@ -0,0 +1,37 @@
+use super::Vec2Int;
+
+/// A rectangle of cells, dragged out from `start` to `end`. Both corners are inside it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Selection {
+    pub start: Vec2Int,
+    pub end: Vec2Int,
+}
+
+impl Selection {
+    pub fn new(start: Vec2Int) -> Self {
+        Selection { start, end: start }
+    }
+
+    pub fn min(&self) -> Vec2Int {
+        Vec2Int::new(self.start.x.min(self.end.x), self.start.y.min(self.end.y))
+    }
+
+    pub fn max(&self) -> Vec2Int {
+        Vec2Int::new(self.start.x.max(self.end.x), self.start.y.max(self.end.y))
+    }
+
+    pub fn size(&self) -> Vec2Int {
+        self.max() - self.min() + Vec2Int::new(1, 1)
+    }
+
+    pub fn contains(&self, pos: Vec2Int) -> bool {
+        let (min, max) = (self.min(), self.max());
+        pos.x >= min.x && pos.x <= max.x && pos.y >= min.y && pos.y <= max.y
+    }
+
+    /// Every cell in the selection, column by column.
+    pub fn positions(&self) -> impl Iterator<Item = Vec2Int> {
+        let (min, max) = (self.min(), self.max());
+        (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| Vec2Int::new(x, y)))
+    }
+}
@@ -0,0 +1,119 @@
+use super::{cells_from_alive, Pattern, SerializationError, MAX_PATTERN_CELLS};
+
+/// Reads a plaintext `.cells` pattern: `!` comment lines, then one line per row with `.`
+/// for a dead cell and `O` for a live one. Short rows are padded out with dead cells.
+pub fn parse(text: &str) -> Result<Pattern, SerializationError> {
+    let mut pattern = Pattern::default();
+    let mut alive_cells = Vec::new();
+    let mut width = 0;
+    let mut rows = 0;
+    let mut height = 0;
+
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.starts_with('!') {
+            read_comment(line, &mut pattern);
+            continue;
+        }
+
+        let y = rows;
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                '.' => {}
+                'O' | '*' => alive_cells.push((x, y)),
+                c => return Err(SerializationError::InvalidCells(format!("unexpected character '{}'", c))),
+            }
+        }
+
+        // Trailing blank lines are just the end of the file, not rows of dead cells.
+        rows += 1;
+        if !line.is_empty() {
+            height = rows;
+        }
+
+        width = width.max(line.chars().count());
+        if width > MAX_PATTERN_CELLS || height > MAX_PATTERN_CELLS {
+            return Err(SerializationError::TooLarge(width, height));
+        }
+    }
+
+    pattern.cells = cells_from_alive(width, height, &alive_cells)?;
+
+    Ok(pattern)
+}
+
+/// Writes a pattern out in plaintext. Anything which isn't `State::Alive` is written as dead.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("!Name: {}\n", name));
+    }
+    if let Some(author) = &pattern.author {
+        out.push_str(&format!("!Author: {}\n", author));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("!{}\n", comment));
+    }
+
+    let mut rows = vec![vec!['.'; pattern.width()]; pattern.height()];
+    for (x, y) in pattern.alive_cells() {
+        rows[y][x] = 'O';
+    }
+    for row in rows {
+        out.extend(row);
+        out.push('\n');
+    }
+
+    out
+}
+
+fn read_comment(line: &str, pattern: &mut Pattern) {
+    let text = line[1..].trim();
+    if let Some(name) = text.strip_prefix("Name:") {
+        pattern.name = Some(name.trim().to_owned());
+    } else if let Some(author) = text.strip_prefix("Author:") {
+        pattern.author = Some(author.trim().to_owned());
+    } else if !text.is_empty() {
+        pattern.comments.push(text.to_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_comments_and_rows() {
+        let text = "!Name: Glider\n!Author: Richard K. Guy\n!The smallest spaceship.\n!\n.O\n..O\nOOO\n";
+        let pattern = parse(text).unwrap();
+
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.author.as_deref(), Some("Richard K. Guy"));
+        assert_eq!(pattern.comments, vec!["The smallest spaceship."]);
+        assert_eq!((pattern.width(), pattern.height()), (3, 3));
+        let alive: Vec<(usize, usize)> = pattern.alive_cells().collect();
+        assert_eq!(alive, vec![(0, 2), (1, 0), (1, 2), (2, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn keeps_blank_rows_inside_the_pattern_only() {
+        let pattern = parse("O*\n\n.O\n\n\n").unwrap();
+
+        assert_eq!((pattern.width(), pattern.height()), (2, 3));
+        let alive: Vec<(usize, usize)> = pattern.alive_cells().collect();
+        assert_eq!(alive, vec![(0, 0), (1, 0), (1, 2)]);
+    }
+
+    #[test]
+    fn refuses_other_characters() {
+        assert!(parse(".O\nOxO").is_err());
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let text = "!Name: Boat\n!Author: Nobody\n!A still life.\nOO.\nO.O\n.O.\n";
+        let pattern = parse(text).unwrap();
+        assert_eq!(write(&pattern), text);
+        assert_eq!(parse(&write(&pattern)).unwrap(), pattern);
+    }
+}
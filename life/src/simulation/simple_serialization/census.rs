@@ -0,0 +1,44 @@
+use super::super::{Census, SearchFind};
+use super::SerializationError;
+use serde_json;
+
+/// Writes a census as a JSON array of `{ name, kind, cells, count }` objects.
+pub fn write_json(census: &Census) -> Result<String, SerializationError> {
+    serde_json::to_string_pretty(&census.entries).map_err(SerializationError::Serialize)
+}
+
+/// Writes a census as CSV with a `name,kind,cells,count` header row.
+pub fn write_csv(census: &Census) -> String {
+    let mut out = "name,kind,cells,count\n".to_owned();
+    for entry in &census.entries {
+        out.push_str(&format!(
+            "{},{},{},{}\n",
+            quote(&entry.name),
+            quote(&entry.kind),
+            entry.cells,
+            entry.count
+        ));
+    }
+
+    out
+}
+
+/// Quotes a field if it needs it, doubling any quotes inside.
+fn quote(field: &str) -> String {
+    if field.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Writes search finds as JSON lines, one find per line.
+pub fn write_search_finds(finds: &[SearchFind]) -> Result<String, SerializationError> {
+    let mut out = String::new();
+    for find in finds {
+        out.push_str(&serde_json::to_string(find).map_err(SerializationError::Serialize)?);
+        out.push('\n');
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,104 @@
+use super::{cells_from_alive, Pattern, SerializationError, MAX_PATTERN_CELLS};
+
+const HEADER: &str = "#Life 1.06";
+
+/// Reads a Life 1.06 pattern: a `#Life 1.06` header, then the `x y` coordinates of each
+/// live cell, one per line. The pattern is moved so its top-left corner sits at the origin.
+pub fn parse(text: &str) -> Result<Pattern, SerializationError> {
+    let mut pattern = Pattern::default();
+    let mut coordinates: Vec<(i64, i64)> = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(HEADER) {
+            continue;
+        }
+
+        // Not in the spec, but some tools leave a description behind.
+        if let Some(description) = line.strip_prefix("#D") {
+            pattern.comments.push(description.trim().to_owned());
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let mut numbers = line.split_whitespace().map(|number| number.parse::<i64>());
+        match (numbers.next(), numbers.next(), numbers.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => coordinates.push((x, y)),
+            _ => return Err(SerializationError::InvalidLife106(format!("bad coordinate `{}`", line))),
+        }
+    }
+
+    if coordinates.is_empty() {
+        return Ok(pattern);
+    }
+
+    let min_x = coordinates.iter().map(|(x, _)| *x).min().unwrap();
+    let min_y = coordinates.iter().map(|(_, y)| *y).min().unwrap();
+    let max_x = coordinates.iter().map(|(x, _)| *x).max().unwrap();
+    let max_y = coordinates.iter().map(|(_, y)| *y).max().unwrap();
+
+    let width = max_x.saturating_sub(min_x).saturating_add(1) as u64;
+    let height = max_y.saturating_sub(min_y).saturating_add(1) as u64;
+    if width > MAX_PATTERN_CELLS as u64 || height > MAX_PATTERN_CELLS as u64 {
+        return Err(SerializationError::TooLarge(width as usize, height as usize));
+    }
+
+    let alive_cells: Vec<(usize, usize)> = coordinates
+        .iter()
+        .map(|(x, y)| ((x - min_x) as usize, (y - min_y) as usize))
+        .collect();
+    pattern.cells = cells_from_alive(width as usize, height as usize, &alive_cells)?;
+
+    Ok(pattern)
+}
+
+/// Writes out the live cells of a pattern. Life 1.06 has nowhere to put the metadata,
+/// so it's dropped.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = format!("{}\n", HEADER);
+    for (x, y) in pattern.alive_cells() {
+        out.push_str(&format!("{} {}\n", x, y));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_the_pattern_to_the_origin() {
+        let text = "#Life 1.06\n#D A glider, somewhere off to the left.\n\
+                    -9 -4\n-8 -3\n-10 -2\n-9 -2\n-8 -2\n";
+        let pattern = parse(text).unwrap();
+
+        assert_eq!(pattern.comments, vec!["A glider, somewhere off to the left."]);
+        assert_eq!((pattern.width(), pattern.height()), (3, 3));
+        let alive: Vec<(usize, usize)> = pattern.alive_cells().collect();
+        assert_eq!(alive, vec![(0, 2), (1, 0), (1, 2), (2, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn reads_an_empty_pattern() {
+        let pattern = parse("#Life 1.06\n").unwrap();
+        assert_eq!(pattern.width(), 0);
+    }
+
+    #[test]
+    fn refuses_bad_coordinates() {
+        assert!(parse("#Life 1.06\n1 2 3\n").is_err());
+        assert!(parse("#Life 1.06\n1\n").is_err());
+        assert!(parse("#Life 1.06\n1 two\n").is_err());
+        assert!(parse("#Life 1.06\n0 0\n100000000 0\n").is_err());
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let text = "#Life 1.06\n0 0\n0 1\n1 1\n2 0\n";
+        let pattern = parse(text).unwrap();
+        assert_eq!(write(&pattern), text);
+    }
+}
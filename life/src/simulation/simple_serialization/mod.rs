@@ -0,0 +1,302 @@
+use super::{Census, Rule, SearchFind, State};
+use failure::Error;
+use serde_json;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+pub mod cells;
+pub mod census;
+pub mod life106;
+pub mod rle;
+
+/// Patterns bigger than this are almost certainly a typo in the file, so we refuse
+/// to allocate them.
+const MAX_PATTERN_CELLS: usize = 1 << 26;
+
+/// A pattern along with whatever metadata its file carried. Cells are indexed `[x][y]`,
+/// the same layout prefabs and `World::paste` use.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pattern {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+    pub rule: Option<Rule>,
+    pub cells: Vec<Vec<State>>,
+}
+
+impl Pattern {
+    pub fn new(cells: Vec<Vec<State>>) -> Self {
+        Pattern {
+            cells,
+            ..Pattern::default()
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.first().map_or(0, |column| column.len())
+    }
+
+    /// Every live cell, as `(x, y)`.
+    pub fn alive_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells.iter().enumerate().flat_map(|(x, column)| {
+            column
+                .iter()
+                .enumerate()
+                .filter(|(_, state)| **state == State::Alive)
+                .map(move |(y, _)| (x, y))
+        })
+    }
+}
+
+/// The file formats we can read and write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Rle,
+    Cells,
+    Life106,
+}
+
+impl Format {
+    /// Picks a format by file extension, falling back to JSON.
+    pub fn from_path(location: &Path) -> Format {
+        Format::from_extension(location).unwrap_or(Format::Json)
+    }
+
+    /// Picks a format by file extension, or `None` if it isn't a pattern file at all.
+    pub fn from_extension(path: &Path) -> Option<Format> {
+        let extension = path.extension().and_then(|ext| ext.to_str())?;
+        match extension.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "rle" => Some(Format::Rle),
+            "cells" => Some(Format::Cells),
+            "lif" | "life" => Some(Format::Life106),
+            _ => None,
+        }
+    }
+
+    /// Works out a format from the text itself, so patterns copied off a wiki don't need
+    /// the right extension. Returns `None` if nothing gives it away.
+    pub fn detect(text: &str) -> Option<Format> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let first_line = lines.next()?;
+
+        if first_line.starts_with("#Life 1.06") {
+            return Some(Format::Life106);
+        }
+        if first_line.starts_with('[') {
+            return Some(Format::Json);
+        }
+        if first_line.starts_with('!') || first_line.chars().all(|c| c == '.' || c == 'O' || c == '*') {
+            return Some(Format::Cells);
+        }
+
+        let is_rle_header = |line: &str| line.starts_with('x') && line.contains('=');
+        if is_rle_header(first_line) || (first_line.starts_with('#') && lines.any(is_rle_header)) {
+            return Some(Format::Rle);
+        }
+
+        None
+    }
+}
+
+pub fn save(data: &[Vec<State>], location: &str) -> Result<(), Error> {
+    save_pattern(&Pattern::new(data.to_vec()), location)
+}
+
+/// Saves a pattern in whichever format the extension of `location` asks for.
+/// JSON has nowhere to put the metadata, so it's dropped.
+pub fn save_pattern<P: AsRef<Path>>(pattern: &Pattern, location: P) -> Result<(), Error> {
+    let location = location.as_ref();
+    let text = match Format::from_path(location) {
+        Format::Json => {
+            serde_json::to_string(&flip_vector(&pattern.cells)).map_err(SerializationError::Serialize)?
+        }
+        Format::Rle => rle::write(pattern),
+        Format::Cells => cells::write(pattern),
+        Format::Life106 => life106::write(pattern),
+    };
+    fs::write(location, text).map_err(SerializationError::WriteToDisk)?;
+
+    Ok(())
+}
+
+/// Saves a census as CSV if `location` ends in `.csv`, and as JSON otherwise.
+pub fn save_census<P: AsRef<Path>>(census: &Census, location: P) -> Result<(), Error> {
+    let location = location.as_ref();
+    let is_csv = location
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let text = if is_csv {
+        census::write_csv(census)
+    } else {
+        census::write_json(census)?
+    };
+    fs::write(location, text).map_err(SerializationError::WriteToDisk)?;
+
+    Ok(())
+}
+
+/// Adds search finds to the end of `location`, one JSON object per line, so a long search
+/// loses nothing if it's stopped.
+pub fn append_search_finds<P: AsRef<Path>>(finds: &[SearchFind], location: P) -> Result<(), Error> {
+    let text = census::write_search_finds(finds)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(location)
+        .map_err(SerializationError::WriteToDisk)?;
+    file.write_all(text.as_bytes())
+        .map_err(SerializationError::WriteToDisk)?;
+
+    Ok(())
+}
+
+/// Loads a pattern, working out its format from the contents of the file and only
+/// falling back on the extension of `location` if that's ambiguous.
+pub fn load_pattern<P: AsRef<Path>>(location: P) -> Result<Pattern, Error> {
+    let location = location.as_ref();
+    let text = fs::read_to_string(location).map_err(SerializationError::ReadFromDisk)?;
+    let format = Format::detect(&text).unwrap_or_else(|| Format::from_path(location));
+
+    parse(&text, format)
+}
+
+/// Reads a pattern out of `text`, which is in the given format.
+pub fn parse(text: &str, format: Format) -> Result<Pattern, Error> {
+    let pattern = match format {
+        Format::Json => {
+            let prefab: Vec<Vec<State>> =
+                serde_json::from_str(text).map_err(SerializationError::Deserialize)?;
+            Pattern::new(flip_vector(&prefab))
+        }
+        Format::Rle => rle::parse(text)?,
+        Format::Cells => cells::parse(text)?,
+        Format::Life106 => life106::parse(text)?,
+    };
+
+    Ok(pattern)
+}
+
+/// Lays out a `width` by `height` pattern of dead cells with the given cells alive.
+fn cells_from_alive(
+    width: usize,
+    height: usize,
+    alive_cells: &[(usize, usize)],
+) -> Result<Vec<Vec<State>>, SerializationError> {
+    if width.saturating_mul(height) > MAX_PATTERN_CELLS {
+        return Err(SerializationError::TooLarge(width, height));
+    }
+
+    let mut cells = vec![vec![State::Dead; height]; width];
+    for &(x, y) in alive_cells {
+        cells[x][y] = State::Alive;
+    }
+
+    Ok(cells)
+}
+
+fn flip_vector(original: &[Vec<State>]) -> Vec<Vec<State>> {
+    // iterate over the Vec:
+    let mut ret: Vec<Vec<State>> = vec![];
+    if original.is_empty() {
+        return ret;
+    }
+
+    for _ in 0..original[0].len() {
+        let mut this_one = Vec::with_capacity(original.len());
+        for _ in 0..this_one.capacity() {
+            this_one.push(State::Dead);
+        }
+        ret.push(this_one);
+    }
+
+    for (x, this_row) in original.iter().enumerate() {
+        for (y, this_entity) in this_row.iter().enumerate() {
+            ret[y][x] = *this_entity;
+        }
+    }
+
+    ret
+}
+
+#[derive(Debug, Fail)]
+pub enum SerializationError {
+    #[fail(display = "Could not serialize.")]
+    Serialize(#[fail(cause)] serde_json::error::Error),
+
+    #[fail(display = "Could not deserialize.")]
+    Deserialize(#[fail(cause)] serde_json::error::Error),
+
+    #[fail(display = "Could not Write to Disk.")]
+    WriteToDisk(#[fail(cause)] std::io::Error),
+
+    #[fail(display = "Could not Read from Disk")]
+    ReadFromDisk(#[fail(cause)] std::io::Error),
+
+    #[fail(display = "Invalid RLE: {}", _0)]
+    InvalidRle(String),
+
+    #[fail(display = "Invalid plaintext pattern: {}", _0)]
+    InvalidCells(String),
+
+    #[fail(display = "Invalid Life 1.06 pattern: {}", _0)]
+    InvalidLife106(String),
+
+    #[fail(display = "Pattern is too large ({} x {}).", _0, _1)]
+    TooLarge(usize, usize),
+
+    #[fail(display = "Invalid rule in pattern: {}", _0)]
+    InvalidRule(#[fail(cause)] super::RuleError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats_from_the_text() {
+        let detect = Format::detect;
+        assert_eq!(detect("#Life 1.06\n0 0\n"), Some(Format::Life106));
+        assert_eq!(detect("[[\"Alive\"]]"), Some(Format::Json));
+        assert_eq!(detect("!Name: Blinker\nOOO\n"), Some(Format::Cells));
+        assert_eq!(detect("\n.O.\n..O\nOOO\n"), Some(Format::Cells));
+        assert_eq!(detect("x = 3, y = 1, rule = B3/S23\n3o!"), Some(Format::Rle));
+        assert_eq!(detect("#N Blinker\n#C P2\nx = 3, y = 1\n3o!"), Some(Format::Rle));
+        assert_eq!(detect("#N Blinker\n3o!"), None);
+        assert_eq!(detect("hello"), None);
+        assert_eq!(detect(""), None);
+    }
+
+    #[test]
+    fn detected_formats_read_the_same_pattern() {
+        let texts = [
+            "x = 3, y = 2\nbo$3o!",
+            "!Name: T\n.O.\nOOO\n",
+            "#Life 1.06\n1 0\n0 1\n1 1\n2 1\n",
+            "[[\"Dead\",\"Alive\",\"Dead\"],[\"Alive\",\"Alive\",\"Alive\"]]",
+        ];
+        for text in texts.iter() {
+            let pattern = parse(text, Format::detect(text).unwrap()).unwrap();
+            let alive: Vec<(usize, usize)> = pattern.alive_cells().collect();
+            assert_eq!(alive, vec![(0, 1), (1, 0), (1, 1), (2, 1)], "{}", text);
+        }
+    }
+
+    #[test]
+    fn picks_formats_by_extension() {
+        let from_extension = |path: &str| Format::from_extension(Path::new(path));
+        assert_eq!(from_extension("a/glider.RLE"), Some(Format::Rle));
+        assert_eq!(from_extension("glider.cells"), Some(Format::Cells));
+        assert_eq!(from_extension("glider.lif"), Some(Format::Life106));
+        assert_eq!(from_extension("glider.life"), Some(Format::Life106));
+        assert_eq!(from_extension("glider.txt"), None);
+        assert_eq!(Format::from_path(Path::new("glider")), Format::Json);
+    }
+}
@@ -0,0 +1,324 @@
+use super::{cells_from_alive, Pattern, Rule, SerializationError, State, MAX_PATTERN_CELLS};
+
+/// RLE lines are kept under this length when writing, as the format asks.
+const MAX_LINE_LENGTH: usize = 70;
+
+/// Reads a run-length-encoded pattern: any `#` comment lines, then an
+/// `x = .., y = .., rule = ..` header, then the cells, finishing with a `!`.
+pub fn parse(text: &str) -> Result<Pattern, SerializationError> {
+    let mut pattern = Pattern::default();
+    let mut header: Option<(usize, usize)> = None;
+    let mut body = String::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if header.is_none() {
+            if line.starts_with('#') {
+                read_comment(line, &mut pattern)?;
+            } else {
+                header = Some(read_header(line, &mut pattern)?);
+            }
+            continue;
+        }
+
+        body.push_str(line);
+        if line.contains('!') {
+            break;
+        }
+    }
+
+    let (header_width, header_height) =
+        header.ok_or_else(|| SerializationError::InvalidRle("missing the `x = .., y = ..` header".to_owned()))?;
+
+    let mut alive_cells = Vec::new();
+    let mut x = 0;
+    let mut y = 0;
+    let mut run: Option<usize> = None;
+    for c in body.chars() {
+        let count = run.unwrap_or(1);
+        match c {
+            '0'..='9' => {
+                let digit = c.to_digit(10).unwrap() as usize;
+                let next_run = run
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|run| run.checked_add(digit))
+                    .filter(|run| *run <= MAX_PATTERN_CELLS)
+                    .ok_or_else(|| SerializationError::InvalidRle("run length is too long".to_owned()))?;
+
+                run = Some(next_run);
+                continue;
+            }
+            'b' | '.' => x += count,
+            '$' => {
+                x = 0;
+                y += count;
+            }
+            '!' => break,
+            c if c.is_ascii_alphabetic() => {
+                for _ in 0..count {
+                    alive_cells.push((x, y));
+                    x += 1;
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => return Err(SerializationError::InvalidRle(format!("unexpected character '{}'", c))),
+        }
+
+        if x > MAX_PATTERN_CELLS || y > MAX_PATTERN_CELLS {
+            return Err(SerializationError::TooLarge(x, y));
+        }
+        run = None;
+    }
+
+    let width = alive_cells.iter().map(|(x, _)| x + 1).fold(header_width, usize::max);
+    let height = alive_cells.iter().map(|(_, y)| y + 1).fold(header_height, usize::max);
+    pattern.cells = cells_from_alive(width, height, &alive_cells)?;
+
+    Ok(pattern)
+}
+
+/// Writes a pattern out as RLE. Anything which isn't `State::Alive` is written as dead.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#N {}\n", name));
+    }
+    if let Some(author) = &pattern.author {
+        out.push_str(&format!("#O {}\n", author));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("#C {}\n", comment));
+    }
+
+    out.push_str(&format!("x = {}, y = {}", pattern.width(), pattern.height()));
+    if let Some(rule) = &pattern.rule {
+        out.push_str(&format!(", rule = {}", rule));
+    }
+    out.push('\n');
+
+    let mut tokens = Vec::new();
+    let mut pending_rows = 0;
+    for y in 0..pattern.height() {
+        let mut runs: Vec<(usize, char)> = Vec::new();
+        for column in &pattern.cells {
+            let tag = if column[y] == State::Alive { 'o' } else { 'b' };
+            match runs.last_mut() {
+                Some((count, last_tag)) if *last_tag == tag => *count += 1,
+                _ => runs.push((1, tag)),
+            }
+        }
+
+        if let Some((_, 'b')) = runs.last() {
+            runs.pop();
+        }
+
+        if !runs.is_empty() {
+            if pending_rows > 0 {
+                tokens.push(make_token(pending_rows, '$'));
+                pending_rows = 0;
+            }
+            tokens.extend(runs.into_iter().map(|(count, tag)| make_token(count, tag)));
+        }
+        pending_rows += 1;
+    }
+    tokens.push("!".to_owned());
+
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length + token.len() > MAX_LINE_LENGTH {
+            out.push('\n');
+            line_length = 0;
+        }
+        line_length += token.len();
+        out.push_str(&token);
+    }
+    out.push('\n');
+
+    out
+}
+
+fn make_token(count: usize, tag: char) -> String {
+    if count == 1 {
+        tag.to_string()
+    } else {
+        format!("{}{}", count, tag)
+    }
+}
+
+fn read_comment(line: &str, pattern: &mut Pattern) -> Result<(), SerializationError> {
+    let text = line.get(2..).unwrap_or("").trim().to_owned();
+    match line.chars().nth(1) {
+        Some('N') => pattern.name = Some(text),
+        Some('O') => pattern.author = Some(text),
+        Some('C') | Some('c') => pattern.comments.push(text),
+        Some('r') => pattern.rule = Some(parse_rule(&text)?),
+        // Positions (#P, #R) and anything else we don't use.
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn read_header(line: &str, pattern: &mut Pattern) -> Result<(usize, usize), SerializationError> {
+    let mut width = None;
+    let mut height = None;
+
+    for field in line.split(',') {
+        // Fields without an `=` are the tail of a Golly topology, e.g. `rule = B3/S23:T100,100`.
+        let mut parts = field.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = match parts.next() {
+            Some(value) => value.trim(),
+            None => continue,
+        };
+
+        let read_size = |value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| SerializationError::InvalidRle(format!("bad size `{}`", value)))
+        };
+        match key {
+            "x" => width = Some(read_size(value)?),
+            "y" => height = Some(read_size(value)?),
+            "rule" => pattern.rule = Some(parse_rule(value)?),
+            _ => {}
+        }
+    }
+
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height)),
+        _ => Err(SerializationError::InvalidRle(format!("bad header `{}`", line))),
+    }
+}
+
+fn parse_rule(text: &str) -> Result<Rule, SerializationError> {
+    // Golly appends the board topology after a colon, e.g. `B3/S23:T100,100`.
+    let rulestring = text.split(':').next().unwrap_or("");
+    rulestring.trim().parse().map_err(SerializationError::InvalidRule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{load_pattern, save_pattern};
+    use super::*;
+    use std::{env, fs, process};
+
+    fn alive(pattern: &Pattern) -> Vec<(usize, usize)> {
+        pattern.alive_cells().collect()
+    }
+
+    #[test]
+    fn reads_the_header_and_comments() {
+        let text = "#N Glider\n#O Richard K. Guy\n#C The smallest spaceship.\n#C It goes diagonally.\n\
+                    x = 3, y = 3, rule = B3/S23\nbob$2bo$3o!";
+        let pattern = parse(text).unwrap();
+
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.author.as_deref(), Some("Richard K. Guy"));
+        assert_eq!(
+            pattern.comments,
+            vec!["The smallest spaceship.", "It goes diagonally."]
+        );
+        assert_eq!(pattern.rule, Some(Rule::CONWAY));
+        assert_eq!((pattern.width(), pattern.height()), (3, 3));
+        assert_eq!(alive(&pattern), vec![(0, 2), (1, 0), (1, 2), (2, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn reads_golly_rules_and_headers_without_a_rule() {
+        let pattern = parse("x = 3, y = 1, rule = B36/S23:T10,10\n3o!").unwrap();
+        assert_eq!(pattern.rule, Some("B36/S23".parse().unwrap()));
+
+        let pattern = parse("x = 3, y = 1\n3o!").unwrap();
+        assert_eq!(pattern.rule, None);
+        assert_eq!(alive(&pattern), vec![(0, 0), (1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn reads_runs_over_several_lines() {
+        // The run of 12 is split over a line break, and `2$` skips an empty row.
+        let pattern = parse("x = 14, y = 3\n1\n2o2$\nob\n12o!").unwrap();
+
+        assert_eq!((pattern.width(), pattern.height()), (14, 3));
+        assert_eq!(pattern.alive_cells().filter(|(_, y)| *y == 0).count(), 12);
+        assert_eq!(pattern.alive_cells().filter(|(_, y)| *y == 1).count(), 0);
+        let last_row: Vec<(usize, usize)> = pattern.alive_cells().filter(|(_, y)| *y == 2).collect();
+        assert_eq!(last_row.len(), 13);
+        assert!(last_row.contains(&(0, 2)) && last_row.contains(&(13, 2)));
+        assert!(!last_row.contains(&(1, 2)));
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_pattern() {
+        let pattern = parse("x = 2, y = 1\n2o!\nThese cells aren't part of it: 5o$5o!").unwrap();
+        assert_eq!(alive(&pattern), vec![(0, 0), (1, 0)]);
+
+        let pattern = parse("x = 2, y = 1\no!o").unwrap();
+        assert_eq!(alive(&pattern), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn grows_to_fit_cells_outside_the_header() {
+        let pattern = parse("x = 1, y = 1\n3o$o!").unwrap();
+        assert_eq!((pattern.width(), pattern.height()), (3, 2));
+    }
+
+    #[test]
+    fn refuses_bad_input() {
+        assert!(parse("3o!").is_err());
+        assert!(parse("x = 3\n3o!").is_err());
+        assert!(parse("x = three, y = 1\n3o!").is_err());
+        assert!(parse("x = 3, y = 1, rule = B9/S23\n3o!").is_err());
+        assert!(parse("x = 3, y = 1\n3o?!").is_err());
+        assert!(parse("x = 3, y = 1\n99999999999o!").is_err());
+    }
+
+    #[test]
+    fn writes_runs_and_metadata() {
+        let mut pattern = parse("x = 5, y = 4\n2ob2o2$o!").unwrap();
+        pattern.name = Some("Test".to_owned());
+        pattern.comments = vec!["A comment.".to_owned()];
+        pattern.rule = Some(Rule::CONWAY);
+
+        assert_eq!(
+            write(&pattern),
+            "#N Test\n#C A comment.\nx = 5, y = 4, rule = B3/S23\n2ob2o2$o!\n"
+        );
+    }
+
+    #[test]
+    fn keeps_lines_short() {
+        let mut cells = vec![vec![State::Dead; 1]; 200];
+        for (x, column) in cells.iter_mut().enumerate() {
+            if x % 2 == 0 {
+                column[0] = State::Alive;
+            }
+        }
+        let text = write(&Pattern::new(cells.clone()));
+
+        assert!(text.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert!(text.lines().count() > 2);
+        assert_eq!(parse(&text).unwrap().cells, cells);
+    }
+
+    #[test]
+    fn saves_and_loads_the_same_pattern() {
+        let mut pattern = parse("x = 10, y = 6\n3bo$4bo$2b3o3$8b2o!").unwrap();
+        pattern.name = Some("Glider and domino".to_owned());
+        pattern.author = Some("Nobody".to_owned());
+        pattern.comments = vec!["First.".to_owned(), "Second.".to_owned()];
+        pattern.rule = Some("B36/S23".parse().unwrap());
+
+        let location = env::temp_dir().join(format!("life-rle-round-trip-{}.rle", process::id()));
+        save_pattern(&pattern, &location).unwrap();
+        let loaded = load_pattern(&location);
+        fs::remove_file(&location).unwrap();
+
+        assert_eq!(loaded.unwrap(), pattern);
+    }
+}
@@ -0,0 +1,280 @@
+use super::{State, Vec2Int, World};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+type Transform = fn(i32, i32) -> (i32, i32);
+
+/// Which ways a soup is symmetric, about the center of the area it fills.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    None,
+    /// Unchanged by a half turn.
+    C2,
+    /// Unchanged by a quarter turn.
+    C4,
+    /// Unchanged by any quarter turn or reflection.
+    D8,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 4] = [Symmetry::None, Symmetry::C2, Symmetry::C4, Symmetry::D8];
+
+    /// Whether the symmetry includes quarter turns, which only map a square back onto itself.
+    pub fn needs_square(self) -> bool {
+        match self {
+            Symmetry::None | Symmetry::C2 => false,
+            Symmetry::C4 | Symmetry::D8 => true,
+        }
+    }
+
+    /// The symmetry's transforms, applied to coordinates relative to the center.
+    fn transforms(self) -> &'static [Transform] {
+        const D8: [Transform; 8] = [
+            |x, y| (x, y),
+            |x, y| (-x, -y),
+            |x, y| (-y, x),
+            |x, y| (y, -x),
+            |x, y| (-x, y),
+            |x, y| (x, -y),
+            |x, y| (y, x),
+            |x, y| (-y, -x),
+        ];
+
+        match self {
+            Symmetry::None => &D8[..1],
+            Symmetry::C2 => &D8[..2],
+            Symmetry::C4 => &D8[..4],
+            Symmetry::D8 => &D8,
+        }
+    }
+}
+
+impl Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Symmetry::None => "None",
+            Symmetry::C2 => "C2",
+            Symmetry::C4 => "C4",
+            Symmetry::D8 => "D8",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Symmetry::ALL
+            .iter()
+            .find(|symmetry| symmetry.to_string().eq_ignore_ascii_case(s.trim()))
+            .cloned()
+            .ok_or_else(|| format!("unknown symmetry `{}`", s))
+    }
+}
+
+/// A random fill of live cells. The same seed, density and symmetry always fill the same
+/// area with the same cells, so an interesting soup can be passed around as just these.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Soup {
+    pub seed: u64,
+    /// The chance of each cell being alive, from 0 to 1.
+    pub density: f32,
+    pub symmetry: Symmetry,
+}
+
+impl Soup {
+    pub fn new(seed: u64, density: f32, symmetry: Symmetry) -> Self {
+        Soup {
+            seed,
+            density: density.clamp(0.0, 1.0),
+            symmetry,
+        }
+    }
+
+    /// A soup with a seed nobody has picked.
+    pub fn random_seed() -> u64 {
+        rand::thread_rng().gen()
+    }
+
+    /// Lays out a `width` by `height` soup, indexed `[x][y]`. C4 and D8 soups only fill the
+    /// largest square in the middle, leaving the cells either side of it dead.
+    pub fn generate(&self, width: usize, height: usize) -> Vec<Vec<State>> {
+        if self.symmetry.needs_square() && width != height {
+            let side = width.min(height);
+            let (dx, dy) = ((width - side) / 2, (height - side) / 2);
+            let mut cells = vec![vec![State::Dead; height]; width];
+            for (x, column) in self.generate(side, side).into_iter().enumerate() {
+                cells[x + dx][dy..dy + side].copy_from_slice(&column);
+            }
+            return cells;
+        }
+
+        // A named generator rather than `StdRng`, which may change between releases of `rand`
+        // and with it every soup anyone has shared.
+        let mut rng = Pcg64Mcg::seed_from_u64(self.seed);
+        let density = f64::from(self.density);
+        let transforms = self.symmetry.transforms();
+
+        // Cells which the symmetry maps onto each other share one draw, keyed by the smallest
+        // of them. Coordinates are doubled so the center lands on a whole number.
+        let mut orbits: HashMap<(i32, i32), State> = HashMap::new();
+        let (w, h) = (width as i32, height as i32);
+        (0..w)
+            .map(|x| {
+                (0..h)
+                    .map(|y| {
+                        let (cx, cy) = (2 * x - (w - 1), 2 * y - (h - 1));
+                        let orbit = transforms.iter().map(|transform| transform(cx, cy)).min();
+                        let mut draw = || {
+                            if rng.gen_bool(density) {
+                                State::Alive
+                            } else {
+                                State::Dead
+                            }
+                        };
+                        match orbit {
+                            Some(orbit) if transforms.len() > 1 => {
+                                *orbits.entry(orbit).or_insert_with(draw)
+                            }
+                            _ => draw(),
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Fills the cells from `min` to `max` inclusive. Dead cells which were never alive
+    /// are left that way, so the fill doesn't show up as a block of dead cells.
+    pub fn fill(&self, world: &mut World, min: Vec2Int, max: Vec2Int) {
+        let size = max - min + Vec2Int::new(1, 1);
+        if size.x <= 0 || size.y <= 0 {
+            return;
+        }
+
+        for (x, column) in self.generate(size.x as usize, size.y as usize).into_iter().enumerate() {
+            for (y, state) in column.into_iter().enumerate() {
+                let pos = min + Vec2Int::new(x as i32, y as i32);
+                if !world.contains(pos) {
+                    continue;
+                }
+                match (state, world.get(pos)) {
+                    (State::Alive, _) => world.set(pos, State::Alive),
+                    (_, State::Alive) => world.set(pos, State::Dead),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl Display for Soup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "seed {}, density {:.2}, symmetry {}",
+            self.seed, self.density, self.symmetry
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The soup as rows of `.` and `O`, top to bottom.
+    fn picture(cells: &[Vec<State>]) -> Vec<String> {
+        let height = cells.first().map_or(0, |column| column.len());
+        (0..height)
+            .map(|y| {
+                cells
+                    .iter()
+                    .map(|column| if column[y] == State::Alive { 'O' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Whether every cell matches the cells the symmetry maps it onto.
+    fn holds(symmetry: Symmetry, cells: &[Vec<State>]) -> bool {
+        let (w, h) = (cells.len() as i32, cells[0].len() as i32);
+        (0..w).all(|x| {
+            (0..h).all(|y| {
+                symmetry.transforms().iter().all(|transform| {
+                    let (tx, ty) = transform(2 * x - (w - 1), 2 * y - (h - 1));
+                    let (x2, y2) = ((tx + w - 1) / 2, (ty + h - 1) / 2);
+                    cells[x as usize][y as usize] == cells[x2 as usize][y2 as usize]
+                })
+            })
+        })
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_soup() {
+        for &symmetry in Symmetry::ALL.iter() {
+            let cells = Soup::new(1234, 0.4, symmetry).generate(20, 13);
+            assert_eq!(cells, Soup::new(1234, 0.4, symmetry).generate(20, 13));
+            assert_ne!(cells, Soup::new(1235, 0.4, symmetry).generate(20, 13));
+        }
+
+        // Shared seeds have to keep working, so the generator can't change underneath them.
+        let soup = picture(&Soup::new(42, 0.5, Symmetry::None).generate(6, 4));
+        assert_eq!(soup, vec![".OO.O.", "OO..O.", "OOO.O.", "O.OOOO"]);
+    }
+
+    #[test]
+    fn symmetries_hold_on_squares() {
+        for &side in [7, 8].iter() {
+            for &symmetry in Symmetry::ALL.iter() {
+                for seed in 0..20 {
+                    let cells = Soup::new(seed, 0.5, symmetry).generate(side, side);
+                    assert!(holds(symmetry, &cells), "{:?}", (symmetry, seed, side));
+                }
+            }
+        }
+
+        // Half turns fit any rectangle.
+        for seed in 0..20 {
+            let cells = Soup::new(seed, 0.5, Symmetry::C2).generate(9, 4);
+            assert!(holds(Symmetry::C2, &cells));
+        }
+    }
+
+    #[test]
+    fn quarter_turns_fill_the_middle_square_of_a_rectangle() {
+        for &symmetry in [Symmetry::C4, Symmetry::D8].iter() {
+            let soup = Soup::new(7, 1.0, symmetry);
+            let cells = soup.generate(10, 4);
+            let rows = picture(&cells);
+            assert!(rows.iter().all(|row| row == "...OOOO..."), "{:?}", rows);
+
+            let soup = Soup::new(7, 0.5, symmetry);
+            let cells = soup.generate(4, 10);
+            let square: Vec<Vec<State>> = cells.iter().map(|column| column[3..7].to_vec()).collect();
+            assert!(holds(symmetry, &square));
+            assert_eq!(square, soup.generate(4, 4));
+        }
+    }
+
+    #[test]
+    fn density_runs_from_empty_to_full() {
+        let empty = Soup::new(3, 0.0, Symmetry::None).generate(8, 8);
+        assert!(empty.iter().flatten().all(|state| *state == State::Dead));
+        let full = Soup::new(3, 1.0, Symmetry::D8).generate(8, 8);
+        assert!(full.iter().flatten().all(|state| *state == State::Alive));
+        assert_eq!(Soup::new(3, 2.0, Symmetry::None).density, 1.0);
+    }
+
+    #[test]
+    fn symmetries_read_back_from_their_names() {
+        for &symmetry in Symmetry::ALL.iter() {
+            assert_eq!(symmetry.to_string().parse(), Ok(symmetry));
+        }
+        assert_eq!(" d8 ".parse(), Ok(Symmetry::D8));
+        assert!("C3".parse::<Symmetry>().is_err());
+    }
+}
@@ -0,0 +1,483 @@
+use super::{world::fresh_version, HashLife, Rule, State, Vec2Int};
+use rayon::{prelude::*, ThreadPool};
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+const TILE_SIZE: i32 = 64;
+
+/// A 64x64 block of cells. Row `y` of the tile is one word, with bit `x` being the cell.
+#[derive(Debug, Clone)]
+struct Tile {
+    alive: [u64; TILE_SIZE as usize],
+    touched: [u64; TILE_SIZE as usize],
+}
+
+impl Tile {
+    fn empty() -> Self {
+        Tile {
+            alive: [0; TILE_SIZE as usize],
+            touched: [0; TILE_SIZE as usize],
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.alive.iter().all(|row| *row == 0)
+    }
+
+    fn state(&self, x: usize, y: usize) -> State {
+        let bit = 1 << x;
+        if self.alive[y] & bit != 0 {
+            State::Alive
+        } else if self.touched[y] & bit != 0 {
+            State::Dead
+        } else {
+            State::Unborn
+        }
+    }
+}
+
+/// An unbounded world, stored as a hash map of 64x64 tiles.
+///
+/// Tiles are allocated when activity reaches them and freed again once they have no live
+/// cells, so the memory used follows the pattern rather than the area it has swept over.
+/// That does mean the record of `State::Dead` cells is forgotten along with an emptied tile.
+/// Rules which give birth on zero neighbors can't be run on an infinite plane; cells far
+/// from any tile would simply never wake up, so `World::check_rule` turns them away.
+///
+/// Jumps are run by HashLife, and the live cells are left in its universe afterwards rather
+/// than written back into tiles, which only happens once the grid is edited or stepped.
+#[derive(Debug, Default)]
+pub struct SparseGrid {
+    tiles: HashMap<Vec2Int, Tile>,
+    /// See `World::version`. Zero is an empty grid which has never been changed.
+    version: u64,
+    /// The universe the last jump was run in, kept so that the next jump under the same
+    /// rule can use everything it worked out.
+    hashlife: Option<Box<HashLife>>,
+    /// Whether the live cells are the universe's, since a jump. The tiles then have no live
+    /// cells of their own and only remember which cells have been touched.
+    jumped: bool,
+}
+
+/// A copy holds the same cells, but leaves the results HashLife has worked out behind,
+/// since there can be a great many of them.
+impl Clone for SparseGrid {
+    fn clone(&self) -> Self {
+        SparseGrid {
+            tiles: self.tiles.clone(),
+            version: self.version,
+            hashlife: self.jumped().map(|hashlife| Box::new(hashlife.snapshot())),
+            jumped: self.jumped,
+        }
+    }
+}
+
+impl SparseGrid {
+    pub fn new() -> Self {
+        SparseGrid::default()
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn get(&self, pos: Vec2Int) -> State {
+        if self.jumped().is_some_and(|hashlife| hashlife.is_alive(pos)) {
+            return State::Alive;
+        }
+
+        let (tile_pos, x, y) = Self::split(pos);
+        self.tiles
+            .get(&tile_pos)
+            .map_or(State::Unborn, |tile| tile.state(x, y))
+    }
+
+    pub fn set(&mut self, pos: Vec2Int, state: State) {
+        self.settle();
+        let (tile_pos, x, y) = Self::split(pos);
+        let bit = 1 << x;
+        self.version = fresh_version();
+        match state {
+            State::Alive => {
+                let tile = self.tiles.entry(tile_pos).or_insert_with(Tile::empty);
+                tile.alive[y] |= bit;
+                tile.touched[y] |= bit;
+            }
+
+            State::Dead => {
+                let tile = self.tiles.entry(tile_pos).or_insert_with(Tile::empty);
+                tile.alive[y] &= !bit;
+                tile.touched[y] |= bit;
+            }
+
+            State::Unborn => {
+                if let Some(tile) = self.tiles.get_mut(&tile_pos) {
+                    tile.alive[y] &= !bit;
+                    tile.touched[y] &= !bit;
+                }
+            }
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        if let Some(hashlife) = self.jumped() {
+            return hashlife.population() as usize;
+        }
+
+        self.tiles
+            .values()
+            .flat_map(|tile| tile.alive.iter())
+            .map(|row| row.count_ones() as usize)
+            .sum()
+    }
+
+    /// Every cell which isn't `State::Unborn`, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec2Int, State)> + '_ {
+        let live = self.jumped().map_or_else(Vec::new, HashLife::live_cells);
+        let tiles = self
+            .tiles
+            .iter()
+            .flat_map(|(tile_pos, tile)| Self::tile_cells(*tile_pos, tile));
+        self.with_jumped_cells(live, tiles)
+    }
+
+    /// Every cell from `min` to `max` inclusive which isn't `State::Unborn`, in no particular
+    /// order. Only the tiles overlapping that area are looked inside.
+    pub fn iter_region(&self, min: Vec2Int, max: Vec2Int) -> impl Iterator<Item = (Vec2Int, State)> + '_ {
+        let (min_tile, max_tile) = (Self::split(min).0, Self::split(max).0);
+        let in_region = move |pos: Vec2Int, min: Vec2Int, max: Vec2Int| {
+            pos.x >= min.x && pos.y >= min.y && pos.x <= max.x && pos.y <= max.y
+        };
+
+        let live = self
+            .jumped()
+            .map_or_else(Vec::new, |hashlife| hashlife.live_cells_in(min, max));
+        let tiles = self
+            .tiles
+            .iter()
+            .filter(move |(tile_pos, _)| in_region(**tile_pos, min_tile, max_tile))
+            .flat_map(|(tile_pos, tile)| Self::tile_cells(*tile_pos, tile))
+            .filter(move |(pos, _)| in_region(*pos, min, max));
+        self.with_jumped_cells(live, tiles)
+    }
+
+    /// Since a jump, the tiles' cells are all dead, and the live cells come from the universe.
+    /// Any tile cell the universe has alive is left for it to give.
+    fn with_jumped_cells<'a>(
+        &'a self,
+        live: Vec<Vec2Int>,
+        tiles: impl Iterator<Item = (Vec2Int, State)> + 'a,
+    ) -> impl Iterator<Item = (Vec2Int, State)> + 'a {
+        let jumped = self.jumped();
+        let dead = tiles.filter(move |(pos, _)| jumped.is_none_or(|hashlife| !hashlife.is_alive(*pos)));
+        live.into_iter().map(|pos| (pos, State::Alive)).chain(dead)
+    }
+
+    fn tile_cells(tile_pos: Vec2Int, tile: &Tile) -> impl Iterator<Item = (Vec2Int, State)> + '_ {
+        let origin = tile_pos * TILE_SIZE;
+        (0..TILE_SIZE as usize).flat_map(move |y| {
+            (0..TILE_SIZE as usize).filter_map(move |x| {
+                let bit = 1 << x;
+                if tile.touched[y] & bit == 0 {
+                    return None;
+                }
+
+                let state = if tile.alive[y] & bit != 0 {
+                    State::Alive
+                } else {
+                    State::Dead
+                };
+                Some((origin + Vec2Int::new(x as i32, y as i32), state))
+            })
+        })
+    }
+
+    /// The smallest and largest cell positions that aren't `State::Unborn`, if there are any.
+    pub fn bounds(&self) -> Option<(Vec2Int, Vec2Int)> {
+        self.iter().fold(None, |bounds, (pos, _)| match bounds {
+            None => Some((pos, pos)),
+            Some((min, max)) => Some((
+                Vec2Int::new(min.x.min(pos.x), min.y.min(pos.y)),
+                Vec2Int::new(max.x.max(pos.x), max.y.max(pos.y)),
+            )),
+        })
+    }
+
+    /// Every cell whose state differs from the same cell in `other`.
+    pub fn changed_cells(&self, other: &SparseGrid) -> Vec<Vec2Int> {
+        // Tiles can only be compared row by row when they both hold the live cells.
+        if self.jumped || other.jumped {
+            let positions: HashSet<Vec2Int> = self.iter().chain(other.iter()).map(|(pos, _)| pos).collect();
+            return positions
+                .into_iter()
+                .filter(|pos| self.get(*pos) != other.get(*pos))
+                .collect();
+        }
+
+        let empty = Tile::empty();
+        let mut changed = Vec::new();
+
+        let tile_positions: HashSet<&Vec2Int> = self.tiles.keys().chain(other.tiles.keys()).collect();
+        for tile_pos in tile_positions {
+            let this = self.tiles.get(tile_pos).unwrap_or(&empty);
+            let that = other.tiles.get(tile_pos).unwrap_or(&empty);
+            let origin = *tile_pos * TILE_SIZE;
+
+            for y in 0..TILE_SIZE as usize {
+                let mut diff = (this.alive[y] ^ that.alive[y]) | (this.touched[y] ^ that.touched[y]);
+                while diff != 0 {
+                    let x = diff.trailing_zeros() as i32;
+                    diff &= diff - 1;
+                    changed.push(origin + Vec2Int::new(x, y as i32));
+                }
+            }
+        }
+        changed
+    }
+
+    /// Roughly how many bytes the tiles, and the universe kept for jumping, take up.
+    pub fn memory_size(&self) -> usize {
+        let tiles = self.tiles.len() * (mem::size_of::<Vec2Int>() + mem::size_of::<Tile>());
+        let hashlife = self
+            .hashlife
+            .as_ref()
+            .map_or(0, |hashlife| hashlife.memory_size());
+        tiles + hashlife
+    }
+
+    /// Advances the world by one generation. Only tiles with live cells, and their
+    /// immediate neighbors, are looked at; with a thread pool they're shared out between threads.
+    pub fn step(&mut self, rule: &Rule, pool: Option<&ThreadPool>) {
+        self.settle();
+        self.tiles = self.next_tiles(rule, pool);
+        self.version = fresh_version();
+    }
+
+    /// Steps like `step`, pushing every cell the generation changes onto `changes` as its
+    /// position and its states before and after. Only the tiles the step looked at, and
+    /// the ones it freed, are compared.
+    pub fn step_with_changes(
+        &mut self,
+        rule: &Rule,
+        pool: Option<&ThreadPool>,
+        changes: &mut Vec<(Vec2Int, State, State)>,
+    ) {
+        self.settle();
+        let next_tiles = self.next_tiles(rule, pool);
+        let empty = Tile::empty();
+
+        let tile_positions: HashSet<&Vec2Int> = self.tiles.keys().chain(next_tiles.keys()).collect();
+        for tile_pos in tile_positions {
+            let before = self.tiles.get(tile_pos).unwrap_or(&empty);
+            let after = next_tiles.get(tile_pos).unwrap_or(&empty);
+            let origin = *tile_pos * TILE_SIZE;
+
+            for y in 0..TILE_SIZE as usize {
+                let mut diff = (before.alive[y] ^ after.alive[y]) | (before.touched[y] ^ after.touched[y]);
+                while diff != 0 {
+                    let x = diff.trailing_zeros() as usize;
+                    diff &= diff - 1;
+
+                    let pos = origin + Vec2Int::new(x as i32, y as i32);
+                    changes.push((pos, before.state(x, y), after.state(x, y)));
+                }
+            }
+        }
+
+        self.tiles = next_tiles;
+        self.version = fresh_version();
+    }
+
+    /// Advances the world by `generations` with HashLife. The universe from the last jump is
+    /// carried on from where it left off if nothing has changed since, or given the grid's
+    /// cells if something has, and only started afresh when the rule changes.
+    ///
+    /// Afterwards, cells alive before the jump but not after, or dead before it, are `Dead`.
+    /// Cells which were born and died again partway through stay `Unborn`.
+    pub fn jump(&mut self, rule: &Rule, generations: u64) {
+        let same_rule = self
+            .hashlife
+            .as_ref()
+            .is_some_and(|hashlife| hashlife.rule() == *rule);
+        if !same_rule {
+            self.settle();
+            self.hashlife = Some(Box::new(HashLife::new(*rule)));
+        }
+
+        if !self.jumped {
+            let live_cells: Vec<Vec2Int> = self
+                .iter()
+                .filter(|(_, state)| *state == State::Alive)
+                .map(|(pos, _)| pos)
+                .collect();
+            for tile in self.tiles.values_mut() {
+                tile.alive = [0; TILE_SIZE as usize];
+            }
+            if let Some(hashlife) = &mut self.hashlife {
+                hashlife.set_cells(&live_cells);
+            }
+            self.jumped = true;
+        }
+
+        if let Some(hashlife) = &mut self.hashlife {
+            hashlife.advance(generations);
+        }
+        self.version = fresh_version();
+    }
+
+    /// The universe holding the live cells, if they've been left there by a jump.
+    fn jumped(&self) -> Option<&HashLife> {
+        if self.jumped {
+            self.hashlife.as_deref()
+        } else {
+            None
+        }
+    }
+
+    /// Writes the live cells left in the universe by a jump back into the tiles, before
+    /// anything changes them. The universe is kept for the next jump.
+    fn settle(&mut self) {
+        let live_cells = match self.jumped() {
+            Some(hashlife) => hashlife.live_cells(),
+            None => return,
+        };
+
+        self.jumped = false;
+        for pos in live_cells {
+            let (tile_pos, x, y) = Self::split(pos);
+            let tile = self.tiles.entry(tile_pos).or_insert_with(Tile::empty);
+            tile.alive[y] |= 1 << x;
+            tile.touched[y] |= 1 << x;
+        }
+    }
+
+    /// The tiles of the next generation.
+    fn next_tiles(&self, rule: &Rule, pool: Option<&ThreadPool>) -> HashMap<Vec2Int, Tile> {
+        let mut candidates = HashSet::new();
+        for (tile_pos, tile) in &self.tiles {
+            if tile.is_empty() {
+                continue;
+            }
+
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    candidates.insert(*tile_pos + Vec2Int::new(dx, dy));
+                }
+            }
+        }
+
+        let candidates: Vec<Vec2Int> = candidates.into_iter().collect();
+        let next_rows: Vec<(Vec2Int, [u64; TILE_SIZE as usize])> = match pool {
+            Some(pool) => pool.install(|| {
+                candidates
+                    .par_iter()
+                    .filter_map(|tile_pos| self.next_tile(rule, *tile_pos).map(|rows| (*tile_pos, rows)))
+                    .collect()
+            }),
+            None => candidates
+                .iter()
+                .filter_map(|tile_pos| self.next_tile(rule, *tile_pos).map(|rows| (*tile_pos, rows)))
+                .collect(),
+        };
+
+        let mut next_tiles = HashMap::with_capacity(next_rows.len());
+        for (tile_pos, next_alive) in next_rows {
+            let mut tile = self.tiles.get(&tile_pos).cloned().unwrap_or_else(Tile::empty);
+            for (y, row) in next_alive.iter().enumerate() {
+                tile.alive[y] = *row;
+                tile.touched[y] |= *row;
+            }
+            next_tiles.insert(tile_pos, tile);
+        }
+        next_tiles
+    }
+
+    /// The live cells of a tile next generation, or `None` if it will be empty.
+    fn next_tile(&self, rule: &Rule, tile_pos: Vec2Int) -> Option<[u64; TILE_SIZE as usize]> {
+        let mut next_alive = [0; TILE_SIZE as usize];
+        for y in 0..TILE_SIZE {
+            let (north, north_west, north_east) = self.row_with_halo(tile_pos, y + 1);
+            let (center, west, east) = self.row_with_halo(tile_pos, y);
+            let (south, south_west, south_east) = self.row_with_halo(tile_pos, y - 1);
+
+            let neighbors = [north_west, north, north_east, west, east, south_west, south, south_east];
+            next_alive[y as usize] = rule.next_word(center, &neighbors);
+        }
+
+        if next_alive.iter().all(|row| *row == 0) {
+            None
+        } else {
+            Some(next_alive)
+        }
+    }
+
+    /// Returns row `y` of a tile (which may spill over into the tile above or below),
+    /// along with that row shifted so each bit holds its west and its east neighbor.
+    fn row_with_halo(&self, tile_pos: Vec2Int, y: i32) -> (u64, u64, u64) {
+        let tile_pos = tile_pos + Vec2Int::new(0, y.div_euclid(TILE_SIZE));
+        let y = y.rem_euclid(TILE_SIZE) as usize;
+        let row_of = |pos: Vec2Int| self.tiles.get(&pos).map_or(0, |tile| tile.alive[y]);
+
+        let row = row_of(tile_pos);
+        let west_tile = row_of(tile_pos - Vec2Int::RIGHT);
+        let east_tile = row_of(tile_pos + Vec2Int::RIGHT);
+
+        let west = (row << 1) | (west_tile >> (TILE_SIZE - 1));
+        let east = (row >> 1) | (east_tile << (TILE_SIZE - 1));
+        (row, west, east)
+    }
+
+    fn split(pos: Vec2Int) -> (Vec2Int, usize, usize) {
+        let tile_pos = Vec2Int::new(pos.x.div_euclid(TILE_SIZE), pos.y.div_euclid(TILE_SIZE));
+        let x = pos.x.rem_euclid(TILE_SIZE) as usize;
+        let y = pos.y.rem_euclid(TILE_SIZE) as usize;
+        (tile_pos, x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glider() -> SparseGrid {
+        let mut grid = SparseGrid::new();
+        for &(x, y) in &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            grid.set(Vec2Int::new(x, y), State::Alive);
+        }
+        grid
+    }
+
+    fn universe(grid: &SparseGrid) -> *const HashLife {
+        grid.hashlife.as_deref().unwrap()
+    }
+
+    #[test]
+    fn jumps_keep_their_universe_until_the_rule_changes() {
+        let mut grid = glider();
+        grid.jump(&Rule::CONWAY, 64);
+        let first = universe(&grid);
+        let worked_out = grid.memory_size();
+
+        grid.jump(&Rule::CONWAY, 64);
+        assert_eq!(universe(&grid), first);
+        assert!(grid.memory_size() >= worked_out);
+
+        // Edits and steps write the cells back, but the universe waits for the next jump.
+        grid.set(Vec2Int::new(100, 100), State::Alive);
+        grid.step(&Rule::CONWAY, None);
+        assert!(grid.jumped().is_none());
+        grid.jump(&Rule::CONWAY, 8);
+        assert_eq!(universe(&grid), first);
+        assert_eq!(grid.population(), 5);
+
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        grid.jump(&highlife, 8);
+        assert_eq!(grid.hashlife.as_ref().unwrap().rule(), highlife);
+        assert_eq!(grid.population(), 5);
+
+        // Copies leave the results behind.
+        let copy = grid.clone();
+        assert!(copy.memory_size() < grid.memory_size());
+        assert_eq!(copy.population(), 5);
+    }
+}
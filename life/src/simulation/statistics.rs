@@ -0,0 +1,102 @@
+use super::{State, Vec2Int};
+use std::collections::VecDeque;
+
+/// What happened over one step of the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerationStats {
+    /// The generation the world reached.
+    pub generation: u64,
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+}
+
+/// Counts generations and keeps the most recent `capacity` steps' worth of population,
+/// births and deaths. A jump of many generations is recorded as a single step, with births
+/// and deaths counted between the start and end of the jump.
+#[derive(Debug)]
+pub struct Statistics {
+    generation: u64,
+    samples: VecDeque<GenerationStats>,
+    capacity: usize,
+}
+
+impl Statistics {
+    pub fn new(capacity: usize) -> Self {
+        Statistics {
+            generation: 0,
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn latest(&self) -> Option<&GenerationStats> {
+        self.samples.back()
+    }
+
+    /// The recorded steps, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = &GenerationStats> {
+        self.samples.iter()
+    }
+
+    /// The smallest and largest population recorded, and the average.
+    pub fn population_range(&self) -> Option<(usize, usize, f32)> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        let populations = self.samples.iter().map(|sample| sample.population);
+        let min = populations.clone().min().unwrap_or(0);
+        let max = populations.clone().max().unwrap_or(0);
+        let average = populations.sum::<usize>() as f32 / self.samples.len() as f32;
+        Some((min, max, average))
+    }
+
+    /// Records a step of `generations` which left `population` cells alive, counting births
+    /// and deaths from the cells it changed, as `World::step_with_changes` lists them.
+    pub fn record_changes(
+        &mut self,
+        changes: &[(Vec2Int, State, State)],
+        population: usize,
+        generations: u64,
+    ) {
+        let (births, deaths) = changes.iter().fold((0, 0), |(births, deaths), (_, was, is)| {
+            match (*was == State::Alive, *is == State::Alive) {
+                (false, true) => (births + 1, deaths),
+                (true, false) => (births, deaths + 1),
+                _ => (births, deaths),
+            }
+        });
+
+        self.generation += generations;
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(GenerationStats {
+            generation: self.generation,
+            population,
+            births,
+            deaths,
+        });
+    }
+
+    /// Goes back `generations`, forgetting anything recorded after that.
+    pub fn rewind(&mut self, generations: u64) {
+        self.generation = self.generation.saturating_sub(generations);
+        while let Some(sample) = self.samples.back() {
+            if sample.generation <= self.generation {
+                break;
+            }
+            self.samples.pop_back();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.generation = 0;
+        self.samples.clear();
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// What lies past the edges of a bounded world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topology {
+    /// Everything outside the world is dead.
+    #[default]
+    Dead,
+    /// Each edge wraps round to the opposite one.
+    Torus,
+    /// Left and right wrap round as on a torus; top and bottom wrap round flipped left to right.
+    KleinBottle,
+    /// Both pairs of edges wrap round flipped, left and right upside down and top and bottom
+    /// left to right.
+    CrossSurface,
+    /// The cells just past an edge copy the cells along it.
+    Mirror,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 5] = [
+        Topology::Dead,
+        Topology::Torus,
+        Topology::KleinBottle,
+        Topology::CrossSurface,
+        Topology::Mirror,
+    ];
+
+    /// A name for menus, e.g. `Klein bottle`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Topology::Dead => "Dead edges",
+            Topology::Torus => "Torus",
+            Topology::KleinBottle => "Klein bottle",
+            Topology::CrossSurface => "Cross-surface",
+            Topology::Mirror => "Mirrored edges",
+        }
+    }
+
+    /// Finds the cell of a `width` by `height` world which the position `(x, y)` lands on.
+    /// Positions inside the world are themselves; `None` means the position is off the
+    /// edge of a world with dead edges.
+    pub fn wrap(self, x: isize, y: isize, width: usize, height: usize) -> Option<(usize, usize)> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let (w, h) = (width as isize, height as isize);
+        let (mut x, mut y) = (x, y);
+
+        if x < 0 || x >= w {
+            match self {
+                Topology::Dead => return None,
+                Topology::Torus | Topology::KleinBottle => x = x.rem_euclid(w),
+                Topology::CrossSurface => {
+                    x = x.rem_euclid(w);
+                    y = h - 1 - y;
+                }
+                Topology::Mirror => x = reflect(x, w),
+            }
+        }
+
+        if y < 0 || y >= h {
+            match self {
+                Topology::Dead => return None,
+                Topology::Torus => y = y.rem_euclid(h),
+                Topology::KleinBottle | Topology::CrossSurface => {
+                    y = y.rem_euclid(h);
+                    x = w - 1 - x;
+                }
+                Topology::Mirror => y = reflect(y, h),
+            }
+        }
+
+        Some((x as usize, y as usize))
+    }
+}
+
+impl Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Topology::Dead => "dead",
+            Topology::Torus => "torus",
+            Topology::KleinBottle => "klein",
+            Topology::CrossSurface => "cross",
+            Topology::Mirror => "mirror",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    /// Reads the short names `Display` writes, e.g. `klein`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Topology::ALL
+            .iter()
+            .find(|topology| topology.to_string().eq_ignore_ascii_case(s.trim()))
+            .cloned()
+            .ok_or_else(|| format!("unknown topology `{}`", s))
+    }
+}
+
+/// Reflects a position just outside `0..len` back onto the edge it went past, and any
+/// further out onwards from there.
+fn reflect(pos: isize, len: isize) -> isize {
+    let pos = if pos < 0 { -pos - 1 } else { 2 * len - pos - 1 };
+    pos.max(0).min(len - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 5;
+    const HEIGHT: usize = 4;
+
+    /// A position to wrap, and where it should end up.
+    type Case = ((isize, isize), Option<(usize, usize)>);
+
+    /// Checks where each of `cases` lands, given as `((x, y), expected)`.
+    fn assert_wraps(topology: Topology, cases: &[Case]) {
+        for &((x, y), expected) in cases {
+            assert_eq!(
+                topology.wrap(x, y, WIDTH, HEIGHT),
+                expected,
+                "{:?} wrapped ({}, {}) wrongly",
+                topology,
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn inside_positions_stay_put() {
+        for &topology in &Topology::ALL {
+            for x in 0..WIDTH {
+                for y in 0..HEIGHT {
+                    assert_eq!(topology.wrap(x as isize, y as isize, WIDTH, HEIGHT), Some((x, y)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_worlds_have_nowhere_to_wrap_to() {
+        for &topology in &Topology::ALL {
+            assert_eq!(topology.wrap(0, 0, 0, HEIGHT), None);
+            assert_eq!(topology.wrap(0, 0, WIDTH, 0), None);
+        }
+    }
+
+    #[test]
+    fn dead_edges() {
+        assert_wraps(
+            Topology::Dead,
+            &[
+                ((-1, 1), None),
+                ((5, 1), None),
+                ((2, -1), None),
+                ((2, 4), None),
+                ((-1, -1), None),
+                ((5, 4), None),
+                ((-1, 4), None),
+                ((5, -1), None),
+            ],
+        );
+    }
+
+    #[test]
+    fn torus() {
+        assert_wraps(
+            Topology::Torus,
+            &[
+                ((-1, 1), Some((4, 1))),
+                ((5, 1), Some((0, 1))),
+                ((1, -1), Some((1, 3))),
+                ((1, 4), Some((1, 0))),
+                ((-1, -1), Some((4, 3))),
+                ((5, 4), Some((0, 0))),
+                ((-1, 4), Some((4, 0))),
+                ((5, -1), Some((0, 3))),
+            ],
+        );
+    }
+
+    #[test]
+    fn klein_bottle_flips_rows_across_the_top_and_bottom() {
+        assert_wraps(
+            Topology::KleinBottle,
+            &[
+                ((-1, 1), Some((4, 1))),
+                ((5, 1), Some((0, 1))),
+                ((1, -1), Some((3, 3))),
+                ((1, 4), Some((3, 0))),
+                ((0, -1), Some((4, 3))),
+                ((4, 4), Some((0, 0))),
+                ((-1, -1), Some((0, 3))),
+                ((5, 4), Some((4, 0))),
+                ((-1, 4), Some((0, 0))),
+                ((5, -1), Some((4, 3))),
+            ],
+        );
+    }
+
+    #[test]
+    fn cross_surface_flips_both_ways() {
+        assert_wraps(
+            Topology::CrossSurface,
+            &[
+                ((-1, 1), Some((4, 2))),
+                ((5, 0), Some((0, 3))),
+                ((-1, 3), Some((4, 0))),
+                ((1, -1), Some((3, 3))),
+                ((1, 4), Some((3, 0))),
+                ((-1, -1), Some((0, 0))),
+                ((5, 4), Some((4, 3))),
+                ((-1, 4), Some((0, 3))),
+                ((5, -1), Some((4, 0))),
+            ],
+        );
+    }
+
+    #[test]
+    fn mirror_copies_the_edge() {
+        assert_wraps(
+            Topology::Mirror,
+            &[
+                ((-1, 1), Some((0, 1))),
+                ((5, 1), Some((4, 1))),
+                ((2, -1), Some((2, 0))),
+                ((2, 4), Some((2, 3))),
+                ((-1, -1), Some((0, 0))),
+                ((5, 4), Some((4, 3))),
+                ((-1, 4), Some((0, 3))),
+                ((5, -1), Some((4, 0))),
+            ],
+        );
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for &topology in &Topology::ALL {
+            assert_eq!(topology.to_string().parse::<Topology>(), Ok(topology));
+        }
+        assert!("sphere".parse::<Topology>().is_err());
+    }
+}
@@ -0,0 +1,464 @@
+use super::{Grid, Rule, SparseGrid, State, Topology, Vec2Int};
+use rayon::ThreadPool;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(1);
+
+/// A version no world has had before, for a world whose cells have just changed.
+pub(super) fn fresh_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The cells of the game, either on a fixed-size board or on an infinite plane.
+#[derive(Debug, Clone)]
+pub enum World {
+    Bounded(Grid),
+    Infinite(SparseGrid),
+}
+
+impl World {
+    /// The size of the board, or `None` if the world goes on forever.
+    pub fn size(&self) -> Option<Vec2Int> {
+        match self {
+            World::Bounded(grid) => Some(Vec2Int::new(grid.width() as i32, grid.height() as i32)),
+            World::Infinite(_) => None,
+        }
+    }
+
+    /// Changes whenever any cell does, and two worlds with the same version hold the same
+    /// cells, so anything worked out from a world can tell when it's out of date.
+    pub fn version(&self) -> u64 {
+        match self {
+            World::Bounded(grid) => grid.version(),
+            World::Infinite(sparse) => sparse.version(),
+        }
+    }
+
+    pub fn contains(&self, pos: Vec2Int) -> bool {
+        match self {
+            World::Bounded(grid) => {
+                pos.x >= 0 && pos.y >= 0 && grid.contains(pos.x as usize, pos.y as usize)
+            }
+            World::Infinite(_) => true,
+        }
+    }
+
+    pub fn get(&self, pos: Vec2Int) -> State {
+        match self {
+            World::Bounded(grid) => {
+                if self.contains(pos) {
+                    grid.get(pos.x as usize, pos.y as usize)
+                } else {
+                    State::Unborn
+                }
+            }
+            World::Infinite(sparse) => sparse.get(pos),
+        }
+    }
+
+    /// Sets a cell's state. Positions off the edge of a bounded world are ignored.
+    pub fn set(&mut self, pos: Vec2Int, state: State) {
+        if !self.contains(pos) {
+            return;
+        }
+
+        match self {
+            World::Bounded(grid) => grid.set(pos.x as usize, pos.y as usize, state),
+            World::Infinite(sparse) => sparse.set(pos, state),
+        }
+    }
+
+    /// Toggles a cell between alive and dead, returning its new state.
+    pub fn flip(&mut self, pos: Vec2Int) -> State {
+        let new_state = match self.get(pos) {
+            State::Unborn | State::Dead => State::Alive,
+            State::Alive => State::Dead,
+        };
+
+        self.set(pos, new_state);
+        new_state
+    }
+
+    pub fn population(&self) -> usize {
+        match self {
+            World::Bounded(grid) => grid.population(),
+            World::Infinite(sparse) => sparse.population(),
+        }
+    }
+
+    /// Every cell worth drawing. On a bounded world that's all of them, but on an infinite
+    /// world it's only the cells which have ever been alive.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (Vec2Int, State)> + '_> {
+        match self {
+            World::Bounded(grid) => {
+                Box::new(grid.iter().map(|(x, y, state)| (Vec2Int::new(x as i32, y as i32), state)))
+            }
+            World::Infinite(sparse) => Box::new(sparse.iter()),
+        }
+    }
+
+    /// Like `iter`, but only the cells from `min` to `max` inclusive, found without walking
+    /// through the rest of the world.
+    pub fn iter_region(&self, min: Vec2Int, max: Vec2Int) -> Box<dyn Iterator<Item = (Vec2Int, State)> + '_> {
+        match self {
+            World::Bounded(grid) => {
+                let min = Vec2Int::new(min.x.max(0), min.y.max(0));
+                let max = Vec2Int::new(
+                    max.x.min(grid.width() as i32 - 1),
+                    max.y.min(grid.height() as i32 - 1),
+                );
+                Box::new((min.y..=max.y).flat_map(move |y| {
+                    (min.x..=max.x).map(move |x| (Vec2Int::new(x, y), grid.get(x as usize, y as usize)))
+                }))
+            }
+            World::Infinite(sparse) => Box::new(sparse.iter_region(min, max)),
+        }
+    }
+
+    /// The smallest and largest positions holding anything, if there are any.
+    pub fn bounds(&self) -> Option<(Vec2Int, Vec2Int)> {
+        match self {
+            World::Bounded(grid) => {
+                if grid.width() == 0 || grid.height() == 0 {
+                    None
+                } else {
+                    Some((
+                        Vec2Int::ZERO,
+                        Vec2Int::new(grid.width() as i32 - 1, grid.height() as i32 - 1),
+                    ))
+                }
+            }
+            World::Infinite(sparse) => sparse.bounds(),
+        }
+    }
+
+    /// The smallest and largest positions of live cells, if any are alive.
+    pub fn live_bounds(&self) -> Option<(Vec2Int, Vec2Int)> {
+        self.iter()
+            .filter(|(_, state)| *state == State::Alive)
+            .fold(None, |bounds, (pos, _)| match bounds {
+                None => Some((pos, pos)),
+                Some((min, max)) => Some((
+                    Vec2Int::new(min.x.min(pos.x), min.y.min(pos.y)),
+                    Vec2Int::new(max.x.max(pos.x), max.y.max(pos.y)),
+                )),
+            })
+    }
+
+    /// Copies everything within `bounds` out into a plain `[x][y]` matrix of states,
+    /// the same layout prefabs use.
+    pub fn to_states(&self) -> Vec<Vec<State>> {
+        match self.bounds() {
+            Some((min, max)) => self.region(min, max),
+            None => vec![],
+        }
+    }
+
+    /// Copies just the live cells out into an `[x][y]` matrix big enough to hold them, with
+    /// everything else `Unborn`, along with the position of its corner. Only the live cells
+    /// are looked at, so the area dead cells have been left over doesn't matter.
+    pub fn live_states(&self) -> (Vec2Int, Vec<Vec<State>>) {
+        let (min, max) = match self.live_bounds() {
+            Some(bounds) => bounds,
+            None => return (Vec2Int::ZERO, vec![]),
+        };
+
+        let size = max - min + Vec2Int::new(1, 1);
+        let mut states = vec![vec![State::Unborn; size.y as usize]; size.x as usize];
+        for (pos, state) in self.iter() {
+            if state == State::Alive {
+                states[(pos.x - min.x) as usize][(pos.y - min.y) as usize] = State::Alive;
+            }
+        }
+        (min, states)
+    }
+
+    /// Copies the cells from `min` to `max` inclusive into an `[x][y]` matrix. Cells off
+    /// the edge of a bounded world come out `Unborn`.
+    pub fn region(&self, min: Vec2Int, max: Vec2Int) -> Vec<Vec<State>> {
+        (min.x..=max.x)
+            .map(|x| (min.y..=max.y).map(|y| self.get(Vec2Int::new(x, y))).collect())
+            .collect()
+    }
+
+    /// Pastes `cells` (indexed `[x][y]`) with its corner at `pos`. Cells falling off the
+    /// edge of a bounded world are cropped, and pasting `Dead` never marks an `Unborn` cell.
+    pub fn paste(&mut self, pos: Vec2Int, cells: &[Vec<State>]) {
+        for (command_x, column) in cells.iter().enumerate() {
+            for (command_y, &new_state) in column.iter().enumerate() {
+                let this_pos = pos + Vec2Int::new(command_x as i32, command_y as i32);
+                if !(self.get(this_pos) == State::Unborn && new_state == State::Dead) {
+                    self.set(this_pos, new_state);
+                }
+            }
+        }
+    }
+
+    /// Every cell which is different now to how it was in `before`, as its position and its
+    /// states before and after. Returns `None` if the two worlds aren't the same shape,
+    /// since then there's no cell-by-cell comparison to make.
+    pub fn changes_since(&self, before: &World) -> Option<Vec<(Vec2Int, State, State)>> {
+        let positions: Vec<Vec2Int> = match (self, before) {
+            (World::Bounded(grid), World::Bounded(before_grid)) => {
+                if grid.width() != before_grid.width() || grid.height() != before_grid.height() {
+                    return None;
+                }
+
+                grid.changed_cells(before_grid)
+                    .into_iter()
+                    .map(|(x, y)| Vec2Int::new(x as i32, y as i32))
+                    .collect()
+            }
+            (World::Infinite(sparse), World::Infinite(before_sparse)) => sparse.changed_cells(before_sparse),
+            _ => return None,
+        };
+
+        Some(
+            positions
+                .into_iter()
+                .map(|pos| (pos, before.get(pos), self.get(pos)))
+                .collect(),
+        )
+    }
+
+    /// Roughly how many bytes the world's cells take up.
+    pub fn memory_size(&self) -> usize {
+        match self {
+            World::Bounded(grid) => grid.memory_size(),
+            World::Infinite(sparse) => sparse.memory_size(),
+        }
+    }
+
+    /// Whether `rule` can be run on this world. A rule which gives birth on zero neighbors
+    /// would bring every cell of an infinite world to life at once, which can't be stored
+    /// or stepped, so infinite worlds turn those rules away.
+    pub fn check_rule(&self, rule: &Rule) -> Result<(), WorldError> {
+        match self {
+            World::Infinite(_) if rule.births_on(0) => Err(WorldError::BirthOnZero(*rule)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Advances the world by one generation. `topology` only matters on a bounded world,
+    /// and `pool` lets the work be split across threads. Fails without changing anything
+    /// if `check_rule` turns the rule away.
+    pub fn step(
+        &mut self,
+        rule: &Rule,
+        topology: Topology,
+        pool: Option<&ThreadPool>,
+    ) -> Result<(), WorldError> {
+        self.check_rule(rule)?;
+        match self {
+            World::Bounded(grid) => grid.step(rule, topology, pool),
+            World::Infinite(sparse) => sparse.step(rule, pool),
+        }
+        Ok(())
+    }
+
+    /// Steps like `step`, pushing every cell the generation changes onto `changes` as its
+    /// position and its states before and after, the same as `changes_since` would give
+    /// without needing a copy of the world from before.
+    pub fn step_with_changes(
+        &mut self,
+        rule: &Rule,
+        topology: Topology,
+        pool: Option<&ThreadPool>,
+        changes: &mut Vec<(Vec2Int, State, State)>,
+    ) -> Result<(), WorldError> {
+        self.check_rule(rule)?;
+        match self {
+            World::Bounded(grid) => grid.step_with_changes(rule, topology, pool, changes),
+            World::Infinite(sparse) => sparse.step_with_changes(rule, pool, changes),
+        }
+        Ok(())
+    }
+
+    /// Advances the world by any number of generations. Infinite worlds are handed to
+    /// HashLife, so this is cheap even for astronomically long jumps; bounded worlds have
+    /// edges HashLife knows nothing about, so they are stepped one generation at a time.
+    ///
+    /// HashLife only keeps track of live cells, so on an infinite world the cells which were
+    /// dead before the jump, or alive before it but not after, are `Dead` afterwards. Cells
+    /// which were born and died again partway through stay `Unborn`.
+    pub fn jump(
+        &mut self,
+        rule: &Rule,
+        generations: u64,
+        topology: Topology,
+        pool: Option<&ThreadPool>,
+    ) -> Result<(), WorldError> {
+        self.check_rule(rule)?;
+        match self {
+            World::Bounded(grid) => {
+                for _ in 0..generations {
+                    grid.step(rule, topology, pool);
+                }
+            }
+
+            World::Infinite(sparse) => sparse.jump(rule, generations),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum WorldError {
+    #[fail(display = "{} gives birth on zero neighbors, so needs a bounded world.", _0)]
+    BirthOnZero(Rule),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::simple_serialization::{self, Format};
+    use super::*;
+
+    const GLIDER: &str = "x = 3, y = 3\nbo$2bo$3o!";
+    const GOSPER_GUN: &str = "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+                              2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!";
+
+    fn infinite_world(rle: &str) -> World {
+        let pattern = simple_serialization::parse(rle, Format::Rle).unwrap();
+        let mut world = World::Infinite(SparseGrid::new());
+        world.paste(Vec2Int::ZERO, &pattern.cells);
+        world
+    }
+
+    fn live_cells(world: &World) -> Vec<(i32, i32)> {
+        let mut cells: Vec<(i32, i32)> = world
+            .iter()
+            .filter(|(_, state)| *state == State::Alive)
+            .map(|(pos, _)| (pos.x, pos.y))
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn jumps_go_as_far_as_steps() {
+        for rle in &[GLIDER, GOSPER_GUN] {
+            let mut stepped = infinite_world(rle);
+            let mut jumped = stepped.clone();
+
+            // Jumps one after another carry on in the same universe, stepping in between
+            // writes the cells back, and an edit starts the next jump from the new cells.
+            for &(jump, steps) in &[(1, 0), (30, 3), (97, 1), (256, 0), (45, 2)] {
+                for _ in 0..jump {
+                    stepped.step(&Rule::CONWAY, Topology::Dead, None).unwrap();
+                }
+                jumped.jump(&Rule::CONWAY, jump, Topology::Dead, None).unwrap();
+                assert_eq!(live_cells(&jumped), live_cells(&stepped), "jumping {}", jump);
+                assert_eq!(jumped.population(), stepped.population());
+
+                for _ in 0..steps {
+                    stepped.step(&Rule::CONWAY, Topology::Dead, None).unwrap();
+                    jumped.step(&Rule::CONWAY, Topology::Dead, None).unwrap();
+                }
+                stepped.flip(Vec2Int::new(-20, -20));
+                jumped.flip(Vec2Int::new(-20, -20));
+            }
+        }
+    }
+
+    #[test]
+    fn jumped_worlds_read_like_stepped_ones() {
+        let mut world = infinite_world(GOSPER_GUN);
+        world.jump(&Rule::CONWAY, 300, Topology::Dead, None).unwrap();
+        let copy = world.clone();
+
+        // Setting a cell to what it already is writes the jump's cells back into tiles.
+        let mut settled = world.clone();
+        let (x, y) = live_cells(&world)[0];
+        settled.set(Vec2Int::new(x, y), State::Alive);
+        for world in &[&world, &copy] {
+            assert_eq!(live_cells(world), live_cells(&settled));
+            assert_eq!(world.bounds(), settled.bounds());
+
+            let (min, max) = (Vec2Int::new(10, 5), Vec2Int::new(90, 70));
+            let mut region: Vec<(Vec2Int, State)> = world.iter_region(min, max).collect();
+            let mut expected: Vec<(Vec2Int, State)> = settled.iter_region(min, max).collect();
+            region.sort_by_key(|(pos, _)| (pos.x, pos.y));
+            expected.sort_by_key(|(pos, _)| (pos.x, pos.y));
+            assert_eq!(region, expected);
+        }
+        assert_eq!(world.changes_since(&copy), Some(vec![]));
+        assert_eq!(settled.changes_since(&world), Some(vec![]));
+    }
+
+    #[test]
+    fn infinite_jumps_keep_the_dead() {
+        let mut world = World::Infinite(SparseGrid::new());
+        world.set(Vec2Int::new(-40, 7), State::Dead);
+        for x in 0..3 {
+            world.set(Vec2Int::new(x, 0), State::Alive);
+        }
+
+        world.jump(&Rule::CONWAY, 1001, Topology::Dead, None).unwrap();
+        assert_eq!(world.get(Vec2Int::new(-40, 7)), State::Dead);
+        assert_eq!(world.get(Vec2Int::new(0, 0)), State::Dead);
+        assert_eq!(world.get(Vec2Int::new(1, -1)), State::Alive);
+        assert_eq!(world.get(Vec2Int::new(1, 0)), State::Alive);
+        assert_eq!(world.get(Vec2Int::new(5, 5)), State::Unborn);
+        assert_eq!(world.population(), 3);
+    }
+
+    #[test]
+    fn infinite_worlds_refuse_birth_on_zero() {
+        let rule: Rule = "B0/S8".parse().unwrap();
+        let mut infinite = World::Infinite(SparseGrid::new());
+        for x in 0..3 {
+            infinite.set(Vec2Int::new(x, 0), State::Alive);
+        }
+        let version = infinite.version();
+
+        assert!(infinite.step(&rule, Topology::Dead, None).is_err());
+        let mut changes = vec![];
+        let stepped = infinite.step_with_changes(&rule, Topology::Dead, None, &mut changes);
+        assert!(stepped.is_err());
+        assert!(infinite.jump(&rule, 1, Topology::Dead, None).is_err());
+        assert_eq!(infinite.version(), version);
+        assert_eq!(infinite.population(), 3);
+
+        // Past the edges of a bounded world there's nothing to be born.
+        let mut bounded = World::Bounded(Grid::new(3, 1));
+        for x in 0..3 {
+            bounded.set(Vec2Int::new(x, 0), State::Alive);
+        }
+        bounded.step(&rule, Topology::Dead, None).unwrap();
+        assert_eq!(bounded.population(), 0);
+        bounded.jump(&rule, 3, Topology::Dead, None).unwrap();
+        assert_eq!(bounded.population(), 3);
+    }
+
+    #[test]
+    fn regions_hold_only_the_cells_inside_them() {
+        let mut bounded = World::Bounded(Grid::new(100, 70));
+        let mut infinite = World::Infinite(SparseGrid::new());
+        for i in 0..200 {
+            let pos = Vec2Int::new(i * 37 % 100, i * 53 % 70);
+            let state = if i % 3 == 0 { State::Dead } else { State::Alive };
+            bounded.set(pos, state);
+            infinite.set(pos - Vec2Int::new(50, 35), state);
+        }
+
+        let regions = [
+            (Vec2Int::new(-10, -10), Vec2Int::new(5, 5)),
+            (Vec2Int::new(20, 3), Vec2Int::new(90, 64)),
+            (Vec2Int::new(-64, -64), Vec2Int::new(63, 63)),
+            (Vec2Int::new(95, 60), Vec2Int::new(200, 200)),
+            (Vec2Int::new(5, 5), Vec2Int::new(4, 4)),
+        ];
+        for world in &[bounded, infinite] {
+            for &(min, max) in &regions {
+                let inside =
+                    |pos: Vec2Int| pos.x >= min.x && pos.y >= min.y && pos.x <= max.x && pos.y <= max.y;
+                let mut expected: Vec<(Vec2Int, State)> =
+                    world.iter().filter(|(pos, _)| inside(*pos)).collect();
+                let mut region: Vec<(Vec2Int, State)> = world.iter_region(min, max).collect();
+                expected.sort_by_key(|(pos, _)| (pos.x, pos.y));
+                region.sort_by_key(|(pos, _)| (pos.x, pos.y));
+                assert_eq!(region, expected, "{:?} to {:?}", min, max);
+            }
+        }
+    }
+}
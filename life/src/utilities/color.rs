@@ -1,4 +1,3 @@
-#[allow(dead_code)]
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Color {
     r: f32,
@@ -7,12 +6,10 @@ pub struct Color {
 }
 
 impl Color {
-    #[allow(dead_code)]
     pub fn new(r: f32, b: f32, g: f32) -> Self {
         Color { r, g, b }
     }
 
-    #[allow(dead_code)]
     pub fn with_u8(r: u8, g: u8, b: u8) -> Self {
         Color {
             r: Color::into_linear(r as f32 / 255.0),
@@ -21,7 +18,6 @@ impl Color {
         }
     }
 
-    #[allow(dead_code)]
     pub fn into_raw_u32(self) -> [u32; 3] {
         [self.r.to_bits(), self.g.to_bits(), self.b.to_bits()]
     }
@@ -6,6 +6,12 @@ pub struct Time {
     pub tick_count: u64,
 }
 
+impl Default for Time {
+    fn default() -> Self {
+        Time::new()
+    }
+}
+
 impl Time {
     pub fn new() -> Self {
         Time {
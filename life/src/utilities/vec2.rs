@@ -18,17 +18,15 @@ impl Vec2 {
 
     pub fn normalize(&mut self) {
         let m = self.magnitude();
-        self.x = self.x / m;
-        self.y = self.y / m;
+        self.x /= m;
+        self.y /= m;
     }
 
-    #[allow(dead_code)]
     pub fn normalized(&self) -> Self {
         let m = self.magnitude();
-        self.clone() / m
+        *self / m
     }
 
-    #[allow(dead_code)]
     pub fn into_raw_usize(self) -> Result<(usize, usize), &'static str> {
         if self.x < 0.0 || self.y < 0.0 {
             Err("This is a negative number! Cannot case to usize intelligently.")
@@ -37,11 +35,15 @@ impl Vec2 {
         }
     }
 
-    #[allow(dead_code)]
     pub fn to_bits(self) -> [u32; 2] {
         [self.x.to_bits(), self.y.to_bits()]
     }
 
+    /// Rounds both components down, so that negative positions land in the right cell.
+    pub fn floor_to_int(self) -> Vec2Int {
+        Vec2Int::new(self.x.floor() as i32, self.y.floor() as i32)
+    }
+
     pub fn clamp_components(&mut self, min_vec: &Vec2, max_vec: &Vec2) {
         self.x = self.x.max(min_vec.x).min(max_vec.x);
 
@@ -50,16 +52,12 @@ impl Vec2 {
 }
 
 impl Vec2 {
-    #[allow(dead_code)]
     pub const ZERO: Vec2 = Vec2 { x: 0.0, y: 0.0 };
 
-    #[allow(dead_code)]
     pub const ONE: Vec2 = Vec2 { x: 1.0, y: 1.0 };
 
-    #[allow(dead_code)]
     pub const UP: Vec2 = Vec2 { x: 0.0, y: 1.0 };
 
-    #[allow(dead_code)]
     pub const RIGHT: Vec2 = Vec2 { x: 1.0, y: 0.0 };
 }
 
@@ -2,7 +2,7 @@ use super::Vec2;
 use std::convert::TryInto;
 use std::fmt::{self, Display};
 
-#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct Vec2Int {
     pub x: i32,
     pub y: i32,
@@ -24,16 +24,14 @@ impl Vec2Int {
         self.y = (self.y as f32 / m) as i32;
     }
 
-    #[allow(dead_code)]
     pub fn normalized(&self) -> Self {
         let m = self.magnitude();
-        let mut ret = self.clone();
+        let mut ret = *self;
         ret.x = (self.x as f32 / m) as i32;
         ret.y = (self.y as f32 / m) as i32;
         ret
     }
 
-    #[allow(dead_code)]
     pub fn into_raw_usize(self) -> Result<(usize, usize), &'static str> {
         if self.x < 0 || self.y < 0 {
             Err("This is a negative number! Cannot cast to usize intelligently.")
@@ -42,7 +40,6 @@ impl Vec2Int {
         }
     }
 
-    #[allow(dead_code)]
     pub fn to_bits(self) -> [u32; 2] {
         [i32::try_into(self.x).unwrap(), i32::try_into(self.y).unwrap()]
     }
@@ -54,16 +51,12 @@ impl Vec2Int {
 }
 
 impl Vec2Int {
-    #[allow(dead_code)]
     pub const ZERO: Vec2Int = Vec2Int { x: 0, y: 0 };
 
-    #[allow(dead_code)]
     pub const ONE: Vec2Int = Vec2Int { x: 1, y: 1 };
 
-    #[allow(dead_code)]
     pub const UP: Vec2Int = Vec2Int { x: 0, y: 1 };
 
-    #[allow(dead_code)]
     pub const RIGHT: Vec2Int = Vec2Int { x: 1, y: 0 };
 }
 
@@ -1,5 +0,0 @@
-pub use super::{Vec2, Vec2Int};
-
-mod entity;
-
-pub use entity::{Entity, State};
\ No newline at end of file
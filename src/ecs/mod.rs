@@ -1,8 +1,10 @@
 pub use super::utilities::{Vec2, Time, Color, Vec2Int};
 pub use super::game::Game;
-pub use super::resources::{SoundsVFX, Sounds, Music};
+pub use super::resources::{
+    SoundsVFX, Sounds, Music, PREFAB_DIRECTORY, user_prefab_directory, Preferences, ColorPreferences,
+    PanelPreferences, PREFERENCES_VERSION,
+};
+pub use life::simulation::*;
 
 mod systems;
-mod entities;
 pub use systems::*;
-pub use entities::*;
\ No newline at end of file
@@ -1,11 +1,34 @@
-use super::{MouseButton, UserInput, Vec2, Window as WinitWindow, Vec2Int};
+use super::{MouseButton, Time, UserInput, Vec2, Vec2Int, Window as WinitWindow};
 use winit::VirtualKeyCode;
 
+/// Zoomed out this far, the world is drawn as density tiles rather than cells, so it's cheap.
+const MAX_INFINITE_SCALE: f32 = 100_000.0;
+const MIN_SCALE: f32 = 0.5;
+/// How much one notch of the mouse wheel zooms by.
+const ZOOM_STEP: f32 = 1.15;
+/// How quickly zooms and flights close in on where they're going, per second. Higher is snappier.
+const EASE_RATE: f32 = 14.0;
+/// How close, as a fraction of the scale, counts as having arrived.
+const EASE_SNAP: f32 = 0.001;
+/// How much room is left around whatever the camera zooms to fit.
+const FIT_MARGIN: f32 = 1.1;
+
 pub struct Camera {
     pub position: Vec2,
     pub scale: f32,
     pub aspect_ratio: f32,
     pub pan_speed: f32,
+    zoom: Option<Zoom>,
+    flight: Option<Vec2>,
+}
+
+/// A zoom on its way to `target_scale`.
+#[derive(Debug, Clone, Copy)]
+struct Zoom {
+    target_scale: f32,
+    /// The point which stays put on screen as the zoom happens, in the camera's coordinates
+    /// and in clip space, or `None` to zoom about the middle of the screen.
+    anchor: Option<(Vec2, Vec2)>,
 }
 
 impl Camera {
@@ -16,10 +39,20 @@ impl Camera {
             aspect_ratio,
             scale,
             pan_speed: 0.05,
+            zoom: None,
+            flight: None,
         }
     }
 
-    pub fn update(&mut self, user_input: &UserInput, winit_window: &WinitWindow, game_size: &Vec2Int) {
+    /// Moves and zooms the camera. `game_size` is `None` when the world is infinite,
+    /// in which case the camera can roam anywhere.
+    pub fn update(
+        &mut self,
+        user_input: &UserInput,
+        winit_window: &WinitWindow,
+        game_size: Option<Vec2Int>,
+        time: &Time,
+    ) {
         let mut move_vector: Vec2 =
             user_input
                 .kb_input
@@ -53,17 +86,110 @@ impl Camera {
             pan += ret / self.scale;
         }
 
-        self.position -= pan;
-        let mut size: Vec2 = game_size.clone().into();
-        size.y *= self.aspect_ratio;
-        self.position.clamp_components(&Vec2::ZERO, &size);
+        // Panning takes over from anything the camera was on its way to do.
+        if pan != Vec2::ZERO {
+            self.position -= pan;
+            self.flight = None;
+            if let Some(zoom) = &mut self.zoom {
+                zoom.anchor = None;
+            }
+        }
+
+        // Each notch of the wheel zooms by the same factor, keeping the cell under the mouse
+        // where it is on screen.
+        let scroll = user_input.mouse_input.mouse_vertical_scroll_delta;
+        if scroll != 0.0 {
+            let mut point = self.display_to_world_position(
+                user_input.mouse_input.mouse_position,
+                winit_window.get_window_size(),
+            );
+            point.y *= self.aspect_ratio;
+            let clip = (point - self.position) / self.scale;
+
+            let scale = self.zoom.map_or(self.scale, |zoom| zoom.target_scale);
+            self.flight = None;
+            self.zoom = Some(Zoom {
+                target_scale: scale * ZOOM_STEP.powf(scroll),
+                anchor: Some((point, clip)),
+            });
+        }
+
+        let max_scale = match game_size {
+            Some(game_size) => game_size.x as f32 * 2.0,
+            None => MAX_INFINITE_SCALE,
+        };
+
+        let ease = 1.0 - (-EASE_RATE * time.delta_time).exp();
+        if let Some(zoom) = self.zoom {
+            let target_scale = zoom.target_scale.min(max_scale).max(MIN_SCALE);
+            let ratio = target_scale / self.scale;
+            if (ratio - 1.0).abs() < EASE_SNAP {
+                self.scale = target_scale;
+                self.zoom = None;
+            } else {
+                // Easing the logarithm of the scale makes zooming in and out feel the same.
+                self.scale *= ratio.powf(ease);
+                self.zoom = Some(Zoom { target_scale, ..zoom });
+            }
+
+            if let Some((point, clip)) = zoom.anchor {
+                self.position = point - clip * self.scale;
+            }
+        }
+
+        if let Some(target) = self.flight {
+            let offset = target - self.position;
+            if offset.magnitude() < EASE_SNAP * self.scale {
+                self.position = target;
+                self.flight = None;
+            } else {
+                self.position += offset * ease;
+            }
+        }
 
-        if user_input.mouse_input.mouse_vertical_scroll_delta != 0.0 {
-            self.scale += user_input.mouse_input.mouse_vertical_scroll_delta;
-            self.scale = self.scale.min(size.x * 2.0).max(0.5);
+        if let Some(game_size) = game_size {
+            let mut size: Vec2 = game_size.into();
+            size.y *= self.aspect_ratio;
+            self.position.clamp_components(&Vec2::ZERO, &size);
         }
     }
 
+    /// The smallest and largest world positions on screen.
+    pub fn view_bounds(&self) -> (Vec2, Vec2) {
+        (
+            Vec2::new(
+                self.position.x - self.scale,
+                (self.position.y - self.scale) / self.aspect_ratio,
+            ),
+            Vec2::new(
+                self.position.x + self.scale,
+                (self.position.y + self.scale) / self.aspect_ratio,
+            ),
+        )
+    }
+
+    /// Puts the world position `pos` in the middle of the screen straight away, dropping
+    /// anything the camera was on its way to do.
+    pub fn center_on(&mut self, pos: Vec2) {
+        self.position = Vec2::new(pos.x, pos.y * self.aspect_ratio);
+        self.flight = None;
+        if let Some(zoom) = &mut self.zoom {
+            zoom.anchor = None;
+        }
+    }
+
+    /// Flies the camera over to frame the cells from `min` to `max`, with a little room to spare.
+    pub fn zoom_to(&mut self, min: Vec2Int, max: Vec2Int) {
+        let size = Vec2::from(max - min + Vec2Int::new(1, 1));
+        let center = Vec2::from(min) + size / 2.0;
+
+        self.flight = Some(Vec2::new(center.x, center.y * self.aspect_ratio));
+        self.zoom = Some(Zoom {
+            target_scale: (size.x / 2.0).max(size.y * self.aspect_ratio / 2.0) * FIT_MARGIN,
+            anchor: None,
+        });
+    }
+
     pub fn position_scaled(&self) -> Vec2 {
         self.position / self.scale
     }
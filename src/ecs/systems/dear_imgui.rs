@@ -1,5 +1,11 @@
-use super::{Camera, Color, Gameplay, Prefab, Time, UserInput, Vec2, Window as WinitWindow};
-use imgui::{Condition, Context, FontConfig, FontSource, ImGuiWindowFlags, ImStr, StyleVar, Ui, Window};
+use super::{
+    Camera, Color, Gameplay, Minimap, Prefab, Rule, Symmetry, Time, Topology, UserInput, Vec2,
+    Window as WinitWindow, World, MAX_INCREMENT_RATE,
+};
+use imgui::{
+    Condition, Context, FontConfig, FontSource, ImGuiSelectableFlags, ImGuiWindowFlags, ImStr, ImString,
+    StyleVar, Ui, Window,
+};
 use imgui_winit_support::{HiDpiMode, WinitPlatform};
 use std::collections::HashMap;
 
@@ -76,6 +82,11 @@ impl Imgui {
         if io.mouse_down[0] && io.want_capture_mouse {
             user_input.mouse_input.mouse_input_taken = true;
         }
+
+        // Don't let typing into a text box trigger our shortcuts.
+        if io.want_text_input {
+            user_input.kb_input.keyboard_input_taken = true;
+        }
     }
 
     pub fn begin_frame<'a>(&mut self, window: &WinitWindow) -> UiHandler<'_> {
@@ -104,13 +115,13 @@ impl Imgui {
         }
 
         if gameplay.show_settings_control {
-            let y_size = 200.0;
+            let y_size = 465.0;
             Window::new(ui, im_str!("Color"))
                 .size([300.0, y_size], Condition::FirstUseEver)
                 .position(
                     [
                         ui_handler.size.x - ((ui_handler.size.x - PWS) / 2.0) - 300.0,
-                        (ui_handler.size.y - PWH * 1.5) - (y_size + 100.0),
+                        (ui_handler.size.y - PWH * 1.5) - (y_size + 210.0),
                     ],
                     Condition::Always,
                 )
@@ -121,6 +132,62 @@ impl Imgui {
                         | ImGuiWindowFlags::NoMove,
                 )
                 .build(|| {
+                    // RULES
+                    let preset_names: Vec<ImString> = Rule::PRESETS
+                        .iter()
+                        .map(|(name, rulestring)| im_str!("{} ({})", name, rulestring))
+                        .collect();
+                    let preset_refs: Vec<&ImString> = preset_names.iter().collect();
+                    let mut current_preset = Rule::PRESETS
+                        .iter()
+                        .position(|(_, rulestring)| rulestring.parse().ok() == Some(gameplay.rule()))
+                        .map_or(-1, |i| i as i32);
+
+                    if ui.combo(im_str!("Presets"), &mut current_preset, &preset_refs, 6) {
+                        if let Ok(rule) = Rule::PRESETS[current_preset as usize].1.parse() {
+                            gameplay.set_rule(rule);
+                        }
+                    }
+
+                    let entered = ui
+                        .input_text(im_str!("Rule (enter)"), &mut gameplay.rule_input)
+                        .chars_noblank(true)
+                        .enter_returns_true(true)
+                        .build();
+                    if entered {
+                        let _ = gameplay.apply_rule_input();
+                    }
+                    if let Some(error) = &gameplay.rule_error {
+                        ui.text_colored([1.0, 0.3, 0.3, 1.0], &im_str!("{}", error));
+                    }
+
+                    // EDGES
+                    let topology_names: Vec<ImString> =
+                        Topology::ALL.iter().map(|topology| im_str!("{}", topology.name())).collect();
+                    let topology_refs: Vec<&ImString> = topology_names.iter().collect();
+                    let mut current_topology = Topology::ALL
+                        .iter()
+                        .position(|topology| *topology == gameplay.topology)
+                        .unwrap_or(0) as i32;
+                    if ui.combo(im_str!("Edges"), &mut current_topology, &topology_refs, 5) {
+                        gameplay.topology = Topology::ALL[current_topology as usize];
+                    }
+
+                    // THREADS
+                    let mut threads = gameplay.simulation_threads;
+                    let max_threads = rayon::current_num_threads() as i32;
+                    if ui.slider_int(im_str!("Threads"), &mut threads, 1, max_threads).build() {
+                        gameplay.set_simulation_threads(threads);
+                    }
+
+                    // UNDO
+                    let mut undo_memory = gameplay.undo_memory_megabytes;
+                    if ui.slider_int(im_str!("Undo Memory (MB)"), &mut undo_memory, 1, 1024).build() {
+                        gameplay.set_undo_memory(undo_memory);
+                    }
+
+                    ui.separator();
+
                     // SOUND
                     ui.slider_float(im_str!("SFX"), &mut gameplay.game_sounds.sfx_volume, 0.0, 1.0)
                         .build();
@@ -145,6 +212,7 @@ impl Imgui {
                     make_color(im_str!("Dead"), &mut gameplay.game_colors.dead, ui);
                     make_color(im_str!("Unborn"), &mut gameplay.game_colors.unborn, ui);
                     make_color(im_str!("Background"), &mut gameplay.game_colors.bg, ui);
+                    make_color(im_str!("Selection"), &mut gameplay.game_colors.selection, ui);
 
                     ui.separator();
 
@@ -164,17 +232,27 @@ impl Imgui {
                     drop(_width);
 
                     make_color(im_str!("Grid"), &mut gameplay.game_colors.grid_line_color, ui);
+
+                    ui.separator();
+
+                    // AGES
+                    ui.checkbox(im_str!("Age Colors"), &mut gameplay.game_colors.age_coloring);
+                    ui.slider_float(im_str!("Age Span"), &mut gameplay.game_colors.age_span, 1.0, 1000.0)
+                        .power(3.0)
+                        .build();
+                    make_color(im_str!("Old Alive"), &mut gameplay.game_colors.old_alive, ui);
+                    make_color(im_str!("Long Dead"), &mut gameplay.game_colors.long_dead, ui);
                 });
         }
 
         // Auto-Increment World
         if gameplay.show_play_control {
             Window::new(ui, im_str!("Game of Life"))
-                .size([300.0, 150.0], Condition::FirstUseEver)
+                .size([300.0, 260.0], Condition::FirstUseEver)
                 .position(
                     [
                         ui_handler.size.x - ((ui_handler.size.x - PWS) / 2.0) - 300.0,
-                        (ui_handler.size.y - PWH * 1.5) - 150.0,
+                        (ui_handler.size.y - PWH * 1.5) - 260.0,
                     ],
                     Condition::Always,
                 )
@@ -190,8 +268,8 @@ impl Imgui {
                         ui.radio_button_bool(im_str!("Automatically Increment"), gameplay.auto_increment);
 
                     let a = ui.push_item_width(80.0);
-                    ui.slider_float(im_str!("Per Second"), &mut gameplay.increment_rate, 0.0, 50.0)
-                        .build();
+                    let rate = &mut gameplay.increment_rate;
+                    ui.slider_float(im_str!("Per Second"), rate, 0.0, MAX_INCREMENT_RATE).build();
                     drop(a);
                     ui.same_line(175.0);
 
@@ -228,16 +306,61 @@ impl Imgui {
                         gameplay.resize_this_frame();
                     }
 
+                    if ui.checkbox(im_str!("Infinite World"), &mut gameplay.infinite_world) {
+                        gameplay.resize_this_frame();
+                    }
+
                     ui.separator();
+
+                    // Long jumps across a bounded board take a few frames, and can be cut short.
+                    if let Some(progress) = gameplay.jump_progress() {
+                        ui.progress_bar(progress).size([165.0, 19.5]).build();
+                        ui.same_line_with_spacing(165.0, 10.0);
+
+                        if ui.button(im_str!("Stop"), [100.0, 19.5]) {
+                            gameplay.stop_jump();
+                        }
+                    } else {
+                        let a = ui.push_item_width(165.0);
+                        ui.input_int(im_str!("##Jump Generations"), &mut gameplay.jump_generations)
+                            .step(64)
+                            .step_fast(1024)
+                            .build();
+                        drop(a);
+                        ui.same_line_with_spacing(ui.get_item_rect_size()[0], 10.0);
+
+                        if ui.button(im_str!("Jump"), [100.0, 19.5]) {
+                            gameplay.jump_this_frame();
+                        }
+                    }
+
+                    ui.separator();
+
+                    // Generations are recorded like any other edit, so undo is also how we step back.
+                    if ui.button(im_str!("Undo (ctrl+z)"), [130.0, 19.5]) && gameplay.can_undo() {
+                        gameplay.undo_this_frame();
+                    }
+                    ui.same_line(145.0);
+                    if ui.button(im_str!("Redo (ctrl+y)"), [130.0, 19.5]) && gameplay.can_redo() {
+                        gameplay.redo_this_frame();
+                    }
+
+                    if ui.button(im_str!("Zoom to Fit (home)"), [130.0, 19.5]) {
+                        gameplay.zoom_to_fit_this_frame();
+                    }
+                    ui.same_line(145.0);
+                    if ui.button(im_str!("Zoom to Selection"), [130.0, 19.5]) {
+                        gameplay.zoom_to_selection_this_frame();
+                    }
                 });
         }
 
         if gameplay.show_instructions {
             Window::new(ui, im_str!("Instructions"))
-                .size([400.0, 200.0], Condition::FirstUseEver)
+                .size([400.0, 335.0], Condition::FirstUseEver)
                 .flags(ImGuiWindowFlags::NoResize)
                 .position(
-                    ((ui_handler.size / 2.0) - Vec2::new(200.0, 100.0)).into(),
+                    ((ui_handler.size / 2.0) - Vec2::new(200.0, 167.5)).into(),
                     Condition::Always,
                 )
                 .title_bar(false)
@@ -247,12 +370,21 @@ impl Imgui {
 
 CLICK on a cell to change it from LIVE to DEAD.
 Use the MOUSE WHEEL to zoom in and out.
+Press HOME to zoom to fit, SHIFT+HOME to zoom to the selection.
 
-Click on a Prefab below, then on a cell,
-to PASTE it into the world.
+Pick a Prefab from the library, then click
+on a cell to PASTE it into the world.
+SHIFT-DRAG to select cells to copy, cut or clear.
 
+Press CTRL+Z to undo and CTRL+Y to redo.
 Press F2 to bring these instructions back.
-Press F1 to hide all UI."
+Press F1 to hide all UI.
+Press F4 to save the board to board.rle.
+Press F5 to show the population graph.
+Press F6 to count the objects on the board.
+Press F7 to fill the board with a random soup.
+Press F8 to search many soups for rare objects.
+Press F9 to show a map of the whole board."
                     ));
                     // ui.same_line_with_spacing(0.0, 20.0);
                     ui.spacing();
@@ -286,26 +418,30 @@ Press F1 to hide all UI."
                 ui.spacing();
                 ui.spacing();
 
-                fn make_prefab_button(
-                    prefab: Prefab,
-                    ui: &mut Ui<'_>,
-                    horizontal: &mut f32,
-                    gameplay: &mut Gameplay,
-                ) {
-                    ui.same_line(*horizontal);
-                    let glider = ui.button(&im_str!("{}", prefab.get_static_name()), [BUTTON, 50.0]);
-                    if glider {
-                        gameplay.saved_prefab = Some(prefab)
-                    }
-                    *horizontal += BUTTON;
-                }
-
-                make_prefab_button(Prefab::Glider, ui, &mut horizontal, gameplay);
-                make_prefab_button(Prefab::SmallExploder, ui, &mut horizontal, gameplay);
-                make_prefab_button(Prefab::Exploder, ui, &mut horizontal, gameplay);
-                make_prefab_button(Prefab::Spaceship, ui, &mut horizontal, gameplay);
-                make_prefab_button(Prefab::Tumbler, ui, &mut horizontal, gameplay);
-                make_prefab_button(Prefab::GliderGun, ui, &mut horizontal, gameplay);
+                ui.same_line(horizontal);
+                ui.child_frame(im_str!("Placing"), [BUTTON * 6.0, 50.0]).build(|| {
+                    match &gameplay.saved_prefab {
+                        Some(prefab) => ui.text(im_str!(
+                            "Click on a cell to paste the {}. R rotates, F mirrors, Escape cancels.",
+                            prefab.name
+                        )),
+                        None => ui.text(im_str!(
+                            "Pick a prefab from the library, then click on a cell. Shift-drag to select."
+                        )),
+                    }
+                    ui.checkbox(im_str!("Prefab Library"), &mut gameplay.show_prefab_library);
+                    ui.same_line(140.0);
+                    ui.checkbox(im_str!("Population (F5)"), &mut gameplay.show_statistics);
+                    ui.same_line(280.0);
+                    ui.checkbox(im_str!("Census (F6)"), &mut gameplay.show_census);
+                    ui.same_line(420.0);
+                    ui.checkbox(im_str!("Soup (F7)"), &mut gameplay.show_soup);
+                    ui.same_line(560.0);
+                    ui.checkbox(im_str!("Search (F8)"), &mut gameplay.show_search);
+                    ui.same_line(700.0);
+                    ui.checkbox(im_str!("Minimap (F9)"), &mut gameplay.show_minimap);
+                });
+                horizontal += BUTTON * 6.0;
 
                 horizontal += PWS / 125.0;
 
@@ -317,6 +453,447 @@ Press F1 to hide all UI."
                     ui.checkbox(im_str!("Settings"), &mut gameplay.show_settings_control);
                 });
             });
+
+        // PREFAB LIBRARY
+        if gameplay.show_prefab_library {
+            const LWS: f32 = 260.0;
+            const LWH: f32 = 420.0;
+            Window::new(ui, im_str!("Prefab Library"))
+                .size([LWS, LWH], Condition::FirstUseEver)
+                .position(
+                    [
+                        (ui_handler.size.x - PWS) / 2.0,
+                        (ui_handler.size.y - PWH * 1.5) - (LWH + 10.0),
+                    ],
+                    Condition::Always,
+                )
+                .flags(ImGuiWindowFlags::NoResize | ImGuiWindowFlags::NoMove)
+                .build(|| {
+                    ui.input_text(im_str!("Search"), &mut gameplay.prefab_search).build();
+                    ui.separator();
+
+                    let query = gameplay.prefab_search.to_str().to_owned();
+                    let mut chosen: Option<Prefab> = None;
+                    let prefabs = gameplay.prefabs();
+                    if prefabs.iter().next().is_none() {
+                        ui.text_wrapped(im_str!(
+                            "No prefabs found. Add .rle, .cells, .lif or .json files to resources/prefabs."
+                        ));
+                    }
+
+                    for category in prefabs.categories() {
+                        let matching: Vec<&Prefab> = prefabs
+                            .iter()
+                            .filter(|prefab| prefab.category == category && prefab.matches(&query))
+                            .collect();
+                        if matching.is_empty() {
+                            continue;
+                        }
+
+                        let header = im_str!("{} ({})###{}", category, matching.len(), category);
+                        if ui.collapsing_header(&header).default_open(true).build() == false {
+                            continue;
+                        }
+
+                        for prefab in matching {
+                            let selected = gameplay.saved_prefab.as_ref().map_or(false, |saved| {
+                                saved.name == prefab.name && saved.category == prefab.category
+                            });
+                            let label = im_str!("{}##{}", prefab.name, category);
+                            if ui.selectable(&label, selected, ImGuiSelectableFlags::empty(), [0.0, 0.0]) {
+                                chosen = Some(prefab.clone());
+                            }
+
+                            if ui.is_item_hovered() {
+                                ui.tooltip(|| {
+                                    ui.text(im_str!("{}", prefab.name));
+                                    if let Some(author) = &prefab.author {
+                                        ui.text(im_str!("by {}", author));
+                                    }
+                                    ui.text(im_str!(
+                                        "{} x {}",
+                                        prefab.cells.len(),
+                                        prefab.cells.first().map_or(0, |column| column.len())
+                                    ));
+                                    if prefab.description.is_empty() == false {
+                                        ui.separator();
+                                        ui.text(im_str!("{}", prefab.description));
+                                    }
+                                });
+                            }
+                        }
+                    }
+
+                    if chosen.is_some() {
+                        gameplay.saved_prefab = chosen;
+                    }
+                });
+        }
+
+        // SOUP
+        if gameplay.show_soup {
+            let mut opened = true;
+            Window::new(ui, im_str!("Random Soup"))
+                .size([300.0, 180.0], Condition::FirstUseEver)
+                .position([10.0, ui_handler.size.y / 2.0 - 90.0], Condition::FirstUseEver)
+                .opened(&mut opened)
+                .build(|| {
+                    match gameplay.selection {
+                        Some(selection) => {
+                            let size = selection.size();
+                            ui.text(im_str!("Fills the {} x {} selection.", size.x, size.y));
+                        }
+                        None => ui.text(im_str!("Fills the whole board.")),
+                    }
+
+                    ui.input_text(im_str!("Seed"), &mut gameplay.soup_seed)
+                        .chars_decimal(true)
+                        .build();
+                    if let Some(error) = &gameplay.soup_error {
+                        ui.text_colored([1.0, 0.3, 0.3, 1.0], &im_str!("{}", error));
+                    }
+                    ui.slider_float(im_str!("Density"), &mut gameplay.fill_density, 0.0, 1.0)
+                        .build();
+
+                    let symmetry_names: Vec<ImString> =
+                        Symmetry::ALL.iter().map(|symmetry| im_str!("{}", symmetry)).collect();
+                    let symmetry_refs: Vec<&ImString> = symmetry_names.iter().collect();
+                    let mut current_symmetry = Symmetry::ALL
+                        .iter()
+                        .position(|symmetry| *symmetry == gameplay.soup_symmetry)
+                        .unwrap_or(0) as i32;
+                    if ui.combo(im_str!("Symmetry"), &mut current_symmetry, &symmetry_refs, 4) {
+                        gameplay.soup_symmetry = Symmetry::ALL[current_symmetry as usize];
+                    }
+                    if gameplay.soup_symmetry.needs_square() {
+                        ui.text_wrapped(&im_str!(
+                            "{} soups fill the largest square in the middle.",
+                            gameplay.soup_symmetry
+                        ));
+                    }
+
+                    if ui.button(im_str!("Randomize"), [130.0, 19.5]) {
+                        gameplay.new_soup_seed();
+                        gameplay.randomize_this_frame();
+                    }
+                    ui.same_line(145.0);
+                    if ui.button(im_str!("Reuse Seed"), [130.0, 19.5]) {
+                        gameplay.randomize_this_frame();
+                    }
+
+                    if let Some((soup, size)) = gameplay.soup() {
+                        ui.text_wrapped(&im_str!("Last soup: {} x {}, {}", size.x, size.y, soup));
+                    }
+                });
+            gameplay.show_soup = opened;
+        }
+
+        // SOUP SEARCH
+        if gameplay.show_search {
+            let mut opened = true;
+            Window::new(ui, im_str!("Soup Search"))
+                .size([420.0, 360.0], Condition::FirstUseEver)
+                .position([320.0, ui_handler.size.y / 2.0 - 180.0], Condition::FirstUseEver)
+                .opened(&mut opened)
+                .build(|| {
+                    ui.text_wrapped(im_str!(
+                        "Runs random soups until they settle, using the seed, density and symmetry \
+                         from the Random Soup window and the current rule."
+                    ));
+                    ui.slider_int(im_str!("Soup Size"), &mut gameplay.search_soup_size, 4, 64)
+                        .build();
+                    ui.input_int(im_str!("Max Generations"), &mut gameplay.search_max_generations)
+                        .step(1000)
+                        .step_fast(10000)
+                        .build();
+
+                    if gameplay.is_searching() {
+                        if ui.button(im_str!("Stop"), [130.0, 19.5]) {
+                            gameplay.stop_search();
+                        }
+                    } else if ui.button(im_str!("Start"), [130.0, 19.5]) {
+                        gameplay.start_search();
+                    }
+                    ui.same_line(145.0);
+                    ui.text(im_str!("Finds go in {}", gameplay.search_results_file()));
+                    if let Some(error) = &gameplay.search_error {
+                        ui.text_colored([1.0, 0.3, 0.3, 1.0], &im_str!("{}", error));
+                    }
+
+                    if let Some(progress) = gameplay.search_progress() {
+                        ui.text(im_str!(
+                            "{} soups, {} finds, next seed {}",
+                            progress.soups_searched,
+                            progress.total_finds,
+                            progress.next_seed
+                        ));
+
+                        ui.separator();
+                        ui.columns(2, im_str!("Search Tally"), true);
+                        for (name, count) in progress.tally.iter().take(10) {
+                            ui.text(im_str!("{}", name));
+                            ui.next_column();
+                            ui.text(im_str!("{}", count));
+                            ui.next_column();
+                        }
+                        ui.columns(1, im_str!("Search Tally"), false);
+                    }
+
+                    ui.separator();
+                    let mut tried = None;
+                    for find in gameplay.search_finds() {
+                        if ui.small_button(&im_str!("Try##{}", find.seed)) {
+                            tried = Some(find.clone());
+                        }
+                        ui.same_line(0.0);
+                        if find.stabilized {
+                            let objects: Vec<String> =
+                                find.objects.iter().map(|entry| entry.name.clone()).collect();
+                            ui.text(im_str!("Seed {}: {}", find.seed, objects.join(", ")));
+                        } else {
+                            ui.text(im_str!("Seed {}: didn't settle", find.seed));
+                        }
+                    }
+                    if let Some(find) = tried {
+                        gameplay.try_search_find(&find);
+                    }
+                });
+            gameplay.show_search = opened;
+        }
+
+        // SELECTION
+        if gameplay.selection.is_some() || gameplay.clipboard_size().is_some() {
+            const SWS: f32 = 260.0;
+            const SWH: f32 = 190.0;
+            Window::new(ui, im_str!("Selection"))
+                .size([SWS, SWH], Condition::FirstUseEver)
+                .position(
+                    [
+                        (ui_handler.size.x - PWS) / 2.0 + 270.0,
+                        (ui_handler.size.y - PWH * 1.5) - (SWH + 10.0),
+                    ],
+                    Condition::Always,
+                )
+                .flags(ImGuiWindowFlags::NoResize | ImGuiWindowFlags::NoMove)
+                .build(|| {
+                    match gameplay.selection {
+                        Some(selection) => {
+                            let size = selection.size();
+                            ui.text(im_str!("{} x {} selected. Escape deselects.", size.x, size.y));
+                        }
+                        None => ui.text(im_str!("Shift-drag to select cells.")),
+                    }
+
+                    if ui.button(im_str!("Copy (ctrl+c)"), [115.0, 19.5]) {
+                        gameplay.copy_this_frame();
+                    }
+                    ui.same_line(130.0);
+                    if ui.button(im_str!("Cut (ctrl+x)"), [115.0, 19.5]) {
+                        gameplay.cut_this_frame();
+                    }
+
+                    if ui.button(im_str!("Clear (delete)"), [115.0, 19.5]) {
+                        gameplay.clear_this_frame();
+                    }
+                    ui.same_line(130.0);
+                    if ui.button(im_str!("Fill Random"), [115.0, 19.5]) {
+                        gameplay.new_soup_seed();
+                        gameplay.randomize_this_frame();
+                    }
+                    ui.slider_float(im_str!("Density"), &mut gameplay.fill_density, 0.0, 1.0)
+                        .build();
+
+                    ui.separator();
+
+                    match gameplay.clipboard_size() {
+                        Some(size) => {
+                            ui.text(im_str!("Clipboard: {} x {}", size.x, size.y));
+                            if ui.button(im_str!("Paste (ctrl+v)"), [115.0, 19.5]) {
+                                gameplay.paste_clipboard();
+                            }
+
+                            ui.input_text(im_str!("Name"), &mut gameplay.clipboard_name).build();
+                            if ui.button(im_str!("Save as Prefab"), [115.0, 19.5]) {
+                                gameplay.save_clipboard_as_prefab();
+                            }
+                        }
+                        None => ui.text(im_str!("The clipboard is empty.")),
+                    }
+                });
+        }
+    }
+
+    pub fn make_statistics_ui(ui_handler: &UiHandler<'_>, gameplay: &mut Gameplay, world: &World) {
+        let ui = &ui_handler.ui;
+        if gameplay.show_ui == false || gameplay.show_statistics == false {
+            return;
+        }
+
+        const SWS: f32 = 320.0;
+        let mut opened = true;
+        Window::new(ui, im_str!("Population"))
+            .size([SWS, 250.0], Condition::FirstUseEver)
+            .position([ui_handler.size.x - SWS - 10.0, 10.0], Condition::FirstUseEver)
+            .opened(&mut opened)
+            .build(|| {
+                let statistics = gameplay.statistics();
+                ui.text(im_str!("Generation: {}", statistics.generation()));
+                ui.text(im_str!("Population: {}", world.population()));
+                if let Some(latest) = statistics.latest() {
+                    ui.text(im_str!("Births: {}  Deaths: {}", latest.births, latest.deaths));
+                }
+                match gameplay.periodicity() {
+                    Some(periodicity) => ui.text(im_str!("Pattern: {}", periodicity)),
+                    None => ui.text(im_str!("Pattern: not repeating yet")),
+                }
+                ui.separator();
+
+                let populations: Vec<f32> = statistics
+                    .samples()
+                    .map(|sample| sample.population as f32)
+                    .collect();
+                match statistics.population_range() {
+                    Some((min, max, average)) => {
+                        let overlay = im_str!("last {} steps", populations.len());
+                        ui.plot_lines(im_str!("##Population"), &populations)
+                            .overlay_text(&overlay)
+                            .scale_min(0.0)
+                            .scale_max(max.max(1) as f32)
+                            .graph_size([SWS - 20.0, 80.0])
+                            .build();
+                        ui.text(im_str!("Min: {}  Max: {}  Average: {:.1}", min, max, average));
+                    }
+                    None => ui.text_wrapped(im_str!("Step the world to start recording the population.")),
+                }
+            });
+        gameplay.show_statistics = opened;
+    }
+
+    pub fn make_census_ui(ui_handler: &UiHandler<'_>, gameplay: &mut Gameplay) {
+        let ui = &ui_handler.ui;
+        if gameplay.show_ui == false || gameplay.show_census == false {
+            return;
+        }
+
+        const CWS: f32 = 420.0;
+        let mut opened = true;
+        Window::new(ui, im_str!("Census"))
+            .size([CWS, 300.0], Condition::FirstUseEver)
+            .position([ui_handler.size.x - CWS - 10.0, 270.0], Condition::FirstUseEver)
+            .opened(&mut opened)
+            .build(|| {
+                if gameplay.taking_census() {
+                    ui.text(im_str!("Taking census..."));
+                } else if ui.button(im_str!("Take Census"), [120.0, 19.5]) {
+                    gameplay.take_census_this_frame();
+                }
+                if gameplay.census().is_some() {
+                    ui.same_line(135.0);
+                    if ui.button(im_str!("Save JSON"), [120.0, 19.5]) {
+                        gameplay.save_census("census.json");
+                    }
+                    ui.same_line(260.0);
+                    if ui.button(im_str!("Save CSV"), [120.0, 19.5]) {
+                        gameplay.save_census("census.csv");
+                    }
+                }
+
+                let (generation, census) = match gameplay.census() {
+                    Some(census) => census,
+                    None => {
+                        ui.text_wrapped(im_str!("Let the board settle down, then take a census."));
+                        return;
+                    }
+                };
+                ui.text(im_str!(
+                    "{} objects at generation {}.",
+                    census.total_objects(),
+                    generation
+                ));
+                ui.separator();
+
+                ui.columns(4, im_str!("Census Table"), true);
+                for heading in &[im_str!("Object"), im_str!("Kind"), im_str!("Cells"), im_str!("Count")] {
+                    ui.text(heading);
+                    ui.next_column();
+                }
+                ui.separator();
+                for entry in &census.entries {
+                    ui.text(im_str!("{}", entry.name));
+                    ui.next_column();
+                    ui.text(im_str!("{}", entry.kind));
+                    ui.next_column();
+                    ui.text(im_str!("{}", entry.cells));
+                    ui.next_column();
+                    ui.text(im_str!("{}", entry.count));
+                    ui.next_column();
+                }
+                ui.columns(1, im_str!("Census Table"), false);
+            });
+        gameplay.show_census = opened;
+    }
+
+    pub fn make_minimap_ui(
+        ui_handler: &UiHandler<'_>,
+        gameplay: &mut Gameplay,
+        camera: &mut Camera,
+        minimap: &Minimap,
+    ) {
+        let ui = &ui_handler.ui;
+        let texture = match minimap.texture {
+            Some(texture) => texture,
+            None => return,
+        };
+        if gameplay.show_ui == false || gameplay.show_minimap == false {
+            return;
+        }
+
+        const MWS: f32 = 240.0;
+        let mut opened = true;
+        Window::new(ui, im_str!("Minimap"))
+            .size([MWS, MWS + 25.0], Condition::FirstUseEver)
+            .position([10.0, ui_handler.size.y - MWS - 35.0], Condition::FirstUseEver)
+            .opened(&mut opened)
+            .build(|| {
+                // The board keeps its shape, fitted into whatever room the window has.
+                let used: Vec2 = minimap.used_size().into();
+                let room = ui.get_content_region_avail();
+                let fit = (room[0] / used.x).min(room[1] / used.y).max(0.0);
+                let size = [used.x * fit, used.y * fit];
+                if size[0] < 1.0 || size[1] < 1.0 {
+                    return;
+                }
+
+                let corner = ui.get_cursor_screen_pos();
+                ui.image(texture, size).uv1(minimap.uv_max()).build();
+
+                // Clicking or dragging on the map puts the camera there.
+                ui.set_cursor_screen_pos(corner);
+                ui.invisible_button(im_str!("##Minimap"), size);
+                if ui.is_item_active() {
+                    let mouse = ui.io().mouse_pos;
+                    let pixel = Vec2::new(
+                        ((mouse[0] - corner[0]) / fit).max(0.0).min(used.x),
+                        ((mouse[1] - corner[1]) / fit).max(0.0).min(used.y),
+                    );
+                    camera.center_on(minimap.pixel_to_world(pixel));
+                }
+
+                let (view_min, view_max) = camera.view_bounds();
+                let (view_min, view_max) =
+                    (minimap.world_to_pixel(view_min), minimap.world_to_pixel(view_max));
+                let to_screen = |pixel: Vec2| {
+                    [
+                        corner[0] + pixel.x.max(0.0).min(used.x) * fit,
+                        corner[1] + pixel.y.max(0.0).min(used.y) * fit,
+                    ]
+                };
+                ui.get_window_draw_list()
+                    .add_rect(to_screen(view_min), to_screen(view_max), [1.0, 1.0, 1.0, 0.8])
+                    .thickness(1.5)
+                    .build();
+            });
+        gameplay.show_minimap = opened;
     }
 
     pub fn make_debug_ui(ui_handler: &UiHandler<'_>, gameplay: &Gameplay, camera: &mut Camera, time: &Time) {
@@ -1,32 +1,115 @@
 use super::{
-    simple_serialization, Color, Entity, MouseButton, Music, Prefab, SoundPlayer, Sounds, SoundsVFX, State,
-    Time, UserInput, Vec2, Vec2Int,
+    simple_serialization, user_prefab_directory, Age, BackgroundCensus, BackgroundSearch, Catalogue,
+    CellAges, Census, Color, ColorPreferences, Grid, History, MouseButton, Music, PanelPreferences,
+    PeriodDetector, Periodicity, Prefab, Prefabs, Preferences, Rule, RuleError, SearchFind, SearchProgress,
+    SearchSettings, Selection, SoundPlayer, Sounds, SoundsVFX, Soup, SoupSearch, SparseGrid, State,
+    Statistics, Symmetry, Time, Topology, UserInput, Vec2, Vec2Int, World, WorldError, PREFAB_DIRECTORY,
+    PREFERENCES_VERSION,
 };
 use anymap::AnyMap;
+use imgui::ImString;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use rodio::Sink;
-use std::{fmt::Debug, io::Cursor};
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    io::Cursor,
+    mem,
+    path::{Path, PathBuf},
+    sync::{mpsc::TryRecvError, Arc},
+    time::{Duration, Instant},
+};
 use winit::VirtualKeyCode as Key;
 
-type UsizeTuple = (usize, usize);
 type SoundFile = &'static [u8];
 
+const BYTES_PER_MEGABYTE: usize = 1024 * 1024;
+
+/// The fastest the game can be set to play, in generations a second.
+pub const MAX_INCREMENT_RATE: f32 = 50.0;
+
+/// How many steps the population graph goes back.
+const STATISTICS_CAPACITY: usize = 500;
+
+/// The longest period we look for before giving up on remembering older generations.
+const MAX_DETECTED_PERIOD: usize = 1024;
+
+/// How long dead cells keep their age, which is as far as the age gradient can reach.
+pub const MAX_TRACKED_AGE: u64 = 1000;
+
+/// Where the soup search writes what it finds.
+const SEARCH_RESULTS_FILE: &str = "search_results.jsonl";
+
+/// How many of the soup search's finds are kept around to show.
+const RECENT_FINDS: usize = 20;
+
+/// How long a jump across a bounded world steps for each frame, so the window keeps drawing
+/// while a long jump works its way through.
+const JUMP_FRAME_BUDGET: Duration = Duration::from_millis(12);
+
+/// Where prefabs made from the clipboard end up in the library.
+const SAVED_PREFAB_CATEGORY: &str = "Saved";
+
 pub struct Gameplay {
     pub show_debug: bool,
     pub auto_increment: bool,
     pub show_instructions: bool,
     pub show_play_control: bool,
+    pub show_prefab_library: bool,
     pub show_settings_control: bool,
+    pub show_statistics: bool,
+    pub show_census: bool,
+    pub show_soup: bool,
+    pub show_search: bool,
+    pub show_minimap: bool,
     pub increment_rate: f32,
+    pub jump_generations: i32,
     pub current_time: f32,
     pub playing: bool,
     pub show_ui: bool,
     pub game_colors: GameColors,
     pub game_sounds: GameSounds,
     pub saved_prefab: Option<Prefab>,
-    pub wrap_grid: bool,
+    pub prefab_search: ImString,
+    pub selection: Option<Selection>,
+    pub clipboard_name: ImString,
+    pub fill_density: f32,
+    pub soup_seed: ImString,
+    pub soup_symmetry: Symmetry,
+    pub soup_error: Option<String>,
+    pub search_soup_size: i32,
+    pub search_max_generations: i32,
+    pub search_error: Option<String>,
+    pub topology: Topology,
+    pub infinite_world: bool,
+    pub rule_input: ImString,
+    pub rule_error: Option<String>,
+    pub simulation_threads: i32,
+    pub undo_memory_megabytes: i32,
+    rule: Rule,
+    thread_pool: Option<ThreadPool>,
     game_size: Vec2,
     next_game_size: Option<Vec2>,
-    coords_pressed: Vec<UsizeTuple>,
+    coords_pressed: Vec<Vec2Int>,
+    selecting: bool,
+    clipboard: Option<Vec<Vec<State>>>,
+    history: History,
+    statistics: Statistics,
+    ages: CellAges,
+    period_detector: PeriodDetector,
+    census: Option<(u64, Census)>,
+    /// The census being taken, and the generation it's of.
+    census_job: Option<(u64, BackgroundCensus)>,
+    /// Kept from one census to the next, as long as the rule stays the same.
+    catalogue: Option<Arc<Catalogue>>,
+    jump: Option<Jump>,
+    soup: Option<(Soup, Vec2Int)>,
+    search: Option<BackgroundSearch>,
+    /// Searches told to stop, kept until their threads end so that the last batch still
+    /// shows. The newest is last.
+    stopping_searches: Vec<BackgroundSearch>,
+    search_progress: Option<SearchProgress>,
+    search_finds: VecDeque<SearchFind>,
     prefabs: Prefabs,
     sound_player: SoundPlayer,
     flags: GameplayFlags,
@@ -36,32 +119,74 @@ impl Gameplay {
     pub fn new(resources: &AnyMap, game_size: Vec2) -> Result<Self, Error> {
         let sound_player = SoundPlayer::new();
         let music_sink = sound_player.make_sink();
+        let rule = Rule::default();
 
-        let this = Gameplay {
+        let mut this = Gameplay {
             auto_increment: false,
             coords_pressed: Vec::new(),
+            selection: None,
+            selecting: false,
+            clipboard: None,
+            clipboard_name: ImString::with_capacity(64),
+            fill_density: 0.5,
+            soup_seed: Self::make_soup_seed(Soup::random_seed()),
+            soup_symmetry: Symmetry::None,
+            soup_error: None,
+            soup: None,
+            search_soup_size: SearchSettings::default().soup_size as i32,
+            search_max_generations: SearchSettings::default().max_generations as i32,
+            search_error: None,
+            search: None,
+            stopping_searches: vec![],
+            search_progress: None,
+            search_finds: VecDeque::with_capacity(RECENT_FINDS),
+            history: History::new(64 * BYTES_PER_MEGABYTE),
+            statistics: Statistics::new(STATISTICS_CAPACITY),
+            ages: CellAges::new(MAX_TRACKED_AGE),
+            period_detector: PeriodDetector::new(MAX_DETECTED_PERIOD),
+            census: None,
+            census_job: None,
+            catalogue: None,
+            jump: None,
             increment_rate: 1.0,
+            jump_generations: 1024,
             show_debug: true,
             current_time: 0.0,
             show_instructions: true,
             show_ui: true,
             playing: true,
-            wrap_grid: false,
+            topology: Topology::Dead,
+            infinite_world: false,
+            rule_input: Self::make_rule_input(&rule),
+            rule_error: None,
+            simulation_threads: 1,
+            undo_memory_megabytes: 64,
+            rule,
+            thread_pool: None,
             show_play_control: true,
+            show_prefab_library: true,
             show_settings_control: false,
+            show_statistics: false,
+            show_census: false,
+            show_minimap: false,
+            show_soup: false,
+            show_search: false,
             game_colors: GameColors::default(),
             game_sounds: GameSounds::new(resources, music_sink),
             sound_player: SoundPlayer::new(),
             saved_prefab: None,
-            prefabs: Prefabs::new()?,
+            prefab_search: ImString::with_capacity(64),
+            prefabs: Self::load_prefabs(),
             game_size,
             next_game_size: None,
             flags: GameplayFlags::empty(),
         };
+        this.set_simulation_threads(rayon::current_num_threads() as i32);
 
         Ok(this)
     }
 
+    #[allow(dead_code)]
     pub fn game_size(&self) -> Vec2Int {
         self.game_size.into()
     }
@@ -81,31 +206,519 @@ impl Gameplay {
         self.flags.insert(GameplayFlags::RESIZE);
     }
 
-    pub fn select(&mut self, click_pos: UsizeTuple, entities: &mut [Vec<Entity>]) {
-        match &self.saved_prefab {
-            Some(prefab) => {
-                if let Some(prefab) = self.prefabs.prefabs.get(&prefab) {
-                    Self::paste_cells(click_pos, prefab, entities);
+    pub fn jump_this_frame(&mut self) {
+        self.flags.insert(GameplayFlags::JUMP);
+    }
 
-                    self.saved_prefab = None;
-                    self.coords_pressed.push(click_pos);
-                    self.sound_player.play_sound(
-                        Cursor::new(self.game_sounds.alive_sound),
-                        self.game_sounds.sfx_volume,
-                    );
+    /// How far through the jump in progress is, from 0 to 1, or `None` if there isn't one.
+    pub fn jump_progress(&self) -> Option<f32> {
+        self.jump
+            .as_ref()
+            .map(|jump| jump.done as f32 / jump.total.max(1) as f32)
+    }
+
+    /// Ends the jump in progress where it's got to, which is recorded as a jump that far.
+    pub fn stop_jump(&mut self) {
+        if let Some(jump) = &mut self.jump {
+            jump.total = jump.done;
+        }
+    }
+
+    pub fn undo_this_frame(&mut self) {
+        self.flags.insert(GameplayFlags::UNDO);
+    }
+
+    pub fn redo_this_frame(&mut self) {
+        self.flags.insert(GameplayFlags::REDO);
+    }
+
+    pub fn copy_this_frame(&mut self) {
+        self.flags.insert(GameplayFlags::COPY);
+    }
+
+    pub fn cut_this_frame(&mut self) {
+        self.flags.insert(GameplayFlags::CUT);
+    }
+
+    pub fn clear_this_frame(&mut self) {
+        self.flags.insert(GameplayFlags::CLEAR);
+    }
+
+    /// Fills the selection, or the whole board if nothing is selected, with the soup described
+    /// by `soup_seed`, `fill_density` and `soup_symmetry`.
+    pub fn randomize_this_frame(&mut self) {
+        self.flags.insert(GameplayFlags::RANDOMIZE);
+    }
+
+    /// Puts a fresh seed in `soup_seed`, for the next randomize.
+    pub fn new_soup_seed(&mut self) {
+        self.soup_seed = Self::make_soup_seed(Soup::random_seed());
+        self.soup_error = None;
+    }
+
+    /// The last soup the board was randomized with, and the size of the area it filled.
+    pub fn soup(&self) -> Option<(Soup, Vec2Int)> {
+        self.soup
+    }
+
+    pub fn take_census_this_frame(&mut self) {
+        self.flags.insert(GameplayFlags::CENSUS);
+    }
+
+    pub fn zoom_to_fit_this_frame(&mut self) {
+        self.flags.insert(GameplayFlags::ZOOM_TO_FIT);
+    }
+
+    pub fn zoom_to_selection_this_frame(&mut self) {
+        self.flags.insert(GameplayFlags::ZOOM_TO_SELECTION);
+    }
+
+    /// Where the camera has been asked to zoom to, as the smallest and largest cells to fit on
+    /// screen: the selection, or everything alive. An empty board fits the whole board, if it
+    /// has edges.
+    pub fn zoom_target(&mut self, world: &World) -> Option<(Vec2Int, Vec2Int)> {
+        let to_selection = self.flags.contains(GameplayFlags::ZOOM_TO_SELECTION);
+        let to_fit = self.flags.contains(GameplayFlags::ZOOM_TO_FIT);
+        self.flags.remove(GameplayFlags::ZOOM_TO_SELECTION | GameplayFlags::ZOOM_TO_FIT);
+
+        match self.selection {
+            Some(selection) if to_selection => Some((selection.min(), selection.max())),
+            _ if to_fit => world.live_bounds().or_else(|| world.bounds()),
+            _ => None,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Caps how much memory the undo history can use. Past that, the oldest edits are forgotten.
+    pub fn set_undo_memory(&mut self, megabytes: i32) {
+        self.undo_memory_megabytes = megabytes.max(1);
+        self.history
+            .set_memory_cap(self.undo_memory_megabytes as usize * BYTES_PER_MEGABYTE);
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn set_rule(&mut self, rule: Rule) {
+        // Anything seen under the old rule says nothing about how the board goes now.
+        if rule != self.rule {
+            self.period_detector.reset();
+        }
+        self.rule = rule;
+        self.rule_input = Self::make_rule_input(&rule);
+        self.rule_error = None;
+    }
+
+    /// Parses whatever is currently typed into `rule_input`, keeping the old rule if it's invalid.
+    pub fn apply_rule_input(&mut self) -> Result<(), RuleError> {
+        match self.rule_input.to_str().parse::<Rule>() {
+            Ok(rule) => {
+                self.set_rule(rule);
+                Ok(())
+            }
+
+            Err(e) => {
+                self.rule_error = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    /// Rebuilds the worker pool used to step the world. With a single thread we skip the
+    /// pool entirely and step on the main thread.
+    pub fn set_simulation_threads(&mut self, threads: i32) {
+        self.simulation_threads = threads.max(1);
+        self.thread_pool = None;
+
+        if self.simulation_threads > 1 {
+            match ThreadPoolBuilder::new().num_threads(self.simulation_threads as usize).build() {
+                Ok(pool) => self.thread_pool = Some(pool),
+                Err(e) => {
+                    error!("Couldn't build a simulation thread pool, stepping on one thread => {}", e);
+                    self.simulation_threads = 1;
+                }
+            }
+        }
+    }
+
+    /// The prefab waiting to be placed, and where its corner would go if the cursor
+    /// were clicked on `cursor`.
+    pub fn placement_preview(&self, cursor: Vec2Int) -> Option<(Vec2Int, &[Vec<State>])> {
+        self.saved_prefab
+            .as_ref()
+            .map(|prefab| (prefab.anchor(cursor), prefab.cells.as_slice()))
+    }
+
+    pub fn is_selecting(&self) -> bool {
+        self.selecting
+    }
+
+    /// Starts a new selection at `pos`, or drags the corner of the one being made out to it.
+    pub fn drag_selection(&mut self, pos: Vec2Int) {
+        match &mut self.selection {
+            Some(selection) if self.selecting => selection.end = pos,
+            _ => {
+                self.selection = Some(Selection::new(pos));
+                self.selecting = true;
+            }
+        }
+    }
+
+    /// The size of whatever was last copied, if anything has been.
+    pub fn clipboard_size(&self) -> Option<Vec2Int> {
+        self.clipboard.as_ref().map(|cells| {
+            let height = cells.first().map_or(0, |column| column.len());
+            Vec2Int::new(cells.len() as i32, height as i32)
+        })
+    }
+
+    /// Picks up the clipboard like a prefab, so it can be previewed, turned and placed.
+    pub fn paste_clipboard(&mut self) {
+        if let Some(cells) = &self.clipboard {
+            self.saved_prefab = Some(Prefab {
+                name: "Clipboard".to_owned(),
+                author: None,
+                description: String::new(),
+                category: String::new(),
+                cells: cells.clone(),
+            });
+        }
+    }
+
+    /// Adds the clipboard to the prefab library under `clipboard_name`, saving it alongside
+    /// the user's own prefabs.
+    pub fn save_clipboard_as_prefab(&mut self) {
+        let cells = match &self.clipboard {
+            Some(cells) => cells.clone(),
+            None => return,
+        };
 
-                    return;
-                } else {
-                    println!("Couldn't find prefab by name of {:?}", prefab);
+        let name = match self.clipboard_name.to_str().trim() {
+            "" => "Untitled".to_owned(),
+            name => name.to_owned(),
+        };
+        let prefab = Prefab {
+            name,
+            author: None,
+            description: String::new(),
+            category: SAVED_PREFAB_CATEGORY.to_owned(),
+            cells,
+        };
+
+        let directory = user_prefab_directory().unwrap_or_else(|| PathBuf::from(PREFAB_DIRECTORY));
+        match self.prefabs.save(prefab, &directory) {
+            Ok(path) => info!("Saved the clipboard to {}", path.display()),
+            Err(e) => error!("Couldn't save the clipboard as a prefab => {}", e),
+        }
+    }
+
+    pub fn ages(&self) -> &CellAges {
+        &self.ages
+    }
+
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// How the world repeats, if it has been stepped far enough to tell.
+    pub fn periodicity(&self) -> Option<Periodicity> {
+        self.period_detector.periodicity()
+    }
+
+    /// The last census taken, and the generation it was taken at.
+    pub fn census(&self) -> Option<(u64, &Census)> {
+        self.census.as_ref().map(|(generation, census)| (*generation, census))
+    }
+
+    /// Whether a census is being taken, which replaces the last one once it's done.
+    pub fn taking_census(&self) -> bool {
+        self.census_job.is_some()
+    }
+
+    /// Saves the last census, as CSV or JSON depending on the extension of `location`.
+    pub fn save_census<P: AsRef<Path>>(&self, location: P) {
+        let location = location.as_ref();
+        if let Some((_, census)) = &self.census {
+            match simple_serialization::save_census(census, location) {
+                Ok(()) => info!("Saved the census to {}", location.display()),
+                Err(e) => error!("Couldn't save the census => {}", e),
+            }
+        }
+    }
+
+    /// Starts searching soups in the background, from the seed in `soup_seed` if there is
+    /// one, with the soup density and symmetry and the current rule.
+    pub fn start_search(&mut self) {
+        self.stop_search();
+
+        let settings = SearchSettings {
+            soup_size: self.search_soup_size.max(1) as usize,
+            density: self.fill_density.max(0.0).min(1.0),
+            symmetry: self.soup_symmetry,
+            rule: self.rule,
+            max_generations: self.search_max_generations.max(1) as u64,
+        };
+        let first_seed = self
+            .soup_seed
+            .to_str()
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| Soup::random_seed());
+        let batch = rayon::current_num_threads() as u64;
+
+        let search = match SoupSearch::new(settings, first_seed) {
+            Ok(search) => search,
+            Err(e) => {
+                self.search_error = Some(e.to_string());
+                return;
+            }
+        };
+
+        info!("Searching soups from seed {}, writing finds to {}", first_seed, SEARCH_RESULTS_FILE);
+        self.search_error = None;
+        self.search_progress = None;
+        self.search_finds.clear();
+        self.search = Some(BackgroundSearch::start(
+            search,
+            batch,
+            PathBuf::from(SEARCH_RESULTS_FILE),
+        ));
+    }
+
+    /// Stops the search once it finishes the soups it's on.
+    pub fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop();
+            self.poll_search(&search);
+            self.stopping_searches.push(search);
+        }
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.search.is_some()
+    }
+
+    pub fn search_progress(&self) -> Option<&SearchProgress> {
+        self.search_progress.as_ref()
+    }
+
+    /// The search's most recent finds, newest first.
+    pub fn search_finds(&self) -> impl Iterator<Item = &SearchFind> {
+        self.search_finds.iter()
+    }
+
+    pub fn search_results_file(&self) -> &'static str {
+        SEARCH_RESULTS_FILE
+    }
+
+    /// Lays a find's soup out again in the top left corner of the board, so it can be
+    /// watched. It has to run under the rule it was found with to turn out the same.
+    pub fn try_search_find(&mut self, find: &SearchFind) {
+        self.soup_seed = Self::make_soup_seed(find.seed);
+        self.soup_error = None;
+        self.fill_density = find.density;
+        self.soup_symmetry = find.symmetry.parse().unwrap_or(Symmetry::None);
+        if let Ok(rule) = find.rule.parse() {
+            self.set_rule(rule);
+        }
+
+        let size = find.soup_size as i32;
+        self.selection = Some(Selection {
+            start: Vec2Int::ZERO,
+            end: Vec2Int::new(size - 1, size - 1),
+        });
+        self.randomize_this_frame();
+    }
+
+    /// Lets go of stopped searches whose threads have ended. The newest one's last batch
+    /// still shows, unless another search has started since.
+    fn reap_searches(&mut self) {
+        let stopping = mem::replace(&mut self.stopping_searches, vec![]);
+        let newest = stopping.len();
+        for (i, search) in stopping.into_iter().enumerate() {
+            let finished = search.is_finished();
+            if i + 1 == newest && self.search.is_none() {
+                self.poll_search(&search);
+            }
+            if finished == false {
+                self.stopping_searches.push(search);
+            }
+        }
+    }
+
+    fn poll_census(&mut self) {
+        let result = match &self.census_job {
+            Some((_, census)) => census.poll(),
+            None => return,
+        };
+        match result {
+            Ok((census, catalogue)) => {
+                let (generation, _) = self.census_job.take().unwrap();
+                self.census = Some((generation, census));
+                self.catalogue = Some(catalogue);
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => {
+                error!("The census thread ended without a census.");
+                self.census_job = None;
+            }
+        }
+    }
+
+    fn poll_search(&mut self, search: &BackgroundSearch) {
+        for progress in search.poll() {
+            for find in &progress.finds {
+                if self.search_finds.len() == RECENT_FINDS {
+                    self.search_finds.pop_back();
                 }
+                self.search_finds.push_front(find.clone());
+            }
+            self.search_progress = Some(progress);
+        }
+    }
+
+    pub fn prefabs(&self) -> &Prefabs {
+        &self.prefabs
+    }
+
+    /// The settings which are kept from one launch to the next, as they are now.
+    pub fn preferences(&self) -> Preferences {
+        let colors = &self.game_colors;
+        Preferences {
+            version: PREFERENCES_VERSION,
+            colors: ColorPreferences {
+                alive: colors.alive.into(),
+                dead: colors.dead.into(),
+                unborn: colors.unborn.into(),
+                bg: colors.bg.into(),
+                grid_lines: colors.grid_lines,
+                grid_line_width: colors.grid_line_width,
+                grid_line_color: colors.grid_line_color.into(),
+                selection: colors.selection.into(),
+                age_coloring: colors.age_coloring,
+                old_alive: colors.old_alive.into(),
+                long_dead: colors.long_dead.into(),
+                age_span: colors.age_span,
+            },
+            sfx_volume: self.game_sounds.sfx_volume,
+            music_volume: self.game_sounds.music_volume(),
+            increment_rate: self.increment_rate,
+            topology: self.topology.to_string(),
+            panels: PanelPreferences {
+                play_control: self.show_play_control,
+                prefab_library: self.show_prefab_library,
+                settings_control: self.show_settings_control,
+                statistics: self.show_statistics,
+                census: self.show_census,
+                soup: self.show_soup,
+                search: self.show_search,
+                minimap: self.show_minimap,
+            },
+            show_instructions: self.show_instructions,
+            show_ui: self.show_ui,
+            show_debug: self.show_debug,
+        }
+    }
+
+    /// Puts back settings saved from an earlier launch. A topology we don't recognise leaves
+    /// the edges as they are.
+    pub fn apply_preferences(&mut self, preferences: &Preferences) {
+        let (colors, saved) = (&mut self.game_colors, &preferences.colors);
+        colors.alive = saved.alive.into();
+        colors.dead = saved.dead.into();
+        colors.unborn = saved.unborn.into();
+        colors.bg = saved.bg.into();
+        colors.grid_lines = saved.grid_lines;
+        colors.grid_line_width = saved.grid_line_width;
+        colors.grid_line_color = saved.grid_line_color.into();
+        colors.selection = saved.selection.into();
+        colors.age_coloring = saved.age_coloring;
+        colors.old_alive = saved.old_alive.into();
+        colors.long_dead = saved.long_dead.into();
+        colors.age_span = saved.age_span;
+
+        self.game_sounds.sfx_volume = preferences.sfx_volume.max(0.0).min(1.0);
+        self.game_sounds
+            .set_music_volume(preferences.music_volume.max(0.0).min(1.0));
+        self.increment_rate = preferences.increment_rate.max(0.0).min(MAX_INCREMENT_RATE);
+        match preferences.topology.parse() {
+            Ok(topology) => self.topology = topology,
+            Err(e) => warn!("Ignoring the saved edges => {}", e),
+        }
+
+        let panels = &preferences.panels;
+        self.show_play_control = panels.play_control;
+        self.show_prefab_library = panels.prefab_library;
+        self.show_settings_control = panels.settings_control;
+        self.show_statistics = panels.statistics;
+        self.show_census = panels.census;
+        self.show_soup = panels.soup;
+        self.show_search = panels.search;
+        self.show_minimap = panels.minimap;
+        self.show_instructions = preferences.show_instructions;
+        self.show_ui = preferences.show_ui;
+        self.show_debug = preferences.show_debug;
+    }
+
+    /// Gathers up the bundled prefabs, along with any the user keeps in their config directory.
+    fn load_prefabs() -> Prefabs {
+        let mut directories = vec![PathBuf::from(PREFAB_DIRECTORY)];
+        directories.extend(user_prefab_directory());
+        Prefabs::load(&directories)
+    }
+
+    fn make_soup_seed(seed: u64) -> ImString {
+        let mut input = ImString::with_capacity(32);
+        input.push_str(&seed.to_string());
+        input
+    }
+
+    fn make_rule_input(rule: &Rule) -> ImString {
+        let mut input = ImString::with_capacity(32);
+        input.push_str(&rule.to_string());
+        input
+    }
+
+    pub fn select(&mut self, click_pos: Vec2Int, world: &mut World) {
+        if self.jump.is_some() {
+            return;
+        }
+
+        if let Some(prefab) = self.saved_prefab.take() {
+            let anchor = prefab.anchor(click_pos);
+            let footprint: Vec<(Vec2Int, State)> = (0..prefab.width())
+                .flat_map(|x| (0..prefab.height()).map(move |y| anchor + Vec2Int::new(x as i32, y as i32)))
+                .map(|pos| (pos, world.get(pos)))
+                .collect();
+
+            world.paste(anchor, &prefab.cells);
+            self.period_detector.reset();
+            for (pos, before) in footprint {
+                self.history.record_cell(pos, before, world.get(pos));
             }
 
-            None => {}
+            self.coords_pressed.push(click_pos);
+            self.sound_player.play_sound(
+                Cursor::new(self.game_sounds.alive_sound),
+                self.game_sounds.sfx_volume,
+            );
+
+            return;
         }
 
         if self.coords_pressed.contains(&click_pos) == false {
-            let entity = &mut entities[click_pos.0][click_pos.1];
-            let new_state = entity.flip_state();
+            let old_state = world.get(click_pos);
+            let new_state = world.flip(click_pos);
+            self.history.record_cell(click_pos, old_state, new_state);
+            self.period_detector.reset();
             match new_state {
                 State::Alive => {
                     self.sound_player.play_sound(
@@ -127,43 +740,130 @@ impl Gameplay {
         }
     }
 
-    pub fn new_size(&mut self, entities: &mut [Vec<Entity>]) -> Option<Vec<Vec<Entity>>> {
-        if self.flags.contains(GameplayFlags::RESIZE) {
-            if let Some(next_size) = self.next_game_size {
-                let mut new_entities = Self::create_game_world(next_size);
+    pub fn new_size(&mut self, world: &World) -> Option<World> {
+        if self.flags.contains(GameplayFlags::RESIZE) == false || self.jump.is_some() {
+            return None;
+        }
+        self.flags.remove(GameplayFlags::RESIZE);
 
-                let old_world = Self::to_pure_states(entities);
-                let center_offset: Vec2 = next_size / 2.0 - self.game_size / 2.0;
-                let center_round_down: Vec2Int = center_offset.into();
+        let new_world = match (self.infinite_world, world) {
+            (true, World::Infinite(_)) => None,
+
+            (true, World::Bounded(_)) => {
+                let mut new_world = World::Infinite(SparseGrid::new());
+                Self::copy_live_cells(world, &mut new_world, Vec2Int::ZERO);
+                Some(new_world)
+            }
+
+            (false, _) => {
+                let next_size = self.next_game_size.unwrap_or(self.game_size);
+                let mut new_world = Self::create_game_world(next_size);
+
+                // Bounded worlds stay centered, and anything that no longer fits is sheared off.
+                // Coming back from an infinite world, cells keep their coordinates.
+                let offset: Vec2Int = match world {
+                    World::Bounded(_) => (next_size / 2.0 - self.game_size / 2.0).into(),
+                    World::Infinite(_) => Vec2Int::ZERO,
+                };
+                Self::copy_live_cells(world, &mut new_world, offset);
 
-                // Throw away if under zero.
-                // in the future, we can sheer
-                if !(center_round_down.x < 0 || center_round_down.y < 0) {
-                    Self::paste_cells(
-                        center_round_down.into_raw_usize().unwrap(),
-                        &old_world,
-                        &mut new_entities,
-                    );
-                }
                 self.game_size = next_size;
-                self.flags.remove(GameplayFlags::RESIZE);
-                Some(new_entities)
-            } else {
-                None
+                Some(new_world)
+            }
+        };
+
+        if let Some(new_world) = &new_world {
+            self.period_detector.reset();
+            self.ages.clear();
+            self.history.record_change(world.clone(), new_world, 0);
+        }
+        new_world
+    }
+
+    /// Copies every live cell of `from` into `to`, moved by `offset`. Only the live cells are
+    /// walked, however far the dead ones have spread.
+    fn copy_live_cells(from: &World, to: &mut World, offset: Vec2Int) {
+        for (pos, state) in from.iter() {
+            if state == State::Alive {
+                to.set(pos + offset, State::Alive);
             }
-        } else {
-            None
         }
     }
 
-    pub fn update(&mut self, user_input: &UserInput, entities: &mut [Vec<Entity>], time: &Time) {
+    pub fn update(&mut self, user_input: &UserInput, world: &mut World, time: &Time) {
+        // Ages aren't kept up while nothing draws them, so whatever's left from before would
+        // be stale by the time age coloring is turned back on. Everyone starts again from zero.
+        if self.game_colors.age_coloring == false {
+            self.ages.clear();
+        }
+
+        // Nothing else touches the board until a jump is over, so it can be undone in one go.
+        if self.jump.is_some() {
+            self.continue_jump(world);
+            return;
+        }
+
         if user_input.mouse_input.is_released(MouseButton::Left) {
             self.coords_pressed.clear();
+            self.history.finish_edit();
+            self.selecting = false;
+        }
+
+        let control = user_input.kb_input.is_held(Key::LControl) || user_input.kb_input.is_held(Key::RControl);
+        let shift = user_input.kb_input.is_held(Key::LShift) || user_input.kb_input.is_held(Key::RShift);
+        if control && user_input.kb_input.is_pressed(Key::Z) {
+            if shift {
+                self.redo_this_frame();
+            } else {
+                self.undo_this_frame();
+            }
+        }
+        if control && user_input.kb_input.is_pressed(Key::Y) {
+            self.redo_this_frame();
+        }
+        if control && user_input.kb_input.is_pressed(Key::C) {
+            self.copy_this_frame();
+        }
+        if control && user_input.kb_input.is_pressed(Key::X) {
+            self.cut_this_frame();
+        }
+        if control && user_input.kb_input.is_pressed(Key::V) {
+            self.paste_clipboard();
+        }
+        if user_input.kb_input.is_pressed(Key::Delete) {
+            self.clear_this_frame();
+        }
+
+        self.update_selection(world);
+
+        if self.flags.contains(GameplayFlags::UNDO) {
+            self.flags.remove(GameplayFlags::UNDO);
+            // Stepping backwards while playing would just be stepped forwards again.
+            self.playing = false;
+            if let Some(generations) = self.history.undo(world) {
+                self.statistics.rewind(generations);
+                self.period_detector.reset();
+                self.ages.clear();
+                self.match_world_shape(world);
+            }
+        }
+
+        if self.flags.contains(GameplayFlags::REDO) {
+            self.flags.remove(GameplayFlags::REDO);
+            if let Some(generations) = self.history.redo(world) {
+                self.period_detector.reset();
+                self.ages.clear();
+                if generations > 0 {
+                    let changes = self.history.latest_changes();
+                    self.statistics.record_changes(&changes, world.population(), generations);
+                }
+                self.match_world_shape(world);
+            }
         }
 
         let mut do_not_update_again = false;
         if user_input.kb_input.is_pressed(Key::Return) {
-            self.set_rules(entities);
+            self.set_rules(world);
             do_not_update_again = true;
             self.sound_player.play_sound(
                 Cursor::new(self.game_sounds.tick_sound),
@@ -171,6 +871,31 @@ impl Gameplay {
             );
         }
 
+        if self.flags.contains(GameplayFlags::JUMP) {
+            self.flags.remove(GameplayFlags::JUMP);
+            self.jump = Some(Jump {
+                before: world.clone(),
+                done: 0,
+                total: self.jump_generations.max(0) as u64,
+            });
+            self.continue_jump(world);
+            do_not_update_again = true;
+        }
+
+        if let Some(prefab) = &mut self.saved_prefab {
+            if user_input.kb_input.is_pressed(Key::R) {
+                prefab.rotate_clockwise();
+            }
+            if user_input.kb_input.is_pressed(Key::F) {
+                prefab.mirror();
+            }
+            if user_input.kb_input.is_pressed(Key::Escape) {
+                self.saved_prefab = None;
+            }
+        } else if user_input.kb_input.is_pressed(Key::Escape) {
+            self.selection = None;
+        }
+
         if user_input.kb_input.is_pressed(Key::Space) {
             self.playing = !self.playing;
         }
@@ -183,169 +908,262 @@ impl Gameplay {
             self.show_instructions = !self.show_instructions;
         }
 
+        if user_input.kb_input.is_pressed(Key::F5) {
+            self.show_statistics = !self.show_statistics;
+        }
+
+        if user_input.kb_input.is_pressed(Key::F6) {
+            self.show_census = !self.show_census;
+            if self.show_census && self.census.is_none() {
+                self.take_census_this_frame();
+            }
+        }
+
+        if self.flags.contains(GameplayFlags::CENSUS) {
+            self.flags.remove(GameplayFlags::CENSUS);
+            let census = BackgroundCensus::start(world.clone(), self.rule, self.catalogue.clone());
+            self.census_job = Some((self.statistics.generation(), census));
+        }
+        self.poll_census();
+
+        if user_input.kb_input.is_pressed(Key::F7) {
+            self.show_soup = !self.show_soup;
+        }
+
+        if user_input.kb_input.is_pressed(Key::F9) {
+            self.show_minimap = !self.show_minimap;
+        }
+
+        if user_input.kb_input.is_pressed(Key::Home) {
+            if shift {
+                self.zoom_to_selection_this_frame();
+            } else {
+                self.zoom_to_fit_this_frame();
+            }
+        }
+
+        if user_input.kb_input.is_pressed(Key::F8) {
+            self.show_search = !self.show_search;
+        }
+
+        if let Some(search) = self.search.take() {
+            self.poll_search(&search);
+            self.search = Some(search);
+        }
+        self.reap_searches();
+
+        if self.flags.contains(GameplayFlags::RANDOMIZE) {
+            self.flags.remove(GameplayFlags::RANDOMIZE);
+            self.randomize(world);
+        }
+
         if user_input.kb_input.is_pressed(Key::F10) {
             self.show_debug = !self.show_debug;
         }
 
         if user_input.kb_input.is_pressed(Key::F3) {
-            let states = Self::to_pure_states(entities);
+            let (_, states) = world.live_states();
             simple_serialization::save(&states, "okay.json").unwrap();
         }
 
+        if user_input.kb_input.is_pressed(Key::F4) {
+            let mut pattern = simple_serialization::Pattern::new(world.live_states().1);
+            pattern.rule = Some(self.rule);
+            if let Some((soup, size)) = self.soup {
+                pattern
+                    .comments
+                    .push(format!("Started from a {} x {} soup with {}.", size.x, size.y, soup));
+            }
+            if let Err(e) = simple_serialization::save_pattern(&pattern, "board.rle") {
+                error!("Couldn't save the board => {}", e);
+            }
+        }
+
         if self.auto_increment && self.playing {
             self.current_time += time.delta_time;
             if self.increment_rate != 0.0 && self.current_time > (1.0 / self.increment_rate) {
                 if do_not_update_again == false {
-                    self.set_rules(entities);
+                    self.set_rules(world);
                 }
                 self.current_time = 0.0;
             }
         }
     }
 
-    pub fn set_rules(&self, current_entities: &mut [Vec<Entity>]) {
-        let mut ret: Vec<Vec<State>> = vec![];
-        for (x, this_row) in current_entities.iter().enumerate() {
-            let mut ret_row = vec![];
-            for (y, entity) in this_row.iter().enumerate() {
-                let current_pos = Vec2Int::new(x as i32, y as i32);
-                let mut count = 0;
+    fn update_selection(&mut self, world: &mut World) {
+        let edits = self.flags & (GameplayFlags::COPY | GameplayFlags::CUT | GameplayFlags::CLEAR);
+        self.flags.remove(edits);
 
-                // Check Up-Left
-                if self.entity_is_alive(current_entities, current_pos - Vec2Int::RIGHT + Vec2Int::UP) {
-                    count += 1;
-                }
+        let selection = match self.selection {
+            Some(selection) => selection,
+            None => return,
+        };
 
-                // Check Up
-                if self.entity_is_alive(current_entities, current_pos + Vec2Int::UP) {
-                    count += 1;
-                }
+        if edits.intersects(GameplayFlags::COPY | GameplayFlags::CUT) {
+            self.clipboard = Some(world.region(selection.min(), selection.max()));
+        }
 
-                // Check Up-Right
-                if self.entity_is_alive(current_entities, current_pos + Vec2Int::RIGHT + Vec2Int::UP) {
-                    count += 1;
-                }
+        if edits.intersects(GameplayFlags::CUT | GameplayFlags::CLEAR) {
+            self.edit_selection(world, |state| match state {
+                State::Alive => State::Dead,
+                state => state,
+            });
+        }
+    }
 
-                // Check Right
-                if self.entity_is_alive(current_entities, current_pos + Vec2Int::RIGHT) {
-                    count += 1;
-                }
+    fn randomize(&mut self, world: &mut World) {
+        let seed = match self.soup_seed.to_str().trim().parse::<u64>() {
+            Ok(seed) => seed,
+            Err(_) => {
+                self.soup_error = Some("The seed has to be a whole number.".to_owned());
+                return;
+            }
+        };
+        self.soup_error = None;
 
-                // Check Down-Right
-                if self.entity_is_alive(current_entities, current_pos + Vec2Int::RIGHT - Vec2Int::UP) {
-                    count += 1;
-                }
+        let (min, max) = match (self.selection, world.size()) {
+            (Some(selection), _) => (selection.min(), selection.max()),
+            (None, Some(size)) => (Vec2Int::ZERO, size - Vec2Int::new(1, 1)),
+            // Infinite worlds get a soup the size they'd have if they were bounded.
+            (None, None) => (Vec2Int::ZERO, Vec2Int::from(self.game_size) - Vec2Int::new(1, 1)),
+        };
 
-                // Check Down
-                if self.entity_is_alive(current_entities, current_pos - Vec2Int::UP) {
-                    count += 1;
-                }
+        let soup = Soup::new(seed, self.fill_density, self.soup_symmetry);
+        let before = world.clone();
+        soup.fill(world, min, max);
+        self.soup = Some((soup, max - min + Vec2Int::new(1, 1)));
+        self.period_detector.reset();
+        self.history.record_change(before, world, 0);
+    }
 
-                // Check Down-Left
-                if self.entity_is_alive(current_entities, current_pos - Vec2Int::RIGHT - Vec2Int::UP) {
-                    count += 1;
+    /// Runs every cell in the selection through `edit`, as one undoable edit.
+    fn edit_selection<F: FnMut(State) -> State>(&mut self, world: &mut World, mut edit: F) {
+        if let Some(selection) = self.selection {
+            self.period_detector.reset();
+            for pos in selection.positions() {
+                let before = world.get(pos);
+                let after = edit(before);
+                if after != before && world.contains(pos) {
+                    world.set(pos, after);
+                    self.history.record_cell(pos, before, after);
                 }
+            }
+            self.history.finish_edit();
+        }
+    }
 
-                // Check Left
-                if self.entity_is_alive(current_entities, current_pos - Vec2Int::RIGHT) {
-                    count += 1;
-                }
+    pub fn set_rules(&mut self, world: &mut World) {
+        let mut changes = Vec::new();
+        let pool = self.thread_pool.as_ref();
+        if let Err(e) = world.step_with_changes(&self.rule, self.topology, pool, &mut changes) {
+            self.refuse_rule(e);
+            return;
+        }
+        self.statistics.record_changes(&changes, world.population(), 1);
+        self.update_ages(world, 1);
+        self.period_detector.observe(self.statistics.generation(), world);
+        self.history.record_step(changes, 1);
+    }
+
+    /// Carries on with the jump in progress. An infinite world is handed to HashLife in one
+    /// go, but a bounded one is stepped for `JUMP_FRAME_BUDGET` a frame until it gets there.
+    fn continue_jump(&mut self, world: &mut World) {
+        let jump = match &mut self.jump {
+            Some(jump) => jump,
+            None => return,
+        };
 
-                if entity.state == State::Alive {
-                    ret_row.push(match count {
-                        2..=3 => State::Alive,
-                        _ => State::Dead,
-                    });
-                } else {
-                    ret_row.push(if count == 3 {
-                        State::Alive
-                    } else {
-                        if entity.state == State::Dead {
-                            State::Dead
-                        } else {
-                            State::Unborn
-                        }
-                    });
+        match world {
+            World::Bounded(_) => {
+                let started = Instant::now();
+                while jump.done < jump.total && started.elapsed() < JUMP_FRAME_BUDGET {
+                    // Bounded worlds can run any rule.
+                    let _ = world.step(&self.rule, self.topology, self.thread_pool.as_ref());
+                    jump.done += 1;
                 }
             }
-            ret.push(ret_row);
-        }
 
-        for (x, this_row) in current_entities.iter_mut().enumerate() {
-            for (y, entity) in this_row.iter_mut().enumerate() {
-                entity.state = ret[x][y];
+            World::Infinite(_) => {
+                let generations = jump.total - jump.done;
+                match world.jump(&self.rule, generations, self.topology, self.thread_pool.as_ref()) {
+                    Ok(()) => jump.done = jump.total,
+                    Err(e) => {
+                        jump.total = jump.done;
+                        self.refuse_rule(e);
+                    }
+                }
             }
         }
-    }
 
-    fn entity_is_alive(&self, entities: &[Vec<Entity>], pos: Vec2Int) -> bool {
-        if self.wrap_grid {
-            let x = Self::wrap(pos.x, entities.len());
-            let y = Self::wrap(pos.y, entities[0].len());
-            entities[x][y].state == State::Alive
-        } else {
-            if pos.x > 0 && pos.x < entities.len() as i32 && pos.y > 0 && pos.y < entities[0].len() as i32 {
-                entities[pos.x as usize][pos.y as usize].state == State::Alive
-            } else {
-                false
-            }
+        let jump = match &self.jump {
+            Some(jump) => jump,
+            None => return,
+        };
+
+        if jump.done < jump.total {
+            return;
         }
-    }
 
-    fn wrap(current: i32, wrap_size: usize) -> usize {
-        if current < 0 {
-            wrap_size - 1
-        } else if current as usize == wrap_size {
-            0
-        } else {
-            current as usize
+        let Jump { before, done, .. } = match self.jump.take() {
+            Some(jump) => jump,
+            None => return,
+        };
+        if done == 0 {
+            return;
         }
-    }
 
-    fn paste_cells(click_pos: UsizeTuple, prefab: &[Vec<State>], entities: &mut [Vec<Entity>]) {
-        for this_x in click_pos.0..click_pos.0 + prefab.len() {
-            let command_x = this_x - click_pos.0;
+        let changes = world.changes_since(&before);
+        let changed = changes.as_ref().map_or(&[][..], |changes| &changes[..]);
+        self.statistics.record_changes(changed, world.population(), done);
+        self.update_ages(world, done);
+        self.history.record_diff(before, world, changes, done);
+        self.sound_player.play_sound(
+            Cursor::new(self.game_sounds.tick_sound),
+            self.game_sounds.sfx_volume,
+        );
+    }
 
-            for this_y in click_pos.1..click_pos.1 + prefab[command_x].len() {
-                if this_x >= entities.len() || this_y >= entities[this_x].len() {
-                    continue;
-                }
-                let command_y = this_y - click_pos.1;
+    /// The world can't run the current rule, so it's said so next to the rule, and play stops
+    /// until the rule or the world changes.
+    fn refuse_rule(&mut self, error: WorldError) {
+        self.rule_error = Some(error.to_string());
+        self.playing = false;
+    }
 
-                let entity = &mut entities[this_x][this_y];
-                let new_state = prefab[command_x][command_y];
-                if !(entity.state == State::Unborn && new_state == State::Dead) {
-                    entity.state = new_state;
-                }
-            }
+    /// Ages are only kept up while they're drawn, since on a big board they cost about as
+    /// much as the step itself.
+    fn update_ages(&mut self, world: &World, generations: u64) {
+        if self.game_colors.age_coloring {
+            self.ages.update(world, generations);
         }
     }
 
-    pub fn create_game_world(size: Vec2) -> Vec<Vec<Entity>> {
-        let mut entities = vec![];
-        for x in 0..size.x as i32 {
-            let mut this_vec = vec![];
-            for y in 0..size.y as i32 {
-                this_vec.push(Entity::new(Vec2::new(x as f32, y as f32)));
+    /// Undoing or redoing a resize swaps the whole world out, so the board settings
+    /// have to follow whatever shape it is now.
+    fn match_world_shape(&mut self, world: &World) {
+        match world.size() {
+            Some(size) => {
+                self.infinite_world = false;
+                self.game_size = size.into();
+                self.next_game_size = None;
             }
-            entities.push(this_vec);
+            None => self.infinite_world = true,
         }
-        entities
     }
 
-    fn to_pure_states(entities: &mut [Vec<Entity>]) -> Vec<Vec<State>> {
-        let mut ret = vec![];
-        for this_row in entities.iter() {
-            let mut ret_row = vec![];
-            for entity in this_row {
-                ret_row.push(entity.state)
-            }
-            ret.push(ret_row);
-        }
-        ret
+    pub fn create_game_world(size: Vec2) -> World {
+        World::Bounded(Grid::new(size.x as usize, size.y as usize))
     }
 }
 
+/// A jump which is being worked through a frame at a time.
+struct Jump {
+    /// The board as it was before the jump, for the history.
+    before: World,
+    done: u64,
+    total: u64,
+}
+
 #[derive(Debug)]
 pub struct GameColors {
     pub alive: Color,
@@ -355,6 +1173,15 @@ pub struct GameColors {
     pub grid_lines: bool,
     pub grid_line_width: f32,
     pub grid_line_color: Color,
+    pub selection: Color,
+    /// Shades cells by how long they've been alive or dead.
+    pub age_coloring: bool,
+    /// What live cells fade towards as they get older.
+    pub old_alive: Color,
+    /// What dead cells fade towards the longer they stay dead.
+    pub long_dead: Color,
+    /// How many generations it takes to fade all the way.
+    pub age_span: f32,
 }
 
 impl GameColors {
@@ -365,6 +1192,20 @@ impl GameColors {
             State::Unborn => &self.unborn,
         }
     }
+
+    /// The color a cell fades towards with age, and how far along it is, from 0 to 1.
+    /// Dead cells old enough to have been forgotten are all the way there.
+    pub fn age_shade(&self, state: State, age: Option<Age>) -> (&Color, f32) {
+        let span = self.age_span.max(1.0);
+        match (state, age) {
+            (_, _) if self.age_coloring == false => (self.get_color(state), 0.0),
+            (State::Alive, Some(Age::Alive(age))) => (&self.old_alive, (age as f32 / span).min(1.0)),
+            (State::Alive, _) => (&self.old_alive, 0.0),
+            (State::Dead, Some(Age::Dead(age))) => (&self.long_dead, (age as f32 / span).min(1.0)),
+            (State::Dead, _) => (&self.long_dead, 1.0),
+            (State::Unborn, _) => (&self.unborn, 0.0),
+        }
+    }
 }
 
 impl Default for GameColors {
@@ -377,6 +1218,11 @@ impl Default for GameColors {
             grid_lines: true,
             grid_line_width: 0.025,
             grid_line_color: Color::new(0.2, 0.5, 0.1),
+            selection: Color::new(0.3, 0.6, 1.0),
+            age_coloring: false,
+            old_alive: Color::with_u8(120, 190, 60),
+            long_dead: Color::with_u8(100, 78, 70),
+            age_span: 100.0,
         }
     }
 }
@@ -430,48 +1276,19 @@ impl<'a> Debug for GameSounds {
 }
 
 use failure::Error;
-use std::collections::HashMap;
-
-#[derive(Debug)]
-pub struct Prefabs {
-    prefabs: HashMap<Prefab, Vec<Vec<State>>>,
-}
-
-impl Prefabs {
-    pub fn new() -> Result<Prefabs, Error> {
-        let mut prefabs: HashMap<Prefab, Vec<Vec<State>>> = HashMap::new();
-
-        prefabs.insert(
-            Prefab::Glider,
-            simple_serialization::load("resources/prefabs/glider.json")?,
-        );
-        prefabs.insert(
-            Prefab::SmallExploder,
-            simple_serialization::load("resources/prefabs/small_exploder.json")?,
-        );
-        prefabs.insert(
-            Prefab::Exploder,
-            simple_serialization::load("resources/prefabs/exploder.json")?,
-        );
-        prefabs.insert(
-            Prefab::Spaceship,
-            simple_serialization::load("resources/prefabs/lw_spaceship.json")?,
-        );
-        prefabs.insert(
-            Prefab::Tumbler,
-            simple_serialization::load("resources/prefabs/tumbler.json")?,
-        );
-        prefabs.insert(
-            Prefab::GliderGun,
-            simple_serialization::load("resources/prefabs/glider_gun.json")?,
-        );
-
-        Ok(Prefabs { prefabs })
-    }
-}
 
 bitflags! {
     struct GameplayFlags: u32 {
         const RESIZE = 0b00000001;
+        const JUMP = 0b00000010;
+        const UNDO = 0b00000100;
+        const REDO = 0b00001000;
+        const COPY = 0b00010000;
+        const CUT = 0b00100000;
+        const CLEAR = 0b01000000;
+        const RANDOMIZE = 0b10000000;
+        const CENSUS = 0b100000000;
+        const ZOOM_TO_FIT = 0b1000000000;
+        const ZOOM_TO_SELECTION = 0b10000000000;
     }
 }
@@ -0,0 +1,143 @@
+use super::{Color, DensityPyramid, GameColors, State, Time, Vec2, Vec2Int, World};
+use imgui::TextureId;
+
+/// The minimap's texture is this many pixels along each side.
+pub const MINIMAP_SIZE: usize = 128;
+/// How long to wait between redraws, in seconds.
+const REFRESH_INTERVAL: f32 = 0.25;
+/// How bright the emptiest pixel with anything alive in it is drawn, so lone objects show up.
+const MIN_SHADE: f32 = 0.35;
+
+/// A small picture of the whole board, drawn on the CPU a pixel per tile of cells.
+pub struct Minimap {
+    /// Where the pixels have been uploaded for imgui to draw, once they have been.
+    pub texture: Option<TextureId>,
+    pixels: Vec<u8>,
+    /// The cell in the corner of the first pixel.
+    origin: Vec2Int,
+    /// Each pixel covers a tile this many levels up a `DensityPyramid`.
+    level: u32,
+    /// How many pixels across and down the board takes up, from the corner.
+    used: Vec2Int,
+    since_refresh: f32,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Minimap {
+            texture: None,
+            pixels: vec![0; MINIMAP_SIZE * MINIMAP_SIZE * 4],
+            origin: Vec2Int::ZERO,
+            level: 0,
+            used: Vec2Int::new(1, 1),
+            since_refresh: REFRESH_INTERVAL,
+        }
+    }
+
+    /// The texture's pixels, as RGBA rows from the top.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// How many pixels across and down the board takes up.
+    pub fn used_size(&self) -> Vec2Int {
+        self.used
+    }
+
+    /// The corner of the texture the board takes up, in texture coordinates.
+    pub fn uv_max(&self) -> [f32; 2] {
+        [
+            self.used.x as f32 / MINIMAP_SIZE as f32,
+            self.used.y as f32 / MINIMAP_SIZE as f32,
+        ]
+    }
+
+    /// Redraws the map if it's been long enough since the last time, returning whether it did.
+    pub fn update(&mut self, world: &World, colors: &GameColors, view: (Vec2, Vec2), time: &Time) -> bool {
+        self.since_refresh += time.delta_time;
+        if self.since_refresh < REFRESH_INTERVAL {
+            return false;
+        }
+
+        self.since_refresh = 0.0;
+        self.redraw(world, colors, view);
+        true
+    }
+
+    /// Draws the whole board, or for an infinite world everything alive along with the
+    /// camera's `view`, shading each pixel by how much of it is alive.
+    pub fn redraw(&mut self, world: &World, colors: &GameColors, view: (Vec2, Vec2)) {
+        let (view_min, view_max) = (view.0.floor_to_int(), view.1.floor_to_int());
+        let (min, max) = match (world.size(), world.live_bounds()) {
+            (Some(_), _) => world.bounds().unwrap_or((Vec2Int::ZERO, Vec2Int::ZERO)),
+            (None, Some((min, max))) => (
+                Vec2Int::new(min.x.min(view_min.x), min.y.min(view_min.y)),
+                Vec2Int::new(max.x.max(view_max.x), max.y.max(view_max.y)),
+            ),
+            (None, None) => (view_min, view_max),
+        };
+
+        let size = MINIMAP_SIZE as i32;
+        let mut level = 0;
+        let mut used = DensityPyramid::tile_of(max, level) - DensityPyramid::tile_of(min, level);
+        while (used.x >= size || used.y >= size) && level < 30 {
+            level += 1;
+            used = DensityPyramid::tile_of(max, level) - DensityPyramid::tile_of(min, level);
+        }
+
+        let first_tile = DensityPyramid::tile_of(min, level);
+        self.level = level;
+        self.origin = first_tile * DensityPyramid::tile_size(level);
+        self.used = used + Vec2Int::new(1, 1);
+
+        let bg = to_srgb(colors.bg);
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&bg);
+        }
+
+        let tiles: Vec<(Vec2Int, u32)> = if level == 0 {
+            world
+                .iter()
+                .filter(|(_, state)| *state == State::Alive)
+                .map(|(pos, _)| (pos, 1))
+                .collect()
+        } else {
+            DensityPyramid::new(world, level).tiles(level).collect()
+        };
+
+        let area = DensityPyramid::tile_size(level) as f32 * DensityPyramid::tile_size(level) as f32;
+        let (bg, alive): ([f32; 3], [f32; 3]) = (colors.bg.into(), colors.alive.into());
+        for (tile, count) in tiles {
+            let pixel = tile - first_tile;
+            if pixel.x < 0 || pixel.y < 0 || pixel.x >= size || pixel.y >= size {
+                continue;
+            }
+
+            let shade = MIN_SHADE + (1.0 - MIN_SHADE) * (count as f32 / area);
+            let color = [
+                bg[0] + (alive[0] - bg[0]) * shade,
+                bg[1] + (alive[1] - bg[1]) * shade,
+                bg[2] + (alive[2] - bg[2]) * shade,
+            ];
+            let index = (pixel.y as usize * MINIMAP_SIZE + pixel.x as usize) * 4;
+            self.pixels[index..index + 4].copy_from_slice(&to_srgb(color.into()));
+        }
+    }
+
+    /// The world position under a point on the map, measured in the map's pixels.
+    pub fn pixel_to_world(&self, pixel: Vec2) -> Vec2 {
+        Vec2::from(self.origin) + pixel * DensityPyramid::tile_size(self.level) as f32
+    }
+
+    /// Where a world position lands on the map, measured in the map's pixels.
+    pub fn world_to_pixel(&self, pos: Vec2) -> Vec2 {
+        (pos - Vec2::from(self.origin)) / DensityPyramid::tile_size(self.level) as f32
+    }
+}
+
+/// Our colors are kept linear, but the texture is sRGB.
+fn to_srgb(color: Color) -> [u8; 4] {
+    let color: [f32; 3] = color.into();
+    let channel = |c: f32| (c.max(0.0).min(1.0).powf(1.0 / 2.2) * 255.0).round() as u8;
+    [channel(color[0]), channel(color[1]), channel(color[2]), 255]
+}
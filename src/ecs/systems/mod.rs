@@ -3,8 +3,7 @@ pub use super::*;
 mod camera;
 mod dear_imgui;
 mod gameplay;
-mod prefabs;
-pub mod simple_serialization;
+mod minimap;
 mod sound_player;
 mod user_input;
 mod window;
@@ -12,7 +11,7 @@ mod window;
 pub use camera::*;
 pub use dear_imgui::*;
 pub use gameplay::*;
-pub use prefabs::Prefab;
+pub use minimap::*;
 pub use sound_player::*;
 pub use user_input::*;
 pub use window::*;
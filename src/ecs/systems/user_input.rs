@@ -259,6 +259,7 @@ pub struct KeyboardInput {
     pub held_keys: ArrayVec<[VirtualKeyCode; 10]>,
     pub released_keys: ArrayVec<[VirtualKeyCode; 10]>,
     pub received_char: ArrayVec<[char; 10]>,
+    pub keyboard_input_taken: bool,
 }
 
 macro_rules! quick_find {
@@ -272,16 +273,17 @@ impl KeyboardInput {
         self.pressed_keys.clear();
         self.released_keys.clear();
         self.received_char.clear();
+        self.keyboard_input_taken = false;
     }
 
     #[allow(dead_code)]
     pub fn is_pressed(&self, target_keycode: VirtualKeyCode) -> bool {
-        quick_find!(self.pressed_keys, target_keycode).is_some()
+        quick_find!(self.pressed_keys, target_keycode).is_some() && self.keyboard_input_taken == false
     }
 
     #[allow(dead_code)]
     pub fn is_held(&self, target_keycode: VirtualKeyCode) -> bool {
-        quick_find!(self.held_keys, target_keycode).is_some()
+        quick_find!(self.held_keys, target_keycode).is_some() && self.keyboard_input_taken == false
     }
 
     #[allow(dead_code)]
@@ -1,11 +1,14 @@
-use super::ecs::{Camera, Entity, Gameplay, Imgui, MouseButton, UiHandler, UserInput, Window};
+use super::ecs::{
+    Camera, Gameplay, Imgui, Minimap, MouseButton, UiHandler, UserInput, Window, World, MINIMAP_SIZE,
+};
 use super::rendering::{
     DrawingError, GameWorldDrawCommands, ImGuiDrawCommands, RendererCommands, TypedRenderer,
 };
-use super::resources::SoundsVFX;
-use super::utilities::{Time, Vec2};
+use super::resources::{Preferences, PreferencesWriter, SoundsVFX};
+use super::utilities::{Time, Vec2, Vec2Int};
 use anymap::AnyMap;
 use failure::Error;
+use winit::VirtualKeyCode;
 
 const DEFAULT_SIZE: Vec2 = Vec2 { x: 1280.0, y: 720.0 };
 const DEFAULT_GAME_SIZE: Vec2 = Vec2 { x: 6.0, y: 6.0 };
@@ -17,7 +20,9 @@ pub struct Game {
     renderer: Option<TypedRenderer>,
     camera: Camera,
     gameplay: Gameplay,
-    entities: Vec<Vec<Entity>>,
+    world: World,
+    minimap: Minimap,
+    preferences: PreferencesWriter,
     time: Time,
 }
 
@@ -33,17 +38,27 @@ impl Game {
         let renderer = TypedRenderer::typed_new(&window)?;
         let camera = Camera::new_at_position(Vec2::new(0.0, 0.0), 1.0);
 
-        // Initialize Entities...
-        let entities = Gameplay::create_game_world(DEFAULT_GAME_SIZE);
-        let gameplay = Gameplay::new(&resources, DEFAULT_GAME_SIZE)?;
+        // Initialize the World...
+        let world = Gameplay::create_game_world(DEFAULT_GAME_SIZE);
+        let mut gameplay = Gameplay::new(&resources, DEFAULT_GAME_SIZE)?;
+
+        // Whatever was set last time, falling back on the defaults if we can't read it.
+        let defaults = gameplay.preferences();
+        let preferences = Preferences::load(&defaults).unwrap_or_else(|e| {
+            error!("Couldn't load preferences => {}", e);
+            defaults
+        });
+        gameplay.apply_preferences(&preferences);
 
         Ok(Game {
             window,
             user_input,
             renderer: Some(renderer),
-            entities,
+            world,
             camera,
             gameplay,
+            minimap: Minimap::new(),
+            preferences: PreferencesWriter::new(preferences),
             time: Time::new(),
             resources,
         })
@@ -53,6 +68,8 @@ impl Game {
         let mut dear_imgui = Imgui::new(&self.window);
         if let Some(renderer) = &mut self.renderer {
             renderer.initialize_imgui(&mut dear_imgui.imgui)?;
+            let texture = renderer.create_texture(self.minimap.pixels(), MINIMAP_SIZE, MINIMAP_SIZE)?;
+            self.minimap.texture = Some(texture);
         };
         self.time.game_start();
 
@@ -66,30 +83,56 @@ impl Game {
             let mut ui_frame = dear_imgui.begin_frame(&self.window);
 
             Imgui::make_ui(&mut ui_frame, &mut self.gameplay);
+            Imgui::make_statistics_ui(&ui_frame, &mut self.gameplay, &self.world);
+            Imgui::make_census_ui(&ui_frame, &mut self.gameplay);
             Imgui::make_debug_ui(&ui_frame, &self.gameplay, &mut self.camera, &self.time);
+            Imgui::make_minimap_ui(&ui_frame, &mut self.gameplay, &mut self.camera, &self.minimap);
+
+            if let Some(new_world) = self.gameplay.new_size(&self.world) {
+                self.world = new_world;
+            }
 
-            if let Some(new_entities) = self.gameplay.new_size(&mut self.entities) {
-                self.entities = new_entities;
+            if let Some((min, max)) = self.gameplay.zoom_target(&self.world) {
+                self.camera.zoom_to(min, max);
             }
 
             self.camera
-                .update(&self.user_input, &self.window, &self.gameplay.game_size());
+                .update(&self.user_input, &self.window, self.world.size(), &self.time);
+
+            if self.gameplay.show_ui && self.gameplay.show_minimap {
+                let view = self.camera.view_bounds();
+                let colors = &self.gameplay.game_colors;
+                if self.minimap.update(&self.world, colors, view, &self.time) {
+                    if let (Some(renderer), Some(texture)) = (&mut self.renderer, self.minimap.texture) {
+                        if let Err(e) = renderer.update_texture(texture, self.minimap.pixels()) {
+                            error!("Couldn't update the minimap => {}", e);
+                        }
+                    }
+                }
+            }
 
-            // Single selection
+            // Single selection, or dragging out a rectangle with shift held
             if self.user_input.mouse_input.is_held(MouseButton::Left) {
                 let world_pos = self.camera.display_to_world_position(
                     self.user_input.mouse_input.mouse_position,
                     self.window.get_window_size(),
                 );
 
-                if let Ok(coord_pos) = world_pos.into_raw_usize() {
-                    if coord_pos.0 < self.entities.len() && coord_pos.1 < self.entities[0].len() {
-                        self.gameplay.select(coord_pos, &mut self.entities);
-                    }
+                let coord_pos = world_pos.floor_to_int();
+                let shift = self.user_input.kb_input.is_held(VirtualKeyCode::LShift)
+                    || self.user_input.kb_input.is_held(VirtualKeyCode::RShift);
+                let start_selecting = shift && self.user_input.mouse_input.is_pressed(MouseButton::Left);
+
+                if self.gameplay.is_selecting() || start_selecting {
+                    self.gameplay.drag_selection(coord_pos);
+                } else if self.world.contains(coord_pos) {
+                    self.gameplay.select(coord_pos, &mut self.world);
                 }
             }
             self.gameplay
-                .update(&self.user_input, &mut self.entities, &self.time);
+                .update(&self.user_input, &mut self.world, &self.time);
+            self.preferences
+                .update(self.gameplay.preferences(), self.time.delta_time);
 
             // render
             if let Err(e) = self.render(ui_frame) {
@@ -110,14 +153,31 @@ impl Game {
             let result = {
                 ui_frame.prepare_draw(&self.window);
                 let position = self.camera.position_scaled();
+                let cursor = if self.gameplay.saved_prefab.is_some() {
+                    self.camera
+                        .display_to_world_position(
+                            self.user_input.mouse_input.mouse_position,
+                            self.window.get_window_size(),
+                        )
+                        .floor_to_int()
+                } else {
+                    Vec2Int::ZERO
+                };
 
                 let instructions = RendererCommands {
                     game_world_draw_commands: Some(GameWorldDrawCommands {
                         aspect_ratio: self.camera.aspect_ratio,
+                        viewport_size: self.window.get_window_size(),
                         camera_position: &position,
                         camera_scale: self.camera.scale,
-                        entities: &mut self.entities,
+                        world: &self.world,
                         game_colors: &self.gameplay.game_colors,
+                        ages: self.gameplay.ages(),
+                        placement_preview: self.gameplay.placement_preview(cursor),
+                        selection: self
+                            .gameplay
+                            .selection
+                            .map(|selection| (selection.min(), selection.max())),
                     }),
                     imgui_draw_commands: Some(ImGuiDrawCommands {
                         draw_data: ui_frame.ui.render(),
@@ -143,7 +203,8 @@ impl Game {
 
                     DrawingError::ResetFence
                     | DrawingError::WaitOnFence
-                    | DrawingError::BufferCreationError => {
+                    | DrawingError::BufferCreationError
+                    | DrawingError::MissingTexture => {
                         error!("Rendering Error: {:?}", e);
                         error!("Auo-restarting Renderer...");
 
@@ -17,7 +17,8 @@ mod ecs;
 mod game;
 mod rendering;
 mod resources;
-mod utilities;
+
+use life::utilities;
 
 fn main() {
     env_logger::init();
@@ -144,3 +144,69 @@ impl<B: Backend> VertexIndexPairBufferBundle<B> {
         }
     }
 }
+
+/// A vertex buffer of per-instance data, which grows to fit whatever it's asked to hold.
+pub struct InstanceBufferBundle<B: Backend> {
+    pub buffer: BufferBundle<B>,
+    pub capacity: usize,
+}
+
+impl<B: Backend> InstanceBufferBundle<B> {
+    pub fn new(
+        instance_size: usize,
+        capacity: usize,
+        device: &B::Device,
+        adapter: &Adapter<B>,
+    ) -> Result<Self, failure::Error> {
+        let buffer = BufferBundle::new(
+            adapter,
+            device,
+            (instance_size * capacity) as u64,
+            buffer::Usage::VERTEX,
+        )?;
+
+        Ok(InstanceBufferBundle { buffer, capacity })
+    }
+
+    /// Makes room for `num_instances`, doubling until they fit so a growing world doesn't
+    /// reallocate every frame. Returns whether the buffer was replaced.
+    pub fn reserve(
+        &mut self,
+        instance_size: usize,
+        num_instances: usize,
+        device: &B::Device,
+        adapter: &Adapter<B>,
+    ) -> Result<bool, failure::Error> {
+        if self.capacity >= num_instances {
+            return Ok(false);
+        }
+
+        let new_capacity = num_instances.next_power_of_two();
+        trace!(
+            "Updating our instance buffer! Old size was {}, new size is {}",
+            self.capacity, new_capacity
+        );
+        let new_buffer = BufferBundle::new(
+            adapter,
+            device,
+            (instance_size * new_capacity) as u64,
+            buffer::Usage::VERTEX,
+        )?;
+
+        // The old buffer could still be in use by a frame in flight.
+        device.wait_idle()?;
+        unsafe {
+            self.buffer.manually_drop(device);
+        }
+        self.buffer = new_buffer;
+        self.capacity = new_capacity;
+
+        Ok(true)
+    }
+
+    pub fn manually_drop_parts(&self, device: &B::Device) {
+        unsafe {
+            self.buffer.manually_drop(device);
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use super::{DensityCache, DensityPyramid, GameWorldDrawCommands, State, Vec2, Vec2Int};
+use gfx_hal::{
+    format::Format,
+    pso::{AttributeDesc, ElemOffset, Element},
+};
+use std::mem;
+
+pub const PREVIEW_ALIVE_ALPHA: f32 = 0.5;
+pub const PREVIEW_DEAD_ALPHA: f32 = 0.15;
+pub const SELECTION_ALPHA: f32 = 0.3;
+
+/// Cells smaller than this many pixels across are drawn as density tiles instead.
+pub const MIN_CELL_PIXELS: f32 = 1.0;
+/// Density tiles are made big enough to be at least this many pixels across.
+pub const MIN_TILE_PIXELS: f32 = 2.0;
+/// How dark the emptiest tile with anything alive in it is drawn, so lone objects don't vanish.
+const MIN_TILE_SHADE: f32 = 0.25;
+
+/// Everything the quad pipeline needs to draw one cell, or one tile of cells, read once per
+/// instance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct CellInstance {
+    pub position: [f32; 2],
+    /// How many cells across the quad is.
+    pub size: f32,
+    pub color: [f32; 4],
+}
+
+impl CellInstance {
+    /// The vertex buffer binding the instances are read from. The quad's own vertices are on 0.
+    pub const BINDING: u32 = 1;
+
+    pub fn attributes() -> Vec<AttributeDesc> {
+        let position_attribute = AttributeDesc {
+            location: 2,
+            binding: Self::BINDING,
+            element: Element {
+                format: Format::Rg32Sfloat,
+                offset: 0,
+            },
+        };
+        let size_attribute = AttributeDesc {
+            location: 3,
+            binding: Self::BINDING,
+            element: Element {
+                format: Format::R32Sfloat,
+                offset: mem::size_of::<[f32; 2]>() as ElemOffset,
+            },
+        };
+        let color_attribute = AttributeDesc {
+            location: 4,
+            binding: Self::BINDING,
+            element: Element {
+                format: Format::Rgba32Sfloat,
+                offset: mem::size_of::<[f32; 3]>() as ElemOffset,
+            },
+        };
+        vec![position_attribute, size_attribute, color_attribute]
+    }
+
+    fn new(pos: Vec2Int, color: [f32; 3], alpha: f32) -> Self {
+        Self::tile(pos, 1, color, alpha)
+    }
+
+    fn tile(corner: Vec2Int, size: i32, color: [f32; 3], alpha: f32) -> Self {
+        let position = Vec2::from(corner);
+        CellInstance {
+            position: [position.x, position.y],
+            size: size as f32,
+            color: [color[0], color[1], color[2], alpha],
+        }
+    }
+}
+
+/// How the world was drawn this frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detail {
+    /// One quad per cell.
+    Cells,
+    /// One quad per tile of a `DensityPyramid` level, shaded by how much of it is alive.
+    Tiles(u32),
+}
+
+/// The cells the camera can see, as the smallest and largest positions on screen.
+pub fn visible_cells(game_world: &GameWorldDrawCommands<'_>) -> (Vec2Int, Vec2Int) {
+    // This undoes the vertex shader, which puts a cell at
+    // `(x / scale - camera.x, y * aspect / scale - camera.y)` on screen.
+    let scale = game_world.camera_scale;
+    let aspect = game_world.aspect_ratio;
+    let center = *game_world.camera_position * scale;
+    let min = Vec2::new(center.x - scale, (center.y - scale) / aspect);
+    let max = Vec2::new(center.x + scale, (center.y + scale) / aspect);
+
+    (min.floor_to_int(), max.floor_to_int())
+}
+
+/// How many pixels across a cell is on screen.
+pub fn cell_pixels(game_world: &GameWorldDrawCommands<'_>) -> f32 {
+    game_world.viewport_size.x / (2.0 * game_world.camera_scale)
+}
+
+/// Picks cells, or the smallest density tiles at least `MIN_TILE_PIXELS` across once cells
+/// are smaller than `MIN_CELL_PIXELS`.
+pub fn detail_for(cell_pixels: f32) -> Detail {
+    if cell_pixels >= MIN_CELL_PIXELS || cell_pixels <= 0.0 {
+        return Detail::Cells;
+    }
+
+    let level = (MIN_TILE_PIXELS / cell_pixels).log2().ceil().max(1.0);
+    Detail::Tiles(level.min(30.0) as u32)
+}
+
+/// Lays out every quad a frame draws, in the order they're drawn: the world's cells or density
+/// tiles, then the prefab waiting to be placed, then the selection over the top. Anything off
+/// screen is left out. `instances` is cleared first, so the same allocation can be handed back
+/// frame after frame, and `density` keeps the tiles until the world next changes.
+pub fn build_cell_instances(
+    game_world: &GameWorldDrawCommands<'_>,
+    density: &mut DensityCache,
+    instances: &mut Vec<CellInstance>,
+) -> Detail {
+    instances.clear();
+    let colors = game_world.game_colors;
+    let (view_min, view_max) = visible_cells(game_world);
+    let on_screen = |pos: Vec2Int| {
+        pos.x >= view_min.x && pos.y >= view_min.y && pos.x <= view_max.x && pos.y <= view_max.y
+    };
+
+    let detail = detail_for(cell_pixels(game_world));
+    match detail {
+        Detail::Cells => {
+            for (pos, state) in game_world.world.iter_region(view_min, view_max) {
+                let (age_color, age) = colors.age_shade(state, game_world.ages.get(pos));
+                let color = mix((*colors.get_color(state)).into(), (*age_color).into(), age);
+                instances.push(CellInstance::new(pos, color, 1.0));
+            }
+        }
+
+        Detail::Tiles(level) => {
+            let pyramid = density.get(game_world.world, level);
+            let size = DensityPyramid::tile_size(level);
+            let (tile_min, tile_max) = (
+                DensityPyramid::tile_of(view_min, level),
+                DensityPyramid::tile_of(view_max, level),
+            );
+
+            let (bg, alive) = (colors.bg.into(), colors.alive.into());
+            for (tile, _) in pyramid.tiles(level) {
+                if tile.x < tile_min.x || tile.y < tile_min.y || tile.x > tile_max.x || tile.y > tile_max.y {
+                    continue;
+                }
+                let shade = MIN_TILE_SHADE + (1.0 - MIN_TILE_SHADE) * pyramid.density(level, tile);
+                let corner = Vec2Int::new(tile.x * size, tile.y * size);
+                instances.push(CellInstance::tile(corner, size, mix(bg, alive, shade), 1.0));
+            }
+        }
+    }
+
+    // The prefab waiting to be placed is drawn see-through over the top of the world.
+    if let Some((corner, cells)) = game_world.placement_preview {
+        for (x, column) in cells.iter().enumerate() {
+            for (y, &state) in column.iter().enumerate() {
+                let alpha = match state {
+                    State::Alive => PREVIEW_ALIVE_ALPHA,
+                    State::Dead => PREVIEW_DEAD_ALPHA,
+                    State::Unborn => continue,
+                };
+
+                let pos = corner + Vec2Int::new(x as i32, y as i32);
+                if on_screen(pos) {
+                    instances.push(CellInstance::new(pos, (*colors.get_color(state)).into(), alpha));
+                }
+            }
+        }
+    }
+
+    // The selection is tinted over everything else, only as far as the screen goes and a tile
+    // at a time when zoomed out to tiles.
+    if let Some((min, max)) = game_world.selection {
+        let color: [f32; 3] = colors.selection.into();
+        let min = Vec2Int::new(min.x.max(view_min.x), min.y.max(view_min.y));
+        let max = Vec2Int::new(max.x.min(view_max.x), max.y.min(view_max.y));
+        if min.x > max.x || min.y > max.y {
+            return detail;
+        }
+
+        match detail {
+            Detail::Cells => {
+                for x in min.x..=max.x {
+                    for y in min.y..=max.y {
+                        instances.push(CellInstance::new(Vec2Int::new(x, y), color, SELECTION_ALPHA));
+                    }
+                }
+            }
+
+            Detail::Tiles(level) => {
+                let size = DensityPyramid::tile_size(level);
+                let (tile_min, tile_max) = (
+                    DensityPyramid::tile_of(min, level),
+                    DensityPyramid::tile_of(max, level),
+                );
+                for x in tile_min.x..=tile_max.x {
+                    for y in tile_min.y..=tile_max.y {
+                        let corner = Vec2Int::new(x * size, y * size);
+                        instances.push(CellInstance::tile(corner, size, color, SELECTION_ALPHA));
+                    }
+                }
+            }
+        }
+    }
+
+    detail
+}
+
+fn mix(from: [f32; 3], to: [f32; 3], t: f32) -> [f32; 3] {
+    [
+        from[0] + (to[0] - from[0]) * t,
+        from[1] + (to[1] - from[1]) * t,
+        from[2] + (to[2] - from[2]) * t,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{CellAges, GameColors, Grid, World};
+
+    const VIEWPORT: Vec2 = Vec2 { x: 800.0, y: 800.0 };
+
+    /// Draw commands for a camera centered on `center` in world space, which is kept in
+    /// `camera` since the commands borrow it.
+    fn commands<'a>(
+        world: &'a World,
+        colors: &'a GameColors,
+        ages: &'a CellAges,
+        camera: &'a Vec2,
+        scale: f32,
+        aspect_ratio: f32,
+    ) -> GameWorldDrawCommands<'a> {
+        GameWorldDrawCommands {
+            world,
+            game_colors: colors,
+            ages,
+            camera_position: camera,
+            camera_scale: scale,
+            aspect_ratio,
+            viewport_size: VIEWPORT,
+            placement_preview: None,
+            selection: None,
+        }
+    }
+
+    /// Where the camera sits for its world-space `center`, as the renderer is handed it.
+    fn camera_at(center: Vec2, scale: f32, aspect_ratio: f32) -> Vec2 {
+        Vec2::new(center.x, center.y * aspect_ratio) / scale
+    }
+
+    /// An 8 by 6 board with two live cells and one dead one.
+    fn small_world() -> World {
+        let mut world = World::Bounded(Grid::new(8, 6));
+        world.set(Vec2Int::new(1, 1), State::Alive);
+        world.set(Vec2Int::new(2, 1), State::Alive);
+        world.set(Vec2Int::new(3, 3), State::Dead);
+        world
+    }
+
+    fn rgb(color: [f32; 4]) -> [f32; 3] {
+        [color[0], color[1], color[2]]
+    }
+
+    /// Where the vertex shader puts a point in world space, in clip space.
+    fn shader_clip(point: Vec2, game_world: &GameWorldDrawCommands<'_>) -> Vec2 {
+        let model = Vec2::new(point.x, point.y * game_world.aspect_ratio);
+        Vec2::new(
+            model.x / game_world.camera_scale - game_world.camera_position.x,
+            model.y / game_world.camera_scale - game_world.camera_position.y,
+        )
+    }
+
+    #[test]
+    fn instances_are_world_then_preview_then_selection() {
+        let (world, colors, ages) = (small_world(), GameColors::default(), CellAges::new(10));
+        let camera = camera_at(Vec2::new(4.0, 3.0), 8.0, 1.0);
+        let preview = vec![vec![State::Alive, State::Dead], vec![State::Unborn, State::Alive]];
+        let mut game_world = commands(&world, &colors, &ages, &camera, 8.0, 1.0);
+        game_world.placement_preview = Some((Vec2Int::new(5, 4), &preview));
+        game_world.selection = Some((Vec2Int::new(0, 0), Vec2Int::new(1, 1)));
+
+        let mut instances = Vec::new();
+        let detail = build_cell_instances(&game_world, &mut DensityCache::new(), &mut instances);
+        assert_eq!(detail, Detail::Cells);
+        assert_eq!(instances.len(), 8 * 6 + 3 + 4);
+
+        // Every cell of the board comes first, in the order the world walks them.
+        for ((pos, state), instance) in world.iter().zip(&instances) {
+            assert_eq!(instance.position, [pos.x as f32, pos.y as f32]);
+            assert_eq!(instance.size, 1.0);
+            assert_eq!(rgb(instance.color), <[f32; 3]>::from(*colors.get_color(state)));
+            assert_eq!(instance.color[3], 1.0);
+        }
+
+        // Then the preview, leaving out its unborn cell.
+        let alive: [f32; 3] = colors.alive.into();
+        let dead: [f32; 3] = colors.dead.into();
+        let previewed: Vec<([f32; 2], [f32; 3], f32)> = instances[48..51]
+            .iter()
+            .map(|instance| (instance.position, rgb(instance.color), instance.color[3]))
+            .collect();
+        assert_eq!(
+            previewed,
+            vec![
+                ([5.0, 4.0], alive, PREVIEW_ALIVE_ALPHA),
+                ([5.0, 5.0], dead, PREVIEW_DEAD_ALPHA),
+                ([6.0, 5.0], alive, PREVIEW_ALIVE_ALPHA),
+            ]
+        );
+
+        // And the selection over the top.
+        let selection: [f32; 3] = colors.selection.into();
+        for instance in &instances[51..] {
+            assert_eq!(rgb(instance.color), selection);
+            assert_eq!(instance.color[3], SELECTION_ALPHA);
+        }
+        let mut selected: Vec<[f32; 2]> = instances[51..].iter().map(|instance| instance.position).collect();
+        selected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(selected, vec![[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]]);
+    }
+
+    #[test]
+    fn off_screen_cells_are_left_out() {
+        let (world, colors, ages) = (small_world(), GameColors::default(), CellAges::new(10));
+        let camera = camera_at(Vec2::new(0.5, 0.5), 1.0, 1.0);
+        let preview = vec![vec![State::Alive]];
+        let mut game_world = commands(&world, &colors, &ages, &camera, 1.0, 1.0);
+        game_world.placement_preview = Some((Vec2Int::new(5, 4), &preview));
+        game_world.selection = Some((Vec2Int::new(0, 0), Vec2Int::new(7, 5)));
+
+        let (view_min, view_max) = visible_cells(&game_world);
+        assert_eq!((view_min, view_max), (Vec2Int::new(-1, -1), Vec2Int::new(1, 1)));
+
+        let (mut density, mut instances) = (DensityCache::new(), Vec::new());
+        build_cell_instances(&game_world, &mut density, &mut instances);
+
+        // The four cells of the board in view, none of the preview, and the selection cut
+        // down to what's in view.
+        let (board, selection) = instances.split_at(4);
+        assert!(board.iter().all(|instance| instance.color[3] == 1.0));
+        assert!(selection
+            .iter()
+            .all(|instance| instance.color[3] == SELECTION_ALPHA));
+        assert_eq!(selection.len(), 4);
+        for instance in &instances {
+            let [x, y] = instance.position;
+            assert!(
+                x >= 0.0 && y >= 0.0 && x <= 1.0 && y <= 1.0,
+                "{:?} is off screen",
+                instance
+            );
+        }
+
+        // Looking somewhere else entirely leaves nothing to draw.
+        let camera = camera_at(Vec2::new(100.0, 100.0), 1.0, 1.0);
+        let game_world = commands(&world, &colors, &ages, &camera, 1.0, 1.0);
+        build_cell_instances(&game_world, &mut density, &mut instances);
+        assert!(instances.is_empty());
+    }
+
+    #[test]
+    fn visible_cells_match_the_vertex_shader() {
+        let (world, colors, ages) = (
+            World::Bounded(Grid::new(1, 1)),
+            GameColors::default(),
+            CellAges::new(10),
+        );
+        let views = [
+            (Vec2::new(0.0, 0.0), 10.0, 16.0 / 9.0),
+            (Vec2::new(3.3, -7.8), 2.5, 16.0 / 9.0),
+            (Vec2::new(-120.4, 55.1), 37.0, 4.0 / 3.0),
+            (Vec2::new(1000.7, 1000.2), 0.6, 1.0),
+        ];
+
+        for &(center, scale, aspect_ratio) in &views {
+            let camera = camera_at(center, scale, aspect_ratio);
+            let game_world = commands(&world, &colors, &ages, &camera, scale, aspect_ratio);
+            let (min, max) = visible_cells(&game_world);
+            let clip = |x: i32, y: i32| shader_clip(Vec2::new(x as f32, y as f32), &game_world);
+            let at_most = |a: f32, b: f32| a <= b + 1e-4;
+
+            // The screen's edges fall inside the first and last cells, or on their corners,
+            // rather than a cell further in or out.
+            assert!(at_most(clip(min.x, min.y).x, -1.0) && at_most(-1.0, clip(min.x + 1, min.y).x));
+            assert!(at_most(clip(min.x, min.y).y, -1.0) && at_most(-1.0, clip(min.x, min.y + 1).y));
+            assert!(at_most(clip(max.x, max.y).x, 1.0) && at_most(1.0, clip(max.x + 1, max.y).x));
+            assert!(at_most(clip(max.x, max.y).y, 1.0) && at_most(1.0, clip(max.x, max.y + 1).y));
+        }
+    }
+}
@@ -22,6 +22,8 @@ pub struct LoadedImage<B: Backend> {
     pub image_view: ManuallyDrop<B::ImageView>,
     pub sampler: ManuallyDrop<B::Sampler>,
     pub descriptor_set: ManuallyDrop<B::DescriptorSet>,
+    pub width: usize,
+    pub height: usize,
     pub phantom: PhantomData<B::Device>,
 }
 
@@ -38,35 +40,7 @@ impl<B: Backend> LoadedImage<B> {
         height: usize,
     ) -> Result<Self, failure::Error> {
         unsafe {
-            // 0.   First we compute some memory related values:
-            let pixel_size = size_of::<image::Rgba<u8>>();
-            let row_size = pixel_size * width;
-            let limits = adapter.physical_device.limits();
-            let row_alignment_mask = limits.optimal_buffer_copy_pitch_alignment as u32 - 1;
-            let row_pitch = ((row_size as u32 + row_alignment_mask) & !row_alignment_mask) as usize;
-            debug_assert!(row_pitch as usize >= row_size);
-
-            // 1.   Make a staging buffer with enough memory for the image
-            //      and a trsnfer_src image
-            let required_bytes = (row_pitch * height) as u64;
-            let staging_bundle =
-                BufferBundle::new(&adapter, device, required_bytes, buffer::Usage::TRANSFER_SRC)?;
-
-            // 2.   Use a mapping writer to put the image data into the buffer
-            let mut writer = device
-                .acquire_mapping_writer::<u8>(&staging_bundle.memory, 0..staging_bundle.requirements.size)
-                .map_err(|e| LoadedImageError::AcquireMappingWriter(e))?;
-
-            for y in 0..height {
-                let row = &(*img)[y * row_size..(y + 1) * row_size];
-                let dest_base = y * row_pitch;
-                writer[dest_base..dest_base + row.len()].copy_from_slice(row);
-            }
-            device
-                .release_mapping_writer(writer)
-                .map_err(|e| LoadedImageError::ReleaseMappingWriter(e))?;
-
-            //  3. Make the image
+            //  1. Make the image
             let mut image_object = device
                 .create_image(
                     gfx_hal::image::Kind::D2(width as u32, height as u32, 1, 1),
@@ -78,7 +52,7 @@ impl<B: Backend> LoadedImage<B> {
                 )
                 .map_err(|e| LoadedImageError::CreateImage(e))?;
 
-            //  4. allocate the memory and bind it
+            //  2. allocate the memory and bind it
             let requirements = device.get_image_requirements(&image_object);
             let memory_type_id = adapter
                 .physical_device
@@ -99,7 +73,7 @@ impl<B: Backend> LoadedImage<B> {
                 .bind_image_memory(&memory, 0, &mut image_object)
                 .map_err(|e| BufferError::Bind(e))?;
 
-            // 5. create image view and sampler
+            // 3. create image view and sampler
             let image_view = device
                 .create_image_view(
                     &image_object,
@@ -121,86 +95,19 @@ impl<B: Backend> LoadedImage<B> {
                 ))
                 .map_err(|e| LoadedImageError::Sampler(e))?;
 
-            // 6. create the command buffer
-            let mut cmd_buffer = command_pool.acquire_command_buffer::<gfx_hal::command::OneShot>();
-            cmd_buffer.begin();
-
-            // 7. Use a pipeline barrier to transition the image from empty/undefined
-            //    to TRANSFER_WRITE/TransferDstOptimal
-            let image_barrier = gfx_hal::memory::Barrier::Image {
-                states: (gfx_hal::image::Access::empty(), Layout::Undefined)
-                    ..(gfx_hal::image::Access::TRANSFER_WRITE, Layout::TransferDstOptimal),
-                target: &image_object,
-                families: None,
-                range: SubresourceRange {
-                    aspects: Aspects::COLOR,
-                    levels: 0..1,
-                    layers: 0..1,
-                },
-            };
-            cmd_buffer.pipeline_barrier(
-                PipelineStage::TOP_OF_PIPE..PipelineStage::TRANSFER,
-                gfx_hal::memory::Dependencies::empty(),
-                &[image_barrier],
-            );
-
-            //  8. perform copy!
-            cmd_buffer.copy_buffer_to_image(
-                &staging_bundle.buffer,
+            // 4. upload the pixels
+            Self::upload(
+                adapter,
+                device,
+                command_pool,
+                command_queue,
                 &image_object,
-                Layout::TransferDstOptimal,
-                &[gfx_hal::command::BufferImageCopy {
-                    buffer_offset: 0,
-                    buffer_width: (row_pitch / pixel_size) as u32,
-                    buffer_height: height as u32,
-                    image_layers: gfx_hal::image::SubresourceLayers {
-                        aspects: Aspects::COLOR,
-                        level: 0,
-                        layers: 0..1,
-                    },
-                    image_offset: gfx_hal::image::Offset { x: 0, y: 0, z: 0 },
-                    image_extent: gfx_hal::image::Extent {
-                        width: width as u32,
-                        height: height as u32,
-                        depth: 1,
-                    },
-                }],
-            );
-
-            // 9. use pipeline barrier to transition the image to SHADER_READ access/
-            //    ShaderReadOnlyOptimal layout
-            let image_barrier = gfx_hal::memory::Barrier::Image {
-                states: (gfx_hal::image::Access::TRANSFER_WRITE, Layout::TransferDstOptimal)
-                    ..(gfx_hal::image::Access::SHADER_READ, Layout::ShaderReadOnlyOptimal),
-                target: &image_object,
-                families: None,
-                range: SubresourceRange {
-                    aspects: Aspects::COLOR,
-                    levels: 0..1,
-                    layers: 0..1,
-                },
-            };
-            cmd_buffer.pipeline_barrier(
-                PipelineStage::TRANSFER..PipelineStage::FRAGMENT_SHADER,
-                gfx_hal::memory::Dependencies::empty(),
-                &[image_barrier],
-            );
-
-            //  10. Submit it!
-            cmd_buffer.finish();
-
-            let upload_fence = device
-                .create_fence(false)
-                .map_err(|e| LoadedImageError::UploadFence(e))?;
-            command_queue.submit_without_semaphores(Some(&cmd_buffer), Some(&upload_fence));
-            device
-                .wait_for_fence(&upload_fence, core::u64::MAX)
-                .map_err(|e| LoadedImageError::WaitForFence(e))?;
-            device.destroy_fence(upload_fence);
-
-            //  11. Kill off our buffer!
-            staging_bundle.manually_drop(device);
-            command_pool.free(Some(cmd_buffer));
+                img,
+                width,
+                height,
+                (gfx_hal::image::Access::empty(), Layout::Undefined),
+                PipelineStage::TOP_OF_PIPE,
+            )?;
 
             let descriptor_set = pipeline_bundle
                 .allocate_descriptor_set()?;
@@ -212,6 +119,8 @@ impl<B: Backend> LoadedImage<B> {
                 image_view: manual_new!(image_view),
                 sampler: manual_new!(sampler),
                 descriptor_set: manual_new!(descriptor_set),
+                width,
+                height,
                 phantom: PhantomData,
             };
 
@@ -237,6 +146,168 @@ impl<B: Backend> LoadedImage<B> {
             Ok(texture)
         }
     }
+    /// Replaces every pixel of the image with `img`, which has to be the same size.
+    pub fn update<C: Capability + Supports<Transfer>>(
+        &self,
+        adapter: &Adapter<B>,
+        device: &B::Device,
+        command_pool: &mut CommandPool<B, C>,
+        command_queue: &mut CommandQueue<B, C>,
+        img: &[u8],
+    ) -> Result<(), failure::Error> {
+        let pixel_size = size_of::<image::Rgba<u8>>();
+        if img.len() != self.width * self.height * pixel_size {
+            return Err(format_err!(
+                "Expected {} bytes to update a {} by {} image, but got {}",
+                self.width * self.height * pixel_size,
+                self.width,
+                self.height,
+                img.len()
+            ));
+        }
+
+        unsafe {
+            Self::upload(
+                adapter,
+                device,
+                command_pool,
+                command_queue,
+                &self.image,
+                img,
+                self.width,
+                self.height,
+                (gfx_hal::image::Access::SHADER_READ, Layout::ShaderReadOnlyOptimal),
+                PipelineStage::FRAGMENT_SHADER,
+            )
+        }
+    }
+
+    /// Copies `img` into the image through a staging buffer, waiting until it's there. `from`
+    /// is the access and layout the image is in now, and `from_stage` the last stage using it;
+    /// it's left ready for the fragment shader to read.
+    unsafe fn upload<C: Capability + Supports<Transfer>>(
+        adapter: &Adapter<B>,
+        device: &B::Device,
+        command_pool: &mut CommandPool<B, C>,
+        command_queue: &mut CommandQueue<B, C>,
+        image_object: &B::Image,
+        img: &[u8],
+        width: usize,
+        height: usize,
+        from: (gfx_hal::image::Access, Layout),
+        from_stage: PipelineStage,
+    ) -> Result<(), failure::Error> {
+        // 0.   First we compute some memory related values:
+        let pixel_size = size_of::<image::Rgba<u8>>();
+        let row_size = pixel_size * width;
+        let limits = adapter.physical_device.limits();
+        let row_alignment_mask = limits.optimal_buffer_copy_pitch_alignment as u32 - 1;
+        let row_pitch = ((row_size as u32 + row_alignment_mask) & !row_alignment_mask) as usize;
+        debug_assert!(row_pitch as usize >= row_size);
+
+        // 1.   Make a staging buffer with enough memory for the image
+        //      and a trsnfer_src image
+        let required_bytes = (row_pitch * height) as u64;
+        let staging_bundle =
+            BufferBundle::new(&adapter, device, required_bytes, buffer::Usage::TRANSFER_SRC)?;
+
+        // 2.   Use a mapping writer to put the image data into the buffer
+        let mut writer = device
+            .acquire_mapping_writer::<u8>(&staging_bundle.memory, 0..staging_bundle.requirements.size)
+            .map_err(|e| LoadedImageError::AcquireMappingWriter(e))?;
+
+        for y in 0..height {
+            let row = &(*img)[y * row_size..(y + 1) * row_size];
+            let dest_base = y * row_pitch;
+            writer[dest_base..dest_base + row.len()].copy_from_slice(row);
+        }
+        device
+            .release_mapping_writer(writer)
+            .map_err(|e| LoadedImageError::ReleaseMappingWriter(e))?;
+
+        // 3. create the command buffer
+        let mut cmd_buffer = command_pool.acquire_command_buffer::<gfx_hal::command::OneShot>();
+        cmd_buffer.begin();
+
+        // 4. Use a pipeline barrier to transition the image from whatever it was
+        //    to TRANSFER_WRITE/TransferDstOptimal
+        let image_barrier = gfx_hal::memory::Barrier::Image {
+            states: from..(gfx_hal::image::Access::TRANSFER_WRITE, Layout::TransferDstOptimal),
+            target: image_object,
+            families: None,
+            range: SubresourceRange {
+                aspects: Aspects::COLOR,
+                levels: 0..1,
+                layers: 0..1,
+            },
+        };
+        cmd_buffer.pipeline_barrier(
+            from_stage..PipelineStage::TRANSFER,
+            gfx_hal::memory::Dependencies::empty(),
+            &[image_barrier],
+        );
+
+        //  5. perform copy!
+        cmd_buffer.copy_buffer_to_image(
+            &staging_bundle.buffer,
+            image_object,
+            Layout::TransferDstOptimal,
+            &[gfx_hal::command::BufferImageCopy {
+                buffer_offset: 0,
+                buffer_width: (row_pitch / pixel_size) as u32,
+                buffer_height: height as u32,
+                image_layers: gfx_hal::image::SubresourceLayers {
+                    aspects: Aspects::COLOR,
+                    level: 0,
+                    layers: 0..1,
+                },
+                image_offset: gfx_hal::image::Offset { x: 0, y: 0, z: 0 },
+                image_extent: gfx_hal::image::Extent {
+                    width: width as u32,
+                    height: height as u32,
+                    depth: 1,
+                },
+            }],
+        );
+
+        // 6. use pipeline barrier to transition the image to SHADER_READ access/
+        //    ShaderReadOnlyOptimal layout
+        let image_barrier = gfx_hal::memory::Barrier::Image {
+            states: (gfx_hal::image::Access::TRANSFER_WRITE, Layout::TransferDstOptimal)
+                ..(gfx_hal::image::Access::SHADER_READ, Layout::ShaderReadOnlyOptimal),
+            target: image_object,
+            families: None,
+            range: SubresourceRange {
+                aspects: Aspects::COLOR,
+                levels: 0..1,
+                layers: 0..1,
+            },
+        };
+        cmd_buffer.pipeline_barrier(
+            PipelineStage::TRANSFER..PipelineStage::FRAGMENT_SHADER,
+            gfx_hal::memory::Dependencies::empty(),
+            &[image_barrier],
+        );
+
+        //  7. Submit it!
+        cmd_buffer.finish();
+
+        let upload_fence = device
+            .create_fence(false)
+            .map_err(|e| LoadedImageError::UploadFence(e))?;
+        command_queue.submit_without_semaphores(Some(&cmd_buffer), Some(&upload_fence));
+        device
+            .wait_for_fence(&upload_fence, core::u64::MAX)
+            .map_err(|e| LoadedImageError::WaitForFence(e))?;
+        device.destroy_fence(upload_fence);
+
+        //  8. Kill off our buffer!
+        staging_bundle.manually_drop(device);
+        command_pool.free(Some(cmd_buffer));
+
+        Ok(())
+    }
+
     pub unsafe fn manually_drop(&self, device: &B::Device) {
         use core::ptr::read;
         device.destroy_sampler(ManuallyDrop::into_inner(read(&self.sampler)));
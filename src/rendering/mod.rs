@@ -1,5 +1,5 @@
 pub use super::ecs::{GameColors, Window};
-pub use super::utilities::Vec2;
+pub use super::utilities::{Vec2, Vec2Int};
 
 macro_rules! manual_drop {
     ($this_val:expr) => {
@@ -14,6 +14,7 @@ macro_rules! manual_new {
 }
 
 mod buffer_bundle;
+mod cell_instance;
 mod loaded_image;
 mod pipeline_bundle;
 mod renderer;
@@ -21,8 +22,9 @@ mod renderer_commands;
 mod renderer_errors;
 mod vertex;
 
-pub use super::ecs::Entity;
+pub use super::ecs::{CellAges, DensityCache, DensityPyramid, State, World};
 pub use buffer_bundle::*;
+pub use cell_instance::*;
 pub use loaded_image::*;
 pub use pipeline_bundle::*;
 pub use renderer::TypedRenderer;
@@ -11,9 +11,9 @@ use gfx_hal::{
     pass::{Attachment, AttachmentLoadOp, AttachmentOps, AttachmentStoreOp, Subpass, SubpassDesc},
     pool::{CommandPool, CommandPoolCreateFlags},
     pso::{
-        AttributeDesc, BakedStates, BasePipeline, BlendDesc, BlendOp, BlendState, ColorBlendDesc, ColorMask,
+        AttributeDesc, BakedStates, BasePipeline, BlendDesc, BlendState, ColorBlendDesc, ColorMask,
         DepthStencilDesc, DescriptorRangeDesc, DescriptorSetLayoutBinding, DescriptorType, ElemStride,
-        Element, EntryPoint, Face, Factor, FrontFace, GraphicsPipelineDesc, GraphicsShaderSet,
+        Element, EntryPoint, Face, FrontFace, GraphicsPipelineDesc, GraphicsShaderSet,
         InputAssemblerDesc, LogicOp, PipelineCreationFlags, PipelineStage, PolygonMode, Rasterizer, Rect,
         ShaderStageFlags, Specialization, VertexBufferDesc, VertexInputRate, Viewport,
     },
@@ -33,18 +33,23 @@ use gfx_backend_metal as back;
 use gfx_backend_vulkan as back;
 
 use super::{
-    BufferBundle, DrawingError, GameWorldDrawCommands, ImGuiDrawCommands, LoadedImage, MemoryWritingError,
+    build_cell_instances, BufferBundle, CellInstance, DensityCache, Detail, DrawingError,
+    GameWorldDrawCommands, ImGuiDrawCommands, InstanceBufferBundle, LoadedImage, MemoryWritingError,
     PipelineBundle, PipelineCreationError, RendererCommands, RendererCreationError, Vertex,
     VertexIndexPairBufferBundle, Window, QUAD_INDICES, QUAD_VERTICES,
 };
 
-const VERTEX_PUSH_CONSTANTS_SIZE: usize = 6;
+const VERTEX_PUSH_CONSTANTS_SIZE: usize = 4;
 const FRAG_PUSH_CONSTANTS_START: u32 = 8;
-const FRAG_PUSH_CONSTANTS_SIZE: usize = 12;
+const FRAG_PUSH_CONSTANTS_SIZE: usize = 5;
+/// How many cells the instance buffer starts out with room for.
+const INITIAL_INSTANCE_CAPACITY: usize = 4096;
 
 const QUAD_DATA: usize = 0;
 const IMGUI_DATA: usize = 1;
 const PIPELINE_SIZE: usize = 2;
+/// The font atlas, and the minimap.
+const MAX_TEXTURES: usize = 2;
 
 pub struct Renderer<I: Instance> {
     // Top
@@ -58,7 +63,10 @@ pub struct Renderer<I: Instance> {
     // Pipeline nonsense
     pipeline_bundles: ArrayVec<[PipelineBundle<I::Backend>; PIPELINE_SIZE]>,
     vertex_index_buffer_bundles: ArrayVec<[VertexIndexPairBufferBundle<I::Backend>; PIPELINE_SIZE]>,
-    images: ArrayVec<[LoadedImage<I::Backend>; 1]>,
+    instance_buffer_bundle: InstanceBufferBundle<I::Backend>,
+    cell_instances: Vec<CellInstance>,
+    density: DensityCache,
+    images: ArrayVec<[LoadedImage<I::Backend>; MAX_TEXTURES]>,
 
     // GPU Swapchain
     swapchain: ManuallyDrop<<I::Backend as Backend>::Swapchain>,
@@ -359,6 +367,13 @@ impl<I: Instance> Renderer<I> {
             num_idx: 6,
         });
 
+        let instance_buffer_bundle = InstanceBufferBundle::new(
+            mem::size_of::<CellInstance>(),
+            INITIAL_INSTANCE_CAPACITY,
+            &device,
+            &adapter,
+        )?;
+
         Ok(Self {
             instance: manual_new!(instance),
             surface,
@@ -379,6 +394,9 @@ impl<I: Instance> Renderer<I> {
             frames_in_flight,
             current_frame: 0,
             vertex_index_buffer_bundles,
+            instance_buffer_bundle,
+            cell_instances: Vec::new(),
+            density: DensityCache::new(),
 
             pipeline_bundles,
             images: ArrayVec::new(),
@@ -440,6 +458,11 @@ impl<I: Instance> Renderer<I> {
                         game_world_commands,
                         &self.pipeline_bundles[QUAD_DATA],
                         &self.vertex_index_buffer_bundles[QUAD_DATA],
+                        &mut self.instance_buffer_bundle,
+                        &mut self.cell_instances,
+                        &mut self.density,
+                        &self.device,
+                        &self.adapter,
                     )?;
                 }
 
@@ -451,7 +474,7 @@ impl<I: Instance> Renderer<I> {
                         &mut self.vertex_index_buffer_bundles[IMGUI_DATA],
                         &self.device,
                         &self.adapter,
-                        self.images[0].descriptor_set.deref(),
+                        &self.images,
                     )?;
                 }
             }
@@ -600,6 +623,44 @@ impl<I: Instance> Renderer<I> {
         Ok(())
     }
 
+    /// Uploads an RGBA image for imgui to draw, and returns the id to draw it with.
+    pub fn create_texture(&mut self, pixels: &[u8], width: usize, height: usize) -> Result<TextureId, Error> {
+        if self.images.is_full() {
+            return Err(format_err!("There's no room for more than {} textures", MAX_TEXTURES));
+        }
+
+        let image = LoadedImage::allocate_and_create(
+            &self.adapter,
+            &self.device,
+            &mut self.command_pool,
+            &mut self.queue_group.queues[0],
+            &mut self.pipeline_bundles[IMGUI_DATA],
+            pixels,
+            width,
+            height,
+        )?;
+
+        let texture_id = TextureId::from(self.images.len());
+        self.images.push(image);
+        Ok(texture_id)
+    }
+
+    /// Replaces the pixels of a texture made by `create_texture`, keeping its size.
+    pub fn update_texture(&mut self, texture_id: TextureId, pixels: &[u8]) -> Result<(), Error> {
+        let image = self
+            .images
+            .get(texture_id.id())
+            .ok_or_else(|| format_err!("There's no texture {}", texture_id.id()))?;
+
+        image.update(
+            &self.adapter,
+            &self.device,
+            &mut self.command_pool,
+            &mut self.queue_group.queues[0],
+            pixels,
+        )
+    }
+
     fn allocate_imgui_buffers(&mut self) -> Result<(), Error> {
         let vertex_buffer = BufferBundle::new(
             &self.adapter,
@@ -696,13 +757,21 @@ impl<I: Instance> Renderer<I> {
             hull: None,
         };
 
-        let vertex_buffers = vec![VertexBufferDesc {
-            binding: 0,
-            stride: mem::size_of::<Vertex>() as ElemStride,
-            rate: VertexInputRate::Vertex,
-        }];
+        let vertex_buffers = vec![
+            VertexBufferDesc {
+                binding: 0,
+                stride: mem::size_of::<Vertex>() as ElemStride,
+                rate: VertexInputRate::Vertex,
+            },
+            VertexBufferDesc {
+                binding: CellInstance::BINDING,
+                stride: mem::size_of::<CellInstance>() as ElemStride,
+                rate: VertexInputRate::Instance(1),
+            },
+        ];
 
-        let attributes = Vertex::attributes();
+        let mut attributes = Vertex::attributes();
+        attributes.extend(CellInstance::attributes());
 
         let rasterizer = Rasterizer {
             depth_clamping: false,
@@ -720,21 +789,11 @@ impl<I: Instance> Renderer<I> {
         };
 
         let blender = {
-            let blend_state = BlendState {
-                color: BlendOp::Add {
-                    src: Factor::One,
-                    dst: Factor::Zero,
-                },
-                alpha: BlendOp::Add {
-                    src: Factor::One,
-                    dst: Factor::Zero,
-                },
-            };
             BlendDesc {
                 logic_op: Some(LogicOp::Copy),
                 targets: vec![ColorBlendDesc {
                     mask: ColorMask::ALL,
-                    blend: Some(blend_state),
+                    blend: Some(BlendState::ALPHA),
                 }],
             }
         };
@@ -894,15 +953,15 @@ impl<I: Instance> Renderer<I> {
         let descriptor_pool = unsafe {
             device
                 .create_descriptor_pool(
-                    1,
+                    MAX_TEXTURES,
                     &[
                         DescriptorRangeDesc {
                             ty: DescriptorType::SampledImage,
-                            count: 1,
+                            count: MAX_TEXTURES,
                         },
                         DescriptorRangeDesc {
                             ty: DescriptorType::Sampler,
-                            count: 1,
+                            count: MAX_TEXTURES,
                         },
                     ],
                     gfx_hal::pso::DescriptorPoolCreateFlags::empty(),
@@ -998,10 +1057,31 @@ impl<I: Instance> Renderer<I> {
         game_world: GameWorldDrawCommands<'_>,
         quad_pipeline: &'a PipelineBundle<I::Backend>,
         buffer_bundle: &'a VertexIndexPairBufferBundle<I::Backend>,
+        instance_bundle: &'a mut InstanceBufferBundle<I::Backend>,
+        cell_instances: &mut Vec<CellInstance>,
+        density: &mut DensityCache,
+        device: &<I::Backend as Backend>::Device,
+        adapter: &Adapter<I::Backend>,
     ) -> Result<(), DrawingError> {
+        let detail = build_cell_instances(&game_world, density, cell_instances);
+        if cell_instances.is_empty() {
+            return Ok(());
+        }
+
+        instance_bundle
+            .reserve(mem::size_of::<CellInstance>(), cell_instances.len(), device, adapter)
+            .map_err(|_| DrawingError::BufferCreationError)?;
+        instance_bundle.buffer.update_buffer(cell_instances, 0);
+
         encoder.bind_graphics_pipeline(&quad_pipeline.graphics_pipeline);
         // Bind the vertex buffers in
-        encoder.bind_vertex_buffers(0, Some((buffer_bundle.vertex_buffer.buffer.deref(), 0)));
+        encoder.bind_vertex_buffers(
+            0,
+            vec![
+                (buffer_bundle.vertex_buffer.buffer.deref(), 0),
+                (instance_bundle.buffer.buffer.deref(), 0),
+            ],
+        );
         encoder.bind_index_buffer(IndexBufferView {
             buffer: &buffer_bundle.index_buffer.buffer,
             offset: 0,
@@ -1009,54 +1089,41 @@ impl<I: Instance> Renderer<I> {
         });
 
         let mut vertex_push_constants: [u32; VERTEX_PUSH_CONSTANTS_SIZE] = [0; VERTEX_PUSH_CONSTANTS_SIZE];
-        vertex_push_constants[2] = game_world.camera_position.x.to_bits();
-        vertex_push_constants[3] = game_world.camera_position.y.to_bits();
-        vertex_push_constants[4] = game_world.camera_scale.to_bits();
-        vertex_push_constants[5] = game_world.aspect_ratio.to_bits();
+        vertex_push_constants[0] = game_world.camera_position.x.to_bits();
+        vertex_push_constants[1] = game_world.camera_position.y.to_bits();
+        vertex_push_constants[2] = game_world.camera_scale.to_bits();
+        vertex_push_constants[3] = game_world.aspect_ratio.to_bits();
 
         let mut frag_push_constants: [u32; FRAG_PUSH_CONSTANTS_SIZE] = [0; FRAG_PUSH_CONSTANTS_SIZE];
-        frag_push_constants[3] = game_world.game_colors.grid_lines.into();
+        let grid_colors = game_world.game_colors.grid_line_color.into_raw_u32();
+        frag_push_constants[0] = grid_colors[0];
+        frag_push_constants[1] = grid_colors[1];
+        frag_push_constants[2] = grid_colors[2];
+        // Grid lines would only outline the tiles once zoomed out that far.
+        frag_push_constants[3] = (game_world.game_colors.grid_lines && detail == Detail::Cells).into();
         // these are basic random values, but they look pretty alright
         frag_push_constants[4] = (0.5f32.min(
             1.0f32.max(game_world.camera_scale / (game_world.aspect_ratio * 10.0))
                 * game_world.game_colors.grid_line_width,
         ))
         .to_bits();
-        let grid_colors = game_world.game_colors.grid_line_color.into_raw_u32();
-        frag_push_constants[8] = grid_colors[0];
-        frag_push_constants[9] = grid_colors[1];
-        frag_push_constants[10] = grid_colors[2];
-
-        for row in game_world.entities {
-            for entity in row {
-                // Vertex push Constants
-                let bits = entity.position.to_bits();
-                vertex_push_constants[0] = bits[0];
-                vertex_push_constants[1] = bits[1];
-
-                // Fragment push Constants
-                let color_bits: [u32; 3] = game_world.game_colors.get_color(entity.state).into_raw_u32();
-                frag_push_constants[0] = color_bits[0];
-                frag_push_constants[1] = color_bits[1];
-                frag_push_constants[2] = color_bits[2];
-
-                encoder.push_graphics_constants(
-                    &quad_pipeline.pipeline_layout,
-                    ShaderStageFlags::VERTEX,
-                    0,
-                    &vertex_push_constants,
-                );
 
-                encoder.push_graphics_constants(
-                    &quad_pipeline.pipeline_layout,
-                    ShaderStageFlags::FRAGMENT,
-                    mem::size_of::<u32>() as u32 * FRAG_PUSH_CONSTANTS_START,
-                    &frag_push_constants,
-                );
+        encoder.push_graphics_constants(
+            &quad_pipeline.pipeline_layout,
+            ShaderStageFlags::VERTEX,
+            0,
+            &vertex_push_constants,
+        );
 
-                encoder.draw_indexed(0..6, 0, 0..1);
-            }
-        }
+        encoder.push_graphics_constants(
+            &quad_pipeline.pipeline_layout,
+            ShaderStageFlags::FRAGMENT,
+            mem::size_of::<u32>() as u32 * FRAG_PUSH_CONSTANTS_START,
+            &frag_push_constants,
+        );
+
+        // Every cell or tile, the preview and the selection go in one draw.
+        encoder.draw_indexed(0..6, 0, 0..cell_instances.len() as u32);
 
         Ok(())
     }
@@ -1068,7 +1135,7 @@ impl<I: Instance> Renderer<I> {
         buffer_bundle: &'a mut VertexIndexPairBufferBundle<I::Backend>,
         device: &<I::Backend as Backend>::Device,
         adapter: &Adapter<I::Backend>,
-        descriptor_set: &<I::Backend as Backend>::DescriptorSet,
+        images: &[LoadedImage<I::Backend>],
     ) -> Result<(), DrawingError> {
         buffer_bundle
             .update_size(
@@ -1091,14 +1158,6 @@ impl<I: Instance> Renderer<I> {
         // Bind pipeline
         encoder.bind_graphics_pipeline(&imgui_pipeline.graphics_pipeline);
 
-        // descriptor SET needs to be here...this is from the texture.
-        encoder.bind_graphics_descriptor_sets(
-            &imgui_pipeline.pipeline_layout,
-            0,
-            Some(descriptor_set),
-            None as Option<u32>,
-        );
-
         // Bind vertex and index buffers
         encoder.bind_vertex_buffers(0, Some((imgui_vertex_buffer.buffer.deref(), 0)));
         encoder.bind_index_buffer(buffer::IndexBufferView {
@@ -1136,6 +1195,7 @@ impl<I: Instance> Renderer<I> {
 
         let mut vertex_offset = 0;
         let mut index_offset = 0;
+        let mut bound_texture = None;
 
         // Iterate over drawlists
         for list in imgui_data.draw_data.draw_lists() {
@@ -1154,6 +1214,19 @@ impl<I: Instance> Renderer<I> {
                     };
                     encoder.set_scissors(0, &[scissor]);
 
+                    // descriptor SET needs to be here...this is from the texture.
+                    let texture = cmd_params.texture_id.id();
+                    if bound_texture != Some(texture) {
+                        let image = images.get(texture).ok_or(DrawingError::MissingTexture)?;
+                        encoder.bind_graphics_descriptor_sets(
+                            &imgui_pipeline.pipeline_layout,
+                            0,
+                            Some(image.descriptor_set.deref()),
+                            None as Option<u32>,
+                        );
+                        bound_texture = Some(texture);
+                    }
+
                     // Actually draw things
                     encoder.draw_indexed(
                         index_offset as u32..(index_offset + count) as u32,
@@ -1234,6 +1307,7 @@ impl<I: Instance> core::ops::Drop for Renderer<I> {
             for this_bundled_bundle in self.vertex_index_buffer_bundles.drain(..) {
                 this_bundled_bundle.manually_drop_parts(&self.device);
             }
+            self.instance_buffer_bundle.manually_drop_parts(&self.device);
 
             // LAST RESORT STYLE CODE, NOT TO BE IMITATED LIGHTLY
             use core::ptr::read;
@@ -1,4 +1,4 @@
-use super::{Entity, GameColors, Vec2};
+use super::{CellAges, GameColors, State, Vec2, Vec2Int, World};
 
 pub struct RendererCommands<'a> {
     pub game_world_draw_commands: Option<GameWorldDrawCommands<'a>>,
@@ -6,11 +6,18 @@ pub struct RendererCommands<'a> {
 }
 
 pub struct GameWorldDrawCommands<'a> {
-    pub entities: &'a mut [Vec<Entity>],
+    pub world: &'a World,
     pub game_colors: &'a GameColors,
+    pub ages: &'a CellAges,
     pub camera_position: &'a Vec2,
     pub camera_scale: f32,
     pub aspect_ratio: f32,
+    /// The size of the window in pixels, for working out how big a cell is on screen.
+    pub viewport_size: Vec2,
+    /// A prefab waiting to be placed, as its corner and its cells.
+    pub placement_preview: Option<(Vec2Int, &'a [Vec<State>])>,
+    /// The selected rectangle, as its smallest and largest corners.
+    pub selection: Option<(Vec2Int, Vec2Int)>,
 }
 
 pub struct ImGuiDrawCommands<'a> {
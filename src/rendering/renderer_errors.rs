@@ -16,6 +16,7 @@ pub enum DrawingError {
     ResetFence,
     PresentIntoSwapchain,
     BufferCreationError,
+    MissingTexture,
 }
 
 impl std::fmt::Display for DrawingError {
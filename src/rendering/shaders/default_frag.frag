@@ -1,14 +1,13 @@
 #version 450
 layout(push_constant) uniform FragPushConsts {
-    layout(offset = 32) vec3 color;
+    layout(offset = 32) vec3 gridline_color;
     bool gridline_draw;
     float gridline_size;
-    vec3 gridline_color;
 }
 push;
 
 layout(location = 1) in vec2 uv;
-layout(location = 2) in vec3 frag_color;
+layout(location = 2) in vec4 frag_color;
 
 layout(location = 0) out vec4 color;
 
@@ -18,8 +17,8 @@ void main() {
 
     if (push.gridline_draw &&
         (uv.x < MIN || uv.x > MAX || uv.y < MIN || uv.y > MAX)) {
-        color = vec4(push.gridline_color.r, push.gridline_color.g, push.gridline_color.b, 1.0);
+        color = vec4(push.gridline_color.r, push.gridline_color.g, push.gridline_color.b, frag_color.a);
     } else {
-        color = vec4(push.color, 1.0);
+        color = frag_color;
     }
 }
\ No newline at end of file
@@ -1,3 +1,7 @@
+mod paths;
+mod preferences;
 mod sound_files;
 
-pub use sound_files::*;
\ No newline at end of file
+pub use paths::*;
+pub use preferences::*;
+pub use sound_files::*;
@@ -0,0 +1,26 @@
+use std::env;
+use std::path::PathBuf;
+
+/// The prefabs which ship with the game.
+pub const PREFAB_DIRECTORY: &str = "resources/prefabs";
+
+/// Where we keep per-user files, following each platform's convention. Returns `None`
+/// if the environment doesn't tell us where the user's home is.
+pub fn user_config_directory() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    base.map(|base| base.join("game_of_life"))
+}
+
+/// Prefabs the user has added themselves, which sit alongside the bundled ones.
+pub fn user_prefab_directory() -> Option<PathBuf> {
+    user_config_directory().map(|config| config.join("prefabs"))
+}
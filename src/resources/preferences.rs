@@ -1,3 +1,297 @@
+use super::user_config_directory;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Bumped whenever a setting is renamed or changes meaning, with a step added to `migrate`
+/// so files written before still load.
+pub const PREFERENCES_VERSION: u32 = 1;
+
+/// Kept in the user's config directory, next to their prefabs.
+const PREFERENCES_FILE: &str = "preferences.json";
+
+/// How long settings have to stay put before they're written out, in seconds, so dragging a
+/// slider doesn't write the file every frame.
+const SAVE_DELAY: f32 = 0.5;
+
+/// The settings which carry over from one launch to the next.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Preferences {
-    
-}
\ No newline at end of file
+    /// The layout the file was written with.
+    pub version: u32,
+    pub colors: ColorPreferences,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub increment_rate: f32,
+    /// What lies past the edges, by the short name `Topology` displays as.
+    pub topology: String,
+    pub panels: PanelPreferences,
+    pub show_instructions: bool,
+    /// Whether the windows are shown at all, toggled with F1.
+    pub show_ui: bool,
+    /// Whether the debug window is shown, toggled with F10.
+    pub show_debug: bool,
+}
+
+/// Every color is linear, as `Color` keeps them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorPreferences {
+    pub alive: [f32; 3],
+    pub dead: [f32; 3],
+    pub unborn: [f32; 3],
+    pub bg: [f32; 3],
+    pub grid_lines: bool,
+    pub grid_line_width: f32,
+    pub grid_line_color: [f32; 3],
+    pub selection: [f32; 3],
+    pub age_coloring: bool,
+    pub old_alive: [f32; 3],
+    pub long_dead: [f32; 3],
+    pub age_span: f32,
+}
+
+/// Which windows are open.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PanelPreferences {
+    pub play_control: bool,
+    pub prefab_library: bool,
+    pub settings_control: bool,
+    pub statistics: bool,
+    pub census: bool,
+    pub soup: bool,
+    pub search: bool,
+    pub minimap: bool,
+}
+
+impl Preferences {
+    /// Where preferences are kept, or `None` if we can't tell where the user's config goes.
+    pub fn path() -> Option<PathBuf> {
+        user_config_directory().map(|config| config.join(PREFERENCES_FILE))
+    }
+
+    /// Reads the preferences file over the top of `defaults`. Anything the file doesn't
+    /// mention, because it was written before the setting existed, keeps its default, and
+    /// anything it has which we don't know about is ignored. With no file yet, this is
+    /// just `defaults`.
+    pub fn load(defaults: &Preferences) -> Result<Preferences, PreferencesError> {
+        let path = Self::path().ok_or(PreferencesError::NoConfigDirectory)?;
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::read(&text, defaults),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(defaults.clone()),
+            Err(e) => Err(PreferencesError::ReadFromDisk(e)),
+        }
+    }
+
+    /// Reads the text of a preferences file over the top of `defaults`, as `load` does.
+    fn read(text: &str, defaults: &Preferences) -> Result<Preferences, PreferencesError> {
+        let mut file: Map<String, Value> =
+            serde_json::from_str(text).map_err(|e| PreferencesError::Deserialize(e))?;
+        let version = file.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+        if version > PREFERENCES_VERSION {
+            warn!(
+                "The preferences were written by a newer version (v{}), reading what we can.",
+                version
+            );
+        }
+        migrate(&mut file, version);
+        file.insert("version".to_owned(), Value::from(PREFERENCES_VERSION));
+
+        let mut preferences = serde_json::to_value(defaults).map_err(|e| PreferencesError::Serialize(e))?;
+        merge(&mut preferences, Value::Object(file));
+        serde_json::from_value(preferences).map_err(|e| PreferencesError::Deserialize(e))
+    }
+
+    pub fn save(&self) -> Result<(), PreferencesError> {
+        let path = Self::path().ok_or(PreferencesError::NoConfigDirectory)?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|e| PreferencesError::WriteToDisk(e))?;
+        }
+
+        let text = serde_json::to_string_pretty(self).map_err(|e| PreferencesError::Serialize(e))?;
+        fs::write(&path, text).map_err(|e| PreferencesError::WriteToDisk(e))
+    }
+}
+
+/// Brings a file written with an older `version` up to the current layout, one version at
+/// a time.
+fn migrate(file: &mut Map<String, Value>, version: u32) {
+    // Before files were versioned, the edges either wrapped round or didn't, as `wrap_grid`.
+    if version < 1 {
+        if let Some(wrap_grid) = file.remove("wrap_grid").and_then(|wrap| wrap.as_bool()) {
+            let topology = if wrap_grid { "torus" } else { "dead" };
+            file.insert("topology".to_owned(), Value::from(topology));
+        }
+    }
+}
+
+/// Copies everything in `from` over `into`, going into objects rather than replacing them
+/// whole, and skipping values of a different kind to the one already there.
+fn merge(into: &mut Value, from: Value) {
+    match (into, from) {
+        (Value::Object(into), Value::Object(from)) => {
+            for (key, value) in from {
+                if let Some(existing) = into.get_mut(&key) {
+                    merge(existing, value);
+                }
+            }
+        }
+        (into, from) => {
+            if same_kind(into, &from) {
+                *into = from;
+            }
+        }
+    }
+}
+
+fn same_kind(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Bool(_), Value::Bool(_))
+        | (Value::Number(_), Value::Number(_))
+        | (Value::String(_), Value::String(_))
+        | (Value::Array(_), Value::Array(_)) => true,
+        _ => false,
+    }
+}
+
+/// Keeps the preferences file in step with the game's settings. Changes are written out
+/// once they've stopped changing for `SAVE_DELAY`, or straight away on `flush`.
+#[derive(Debug)]
+pub struct PreferencesWriter {
+    saved: Preferences,
+    unsaved_for: Option<f32>,
+}
+
+/// Whatever hasn't been written yet is written on the way out, however the game ends.
+impl Drop for PreferencesWriter {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl PreferencesWriter {
+    /// `saved` is what the file already holds, so there's nothing to write until it changes.
+    pub fn new(saved: Preferences) -> Self {
+        PreferencesWriter {
+            saved,
+            unsaved_for: None,
+        }
+    }
+
+    pub fn update(&mut self, current: Preferences, delta_time: f32) {
+        if current != self.saved {
+            self.saved = current;
+            self.unsaved_for = Some(0.0);
+            return;
+        }
+
+        let unsaved_for = self.unsaved_for.map(|unsaved_for| unsaved_for + delta_time);
+        self.unsaved_for = unsaved_for;
+        if unsaved_for.map_or(false, |unsaved_for| unsaved_for >= SAVE_DELAY) {
+            self.flush();
+        }
+    }
+
+    /// Writes any changes which are still waiting, logging if that fails.
+    pub fn flush(&mut self) {
+        if self.unsaved_for.take().is_none() {
+            return;
+        }
+
+        if let Err(e) = self.saved.save() {
+            error!("Couldn't save preferences => {}", e);
+        }
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum PreferencesError {
+    #[fail(display = "Couldn't find the user's config directory.")]
+    NoConfigDirectory,
+
+    #[fail(display = "Could not serialize.")]
+    Serialize(#[fail(cause)] serde_json::error::Error),
+
+    #[fail(display = "Could not deserialize.")]
+    Deserialize(#[fail(cause)] serde_json::error::Error),
+
+    #[fail(display = "Could not Write to Disk.")]
+    WriteToDisk(#[fail(cause)] io::Error),
+
+    #[fail(display = "Could not Read from Disk")]
+    ReadFromDisk(#[fail(cause)] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> Preferences {
+        Preferences {
+            version: PREFERENCES_VERSION,
+            colors: ColorPreferences {
+                alive: [1.0, 1.0, 1.0],
+                dead: [0.5, 0.5, 0.5],
+                unborn: [0.0, 0.0, 0.0],
+                bg: [0.1, 0.1, 0.1],
+                grid_lines: true,
+                grid_line_width: 1.0,
+                grid_line_color: [0.2, 0.2, 0.2],
+                selection: [0.0, 0.5, 1.0],
+                age_coloring: false,
+                old_alive: [1.0, 0.5, 0.0],
+                long_dead: [0.2, 0.0, 0.2],
+                age_span: 100.0,
+            },
+            sfx_volume: 0.5,
+            music_volume: 0.5,
+            increment_rate: 1.0,
+            topology: "dead".to_owned(),
+            panels: PanelPreferences {
+                play_control: true,
+                prefab_library: true,
+                settings_control: true,
+                statistics: false,
+                census: false,
+                soup: false,
+                search: false,
+                minimap: false,
+            },
+            show_instructions: true,
+            show_ui: true,
+            show_debug: true,
+        }
+    }
+
+    #[test]
+    fn settings_the_file_leaves_out_keep_their_defaults() {
+        let text = r#"{ "version": 1, "sfx_volume": 0.25, "panels": { "soup": true } }"#;
+        let preferences = Preferences::read(text, &defaults()).unwrap();
+
+        let mut expected = defaults();
+        expected.sfx_volume = 0.25;
+        expected.panels.soup = true;
+        assert_eq!(preferences, expected);
+    }
+
+    #[test]
+    fn windows_being_hidden_is_kept() {
+        let text = r#"{ "version": 1, "show_ui": false, "show_debug": false }"#;
+        let preferences = Preferences::read(text, &defaults()).unwrap();
+        assert_eq!((preferences.show_ui, preferences.show_debug), (false, false));
+
+        let saved = serde_json::to_string(&preferences).unwrap();
+        assert_eq!(Preferences::read(&saved, &defaults()).unwrap(), preferences);
+    }
+
+    #[test]
+    fn older_files_are_migrated_and_mistyped_settings_ignored() {
+        let text = r#"{ "wrap_grid": true, "music_volume": "loud", "show_ui": 0 }"#;
+        let preferences = Preferences::read(text, &defaults()).unwrap();
+
+        let mut expected = defaults();
+        expected.topology = "torus".to_owned();
+        assert_eq!(preferences, expected);
+    }
+}